@@ -0,0 +1,9 @@
+-- TABLE: Reminder_States
+-- Delivery state of reminders, i.e. note dates whose label marks them as reminders.
+-- A reminder without a row here hasn't been notified, snoozed or acknowledged yet.
+CREATE TABLE reminder_states (
+    note_date_id UUID PRIMARY KEY REFERENCES note_dates(id) ON DELETE CASCADE,  -- The reminding date
+    notified_at TIMESTAMP,                                                      -- Last time clients were notified
+    snoozed_until TIMESTAMP,                                                    -- Not due again before then
+    acknowledged_at TIMESTAMP                                                   -- Done with, never due again
+);
@@ -0,0 +1,12 @@
+-- Recurrence rules on note dates, a subset of iCalendar's RRULE (e.g. 'FREQ=MONTHLY;BYDAY=1MO').
+-- A date with a rule is the first date the rule applies from, without one it happens once.
+ALTER TABLE note_dates ADD COLUMN rrule TEXT;
+
+-- A recurring reminder comes due on every occurrence, so its state is kept per occurrence.
+-- States from before were about the note date itself, its first occurrence.
+ALTER TABLE reminder_states ADD COLUMN occurrence DATE;
+UPDATE reminder_states rs SET occurrence = nd.date FROM note_dates nd WHERE nd.id = rs.note_date_id;
+ALTER TABLE reminder_states
+    ALTER COLUMN occurrence SET NOT NULL,
+    DROP CONSTRAINT reminder_states_pkey,
+    ADD PRIMARY KEY (note_date_id, occurrence);
@@ -0,0 +1,15 @@
+-- Timestamps become TIMESTAMPTZ, so notes written from machines in different zones sort together.
+-- Rows defaulting to CURRENT_TIMESTAMP were stored in the server's local time, which is read from
+-- the `rember.source_timezone` setting and taken as UTC if it's unset, e.g. run
+--   ALTER DATABASE rember SET rember.source_timezone = 'Europe/Vienna';
+-- before migrating. Reminder states were always written in UTC.
+ALTER TABLE notes ALTER COLUMN created_at TYPE TIMESTAMPTZ
+    USING created_at AT TIME ZONE COALESCE(NULLIF(current_setting('rember.source_timezone', true), ''), 'UTC');
+
+ALTER TABLE note_links ALTER COLUMN created_at TYPE TIMESTAMPTZ
+    USING created_at AT TIME ZONE COALESCE(NULLIF(current_setting('rember.source_timezone', true), ''), 'UTC');
+
+ALTER TABLE reminder_states
+    ALTER COLUMN notified_at TYPE TIMESTAMPTZ USING notified_at AT TIME ZONE 'UTC',
+    ALTER COLUMN snoozed_until TYPE TIMESTAMPTZ USING snoozed_until AT TIME ZONE 'UTC',
+    ALTER COLUMN acknowledged_at TYPE TIMESTAMPTZ USING acknowledged_at AT TIME ZONE 'UTC';
@@ -0,0 +1,3 @@
+-- Optional note titles. Notes without one are shown by a title derived from their text, and
+-- search weighs titles, given or derived from the first line, on top of the text.
+ALTER TABLE notes ADD COLUMN title TEXT;
@@ -0,0 +1,56 @@
+-- SQLite version of migrations/20250808072001_init.sql
+-- UUIDs are generated by the application and stored as blobs, timestamps as RFC 3339 text with their offset.
+
+-- TABLE: Notes
+CREATE TABLE notes (
+    id BLOB PRIMARY KEY NOT NULL,                            -- Unique identifier for the note
+    text TEXT NOT NULL,                                      -- Main body of the note
+    created_at TEXT NOT NULL                                 -- When the note was created
+);
+
+-- Full text index standing in for pg_trgm's GIN index.
+-- Each row holds the note's pg_trgm-style trigrams as tokens (padding spaces written as '_'),
+-- so a MATCH finds exactly the notes sharing at least one trigram with the search text.
+-- It's maintained by the application, since SQL can't compute the trigrams.
+CREATE VIRTUAL TABLE notes_trigrams USING fts5(
+    trigrams,
+    tokenize = "unicode61 remove_diacritics 0 tokenchars '_'"
+);
+
+CREATE TRIGGER notes_trigrams_delete AFTER DELETE ON notes BEGIN
+    DELETE FROM notes_trigrams WHERE rowid = old.rowid;
+END;
+
+
+-- TABLE: Tags
+CREATE TABLE tags (
+    id BLOB PRIMARY KEY NOT NULL,                            -- Unique identifier for the tag
+    name TEXT UNIQUE NOT NULL                                -- Tag name (e.g. 'idea', 'project', 'dream')
+);
+
+
+-- TABLE: Note_Tags
+CREATE TABLE note_tags (
+    note_id BLOB NOT NULL REFERENCES notes(id) ON DELETE CASCADE,  -- Linked note
+    tag_id BLOB NOT NULL REFERENCES tags(id) ON DELETE CASCADE,    -- Linked tag
+    PRIMARY KEY (note_id, tag_id)                                  -- Ensures each pair is unique
+);
+
+
+-- TABLE: Note_Dates
+CREATE TABLE note_dates (
+    id BLOB PRIMARY KEY NOT NULL,                                    -- Unique date reference
+    note_id BLOB NOT NULL REFERENCES notes(id) ON DELETE CASCADE,    -- Associated note
+    label TEXT,                                                      -- Optional label (e.g., 'event', 'deadline', 'reminder')
+    date TEXT NOT NULL                                               -- The actual date being referenced
+);
+
+
+-- TABLE: Note_Links
+CREATE TABLE note_links (
+    note1_id BLOB NOT NULL REFERENCES notes(id) ON DELETE CASCADE,  -- One note in the pair
+    note2_id BLOB NOT NULL REFERENCES notes(id) ON DELETE CASCADE,  -- The other note in the pair
+    created_at TEXT,                                                -- When the link was created
+    PRIMARY KEY (note1_id, note2_id),                               -- Enforce uniqueness of each link
+    CHECK (note1_id < note2_id)                                     -- Prevents (A,B) and (B,A) duplicates
+);
@@ -0,0 +1,9 @@
+-- SQLite version of migrations/20261019090000_reminder_states.sql
+
+-- TABLE: Reminder_States
+CREATE TABLE reminder_states (
+    note_date_id BLOB PRIMARY KEY NOT NULL REFERENCES note_dates(id) ON DELETE CASCADE,  -- The reminding date
+    notified_at TEXT,                                                                    -- Last time clients were notified
+    snoozed_until TEXT,                                                                  -- Not due again before then
+    acknowledged_at TEXT                                                                 -- Done with, never due again
+);
@@ -0,0 +1,17 @@
+-- SQLite version of migrations/20261019100000_note_date_rrules.sql
+ALTER TABLE note_dates ADD COLUMN rrule TEXT;
+
+-- SQLite can't change a primary key, so the reminder states move to a new table keyed by occurrence
+CREATE TABLE reminder_states_by_occurrence (
+    note_date_id BLOB NOT NULL REFERENCES note_dates(id) ON DELETE CASCADE,  -- The reminding date
+    occurrence TEXT NOT NULL,                                                -- Which of its occurrences
+    notified_at TEXT,                                                        -- Last time clients were notified
+    snoozed_until TEXT,                                                      -- Not due again before then
+    acknowledged_at TEXT,                                                    -- Done with, never due again
+    PRIMARY KEY (note_date_id, occurrence)
+);
+INSERT INTO reminder_states_by_occurrence
+SELECT rs.note_date_id, nd.date, rs.notified_at, rs.snoozed_until, rs.acknowledged_at
+FROM reminder_states rs JOIN note_dates nd ON nd.id = rs.note_date_id;
+DROP TABLE reminder_states;
+ALTER TABLE reminder_states_by_occurrence RENAME TO reminder_states;
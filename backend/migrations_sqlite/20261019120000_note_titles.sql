@@ -0,0 +1,2 @@
+-- SQLite version of migrations/20261019120000_note_titles.sql
+ALTER TABLE notes ADD COLUMN title TEXT;
@@ -1,6 +1,11 @@
 -- PARAMETERS
 -- $1: search_text          -> The text for fuzzy searching (e.g., 'databas desin').
 -- $2: search_tags          -> An array of tag names to match (e.g., ARRAY['project', 'idea']).
+-- $3: limit                -> Maximum number of notes to return (NULL for no limit).
+-- $4: offset               -> Number of notes to skip (NULL for none).
+-- $5: note_ids             -> Only consider these notes (NULL for all of them).
+-- $6: created_from         -> Only notes created at or after this (NULL for no bound).
+-- $7: created_until        -> Only notes created before this (NULL for no bound).
 
 WITH notes_with_tags AS (
     SELECT
@@ -14,16 +19,30 @@ WITH notes_with_tags AS (
 SELECT
     n.id,
     n.text,
+    n.title,
     n.created_at
 FROM
     notes AS n
-JOIN
+-- What search weighs as the title: the given one, else the first line without heading marks
+CROSS JOIN LATERAL (
+    SELECT COALESCE(n.title, split_part(ltrim(n.text, E' \t\r\n#'), E'\n', 1)) AS title
+) AS search
+LEFT JOIN
     notes_with_tags nwt ON n.id = nwt.note_id
 WHERE
-    -- 1. Check if the note's tag array contains all the search tags
-    nwt.tags @> $2
-    -- 2. Perform the fuzzy text search only if search text is not empty
-    AND ($1 = '' OR n.text % $1)
+    -- 1. Check if the note's tag array contains all the search tags (untagged notes have none)
+    COALESCE(nwt.tags, '{}') @> $2
+    -- 2. Perform the fuzzy search on text and title only if search text is not empty
+    AND ($1 = '' OR n.text % $1 OR search.title % $1)
+    -- 3. Restrict to the given notes, if any
+    AND ($5::uuid[] IS NULL OR n.id = ANY($5))
+    -- 4. Restrict to notes created within the range, if any
+    AND ($6::timestamptz IS NULL OR n.created_at >= $6)
+    AND ($7::timestamptz IS NULL OR n.created_at < $7)
 ORDER BY
-    -- 3. Sort by similarity score (use 0 if no search text)
-    CASE WHEN $1 = '' THEN 0 ELSE similarity(n.text, $1) END DESC;
+    -- 5. Sort by similarity score, where a title match adds to the text's (use 0 if no search
+    --    text), then oldest first for stable pages
+    CASE WHEN $1 = '' THEN 0 ELSE similarity(n.text, $1) + similarity(search.title, $1) END DESC,
+    n.created_at
+LIMIT $3
+OFFSET $4;
@@ -0,0 +1,430 @@
+//! Lossless backups as JSON Lines: a header line carrying the schema version, then one line per
+//! table row, each tagged with its `kind`
+//!
+//! ```json
+//! {"kind":"header","schema_version":1,"created_at":"2025-08-08T07:20:01.123456Z"}
+//! {"kind":"note","id":"0198a2c4-...","text":"...","created_at":"2025-08-08T07:20:01.123456Z"}
+//! {"kind":"tag","id":"0198a2c4-...","name":"idea"}
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    engine::{
+        core::Engine,
+        database::{
+            NoteStore, error,
+            notes::{Note, NoteDate},
+            snapshot::{NoteLink, NoteTag, ReminderState, Snapshot},
+            tags::Tag,
+        },
+    },
+    env_or, env_or_valid,
+};
+
+/// Bumped whenever the record layout changes, restores refuse backups from newer versions
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Record {
+    Header {
+        schema_version: u32,
+        created_at: DateTime<Utc>,
+    },
+    Note(Note),
+    Tag(Tag),
+    NoteTag(NoteTag),
+    NoteDate(NoteDate),
+    NoteLink(NoteLink),
+    ReminderState(ReminderState),
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Json {
+        line: usize,
+        error: serde_json::Error,
+    },
+    MissingHeader,
+    UnsupportedVersion(u32),
+    /// Notes of the backup that already exist, under [`ConflictPolicy::Fail`]
+    Conflict(Vec<Uuid>),
+    Store(error::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Json { line, error } => write!(f, "invalid record on line {line}: {error}"),
+            Self::MissingHeader => write!(f, "not a rember backup, the header line is missing"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "backup has schema version {version}, this build only reads up to {SCHEMA_VERSION}"
+            ),
+            Self::Conflict(ids) => write!(
+                f,
+                "{} notes of the backup already exist, pick a conflict policy to restore anyway",
+                ids.len()
+            ),
+            Self::Store(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<error::Error> for Error {
+    fn from(e: error::Error) -> Self {
+        Self::Store(e)
+    }
+}
+
+/// What to do with notes of the backup whose id already exists
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ConflictPolicy {
+    /// Restore nothing if any note exists already
+    #[default]
+    Fail,
+    /// Keep the existing notes as they are
+    Skip,
+    /// Replace the existing notes, with their tags and dates, by the backup's
+    Overwrite,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(Self::Fail),
+            "skip" => Ok(Self::Skip),
+            "overwrite" => Ok(Self::Overwrite),
+            _ => Err(format!(
+                "unknown conflict policy {s}, expected fail, skip or overwrite"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct RestoreReport {
+    pub created: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
+    pub tags_created: usize,
+    pub links: usize,
+}
+
+pub fn write_backup(snapshot: &Snapshot, mut writer: impl Write) -> io::Result<()> {
+    let header = Record::Header {
+        schema_version: SCHEMA_VERSION,
+        created_at: Utc::now(),
+    };
+
+    let records = std::iter::once(header)
+        .chain(snapshot.notes.iter().cloned().map(Record::Note))
+        .chain(snapshot.tags.iter().cloned().map(Record::Tag))
+        .chain(snapshot.note_tags.iter().cloned().map(Record::NoteTag))
+        .chain(snapshot.note_dates.iter().cloned().map(Record::NoteDate))
+        .chain(snapshot.note_links.iter().cloned().map(Record::NoteLink))
+        .chain(
+            snapshot
+                .reminder_states
+                .iter()
+                .cloned()
+                .map(Record::ReminderState),
+        );
+
+    for record in records {
+        serde_json::to_writer(&mut writer, &record)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+pub fn read_backup(reader: impl BufRead) -> Result<Snapshot, Error> {
+    let mut snapshot = Snapshot::default();
+    let mut has_header = false;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let record = serde_json::from_str(&line).map_err(|error| {
+            // anything but a header on the first line means this isn't a backup at all
+            if has_header {
+                Error::Json { line: i + 1, error }
+            } else {
+                Error::MissingHeader
+            }
+        })?;
+
+        match record {
+            Record::Header { schema_version, .. } if !has_header => {
+                if schema_version > SCHEMA_VERSION {
+                    return Err(Error::UnsupportedVersion(schema_version));
+                }
+                has_header = true;
+            }
+            _ if !has_header => return Err(Error::MissingHeader),
+            Record::Header { .. } => {
+                return Err(Error::Json {
+                    line: i + 1,
+                    error: serde::de::Error::custom("a backup has a single header"),
+                });
+            }
+            Record::Note(note) => snapshot.notes.push(note),
+            Record::Tag(tag) => snapshot.tags.push(tag),
+            Record::NoteTag(note_tag) => snapshot.note_tags.push(note_tag),
+            Record::NoteDate(note_date) => snapshot.note_dates.push(note_date),
+            Record::NoteLink(note_link) => snapshot.note_links.push(note_link),
+            Record::ReminderState(reminder_state) => snapshot.reminder_states.push(reminder_state),
+        }
+    }
+
+    if !has_header {
+        return Err(Error::MissingHeader);
+    }
+    Ok(snapshot)
+}
+
+/// Restores a backup into a store that may already hold notes
+///
+/// Tags are matched by name, so a backup's `idea` tag lands on an existing `idea` tag whatever
+/// its id. Links are restored when both of their notes exist afterwards.
+pub async fn restore(
+    store: &dyn NoteStore,
+    backup: &Snapshot,
+    policy: ConflictPolicy,
+) -> Result<RestoreReport, Error> {
+    let current = store.snapshot().await?;
+    let existing = current
+        .notes
+        .iter()
+        .map(|note| note.id)
+        .collect::<HashSet<_>>();
+
+    let conflicts = backup
+        .notes
+        .iter()
+        .map(|note| note.id)
+        .filter(|id| existing.contains(id))
+        .collect::<Vec<_>>();
+    if policy == ConflictPolicy::Fail && !conflicts.is_empty() {
+        return Err(Error::Conflict(conflicts));
+    }
+
+    let mut report = RestoreReport::default();
+    let notes = backup
+        .notes
+        .iter()
+        .filter(|note| {
+            let exists = existing.contains(&note.id);
+            match (exists, policy) {
+                (true, ConflictPolicy::Skip) => report.skipped += 1,
+                (true, _) => report.overwritten += 1,
+                (false, _) => report.created += 1,
+            }
+            !exists || policy != ConflictPolicy::Skip
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    let restored = notes.iter().map(|note| note.id).collect::<HashSet<_>>();
+
+    let existing_tags = current
+        .tags
+        .iter()
+        .map(|tag| (tag.name.as_str(), tag.id))
+        .collect::<HashMap<_, _>>();
+    let taken_ids = existing_tags.values().copied().collect::<HashSet<_>>();
+    let mut tag_ids = HashMap::new();
+    let mut tags = Vec::new();
+    for tag in &backup.tags {
+        match existing_tags.get(tag.name.as_str()) {
+            Some(&id) => {
+                tag_ids.insert(tag.id, id);
+            }
+            None => {
+                // the id may belong to a different tag here, which mustn't be renamed
+                let id = if taken_ids.contains(&tag.id) {
+                    Uuid::new_v4()
+                } else {
+                    tag.id
+                };
+                tag_ids.insert(tag.id, id);
+                tags.push(Tag {
+                    id,
+                    name: tag.name.clone(),
+                });
+            }
+        }
+    }
+    report.tags_created = tags.len();
+
+    let note_tags = backup
+        .note_tags
+        .iter()
+        .filter(|note_tag| restored.contains(&note_tag.note_id))
+        .filter_map(|note_tag| {
+            Some(NoteTag {
+                note_id: note_tag.note_id,
+                tag_id: *tag_ids.get(&note_tag.tag_id)?,
+            })
+        })
+        .collect();
+
+    let note_dates = backup
+        .note_dates
+        .iter()
+        .filter(|note_date| restored.contains(&note_date.note_id))
+        .cloned()
+        .collect::<Vec<_>>();
+    let reminder_states = backup
+        .reminder_states
+        .iter()
+        .filter(|reminder_state| {
+            note_dates
+                .iter()
+                .any(|note_date| note_date.id == reminder_state.note_date_id)
+        })
+        .cloned()
+        .collect();
+
+    // a link needs both notes, and at least one of them must be restored to be part of this
+    let note_links = backup
+        .note_links
+        .iter()
+        .filter(|link| {
+            let ends = [link.note1_id, link.note2_id];
+            ends.iter()
+                .all(|id| restored.contains(id) || existing.contains(id))
+                && ends.iter().any(|id| restored.contains(id))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    report.links = note_links.len();
+
+    store
+        .restore_snapshot(&Snapshot {
+            notes,
+            tags,
+            note_tags,
+            note_dates,
+            note_links,
+            reminder_states,
+        })
+        .await?;
+
+    Ok(report)
+}
+
+/// Periodic backups into a directory, keeping only the most recent ones
+#[derive(Debug, Clone)]
+pub struct BackupSchedule {
+    pub dir: PathBuf,
+    pub interval: Duration,
+    /// How many backups to keep, older ones are deleted
+    pub keep: usize,
+}
+
+impl BackupSchedule {
+    /// Reads `BACKUP_DIR`, `BACKUP_INTERVAL_SECS` (daily by default) and `BACKUP_KEEP`
+    /// (7 by default), scheduled backups are off unless `BACKUP_DIR` is set
+    pub fn from_env() -> Option<Self> {
+        let dir = std::env::var("BACKUP_DIR").ok()?;
+        Some(Self {
+            dir: dir.into(),
+            interval: Duration::from_secs(env_or_valid(
+                "BACKUP_INTERVAL_SECS",
+                24 * 60 * 60,
+                |&secs| secs > 0,
+            )),
+            keep: env_or("BACKUP_KEEP", 7),
+        })
+    }
+
+    /// Takes a backup every interval, starting one interval from now
+    pub async fn run(self, engine: Engine) {
+        let mut interval =
+            tokio::time::interval_at(tokio::time::Instant::now() + self.interval, self.interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            match self.backup(engine.store()).await {
+                Ok(path) => info!("wrote backup {}", path.display()),
+                Err(e) => warn!("scheduled backup failed: {e}"),
+            }
+        }
+    }
+
+    /// Writes a new backup and prunes the old ones, returning the new file's path
+    pub async fn backup(&self, store: &dyn NoteStore) -> Result<PathBuf, Error> {
+        let snapshot = store.snapshot().await?;
+        let schedule = self.clone();
+
+        tokio::task::spawn_blocking(move || {
+            fs::create_dir_all(&schedule.dir)?;
+            // timestamps in the name sort the same as the backups' ages
+            let name = format!("rember-{}.jsonl", Utc::now().format("%Y%m%dT%H%M%S%.3f"));
+            let path = schedule.dir.join(name);
+
+            // written under a temporary name, so a crash never leaves a truncated backup behind
+            let partial = path.with_extension("jsonl.partial");
+            write_backup(&snapshot, io::BufWriter::new(fs::File::create(&partial)?))?;
+            fs::rename(&partial, &path)?;
+
+            schedule.prune()?;
+            Ok(path)
+        })
+        .await
+        .expect("backup task panicked")
+    }
+
+    fn prune(&self) -> io::Result<()> {
+        let mut backups = backup_files(&self.dir)?;
+        backups.sort();
+
+        let excess = backups.len().saturating_sub(self.keep);
+        for path in &backups[..excess] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+fn backup_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with("rember-") && name.ends_with(".jsonl") {
+            paths.push(dir.join(&*name));
+        }
+    }
+    Ok(paths)
+}
@@ -0,0 +1,205 @@
+//! Just enough YAML for note front matter: scalars, flow and block lists, and lists of flat maps
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Scalar(String),
+    List(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Scalar(scalar) => Some(scalar),
+            _ => None,
+        }
+    }
+
+    /// Items of a list, where a lone scalar counts as a list of one (`tags: idea`)
+    pub fn items(&self) -> Vec<&Value> {
+        match self {
+            Self::List(items) => items.iter().collect(),
+            Self::Scalar(scalar) if scalar.is_empty() => Vec::new(),
+            value => vec![value],
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Self::Map(entries) => get(entries, key),
+            _ => None,
+        }
+    }
+}
+
+pub fn get<'a>(entries: &'a [(String, Value)], key: &str) -> Option<&'a Value> {
+    entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+}
+
+/// Splits `---` delimited front matter off the start of a file
+/// Example: "---\nid: 1\n---\n\nbody" -> ("id: 1\n", "\nbody")
+pub fn split(content: &str) -> Option<(&str, &str)> {
+    let rest = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))?;
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+
+    None
+}
+
+/// Double quotes a string the way both JSON and YAML read it back
+pub fn quote(text: &str) -> String {
+    serde_json::to_string(text).expect("failed to quote string")
+}
+
+pub fn parse(front_matter: &str) -> Result<Vec<(String, Value)>, String> {
+    let lines = front_matter
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .collect::<Vec<_>>();
+
+    let mut entries = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if indent(line) > 0 {
+            return Err(format!("unexpected indentation: {line}"));
+        }
+
+        let (key, rest) =
+            split_key(line).ok_or_else(|| format!("expected `key: value`: {line}"))?;
+        i += 1;
+
+        if !rest.is_empty() {
+            entries.push((key, parse_inline(rest)?));
+            continue;
+        }
+
+        // a block value is everything indented below the key
+        let start = i;
+        while i < lines.len() && (indent(lines[i]) > 0 || lines[i].starts_with("- ")) {
+            i += 1;
+        }
+        entries.push((key, parse_block(&lines[start..i])?));
+    }
+
+    Ok(entries)
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Splits `key: value` or a bare `key:`, where the colon must be followed by a space like in YAML
+fn split_key(line: &str) -> Option<(String, &str)> {
+    let line = line.trim();
+    let (key, rest) = match line.split_once(": ") {
+        Some((key, rest)) => (key, rest),
+        None => (line.strip_suffix(':')?, ""),
+    };
+    Some((key.trim().to_string(), rest.trim()))
+}
+
+fn parse_block(lines: &[&str]) -> Result<Value, String> {
+    if lines.is_empty() {
+        return Ok(Value::Scalar(String::new()));
+    }
+
+    let mut items = Vec::new();
+    let item_indent = indent(lines[0]);
+    for line in lines {
+        let trimmed = line.trim();
+
+        if let Some(item) = trimmed.strip_prefix('-') {
+            if indent(line) != item_indent {
+                return Err(format!("unexpected indentation: {line}"));
+            }
+
+            let item = item.trim();
+            items.push(match split_key(item) {
+                // `- label: deadline` starts a map
+                Some((key, rest)) if !item.starts_with(['"', '\'', '[']) => {
+                    Value::Map(vec![(key, parse_inline(rest)?)])
+                }
+                _ => parse_inline(item)?,
+            });
+        } else if let Some(Value::Map(entries)) = items.last_mut()
+            && indent(line) > item_indent
+        {
+            // continuation of the map started by the last item
+            let (key, rest) =
+                split_key(trimmed).ok_or_else(|| format!("expected `key: value`: {line}"))?;
+            entries.push((key, parse_inline(rest)?));
+        } else {
+            return Err(format!("expected a list item: {line}"));
+        }
+    }
+
+    Ok(Value::List(items))
+}
+
+fn parse_inline(text: &str) -> Result<Value, String> {
+    let text = text.trim();
+
+    if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        return split_flow_list(inner)?
+            .into_iter()
+            .filter(|item| !item.is_empty())
+            .map(parse_inline)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::List);
+    }
+
+    parse_scalar(text).map(Value::Scalar)
+}
+
+fn parse_scalar(text: &str) -> Result<String, String> {
+    if text.starts_with('"') {
+        return serde_json::from_str(text).map_err(|e| format!("invalid string {text}: {e}"));
+    }
+
+    if let Some(inner) = text.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+        return Ok(inner.replace("''", "'"));
+    }
+
+    Ok(text.to_string())
+}
+
+/// Splits the inside of `[a, "b, c"]` on commas outside of quotes
+fn split_flow_list(inner: &str) -> Result<Vec<&str>, String> {
+    let mut items = Vec::new();
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (i, c) in inner.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, ',') => {
+                items.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+        escaped = false;
+    }
+
+    if quote.is_some() {
+        return Err(format!("unterminated string in [{inner}]"));
+    }
+
+    items.push(inner[start..].trim());
+    Ok(items)
+}
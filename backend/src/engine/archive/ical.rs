@@ -0,0 +1,139 @@
+//! iCalendar (RFC 5545) export of note dates, one all-day event per date
+
+use chrono::Days;
+
+use crate::engine::database::{NoteStore, error::Result, notes::NoteWithDetails};
+
+/// Label words meaning the date repeats, e.g. a `birthday` comes back every year
+const RECURRING_LABELS: &[(&str, &str)] = &[
+    ("birthday", "FREQ=YEARLY"),
+    ("anniversary", "FREQ=YEARLY"),
+    ("yearly", "FREQ=YEARLY"),
+    ("annual", "FREQ=YEARLY"),
+    ("annually", "FREQ=YEARLY"),
+    ("monthly", "FREQ=MONTHLY"),
+    ("weekly", "FREQ=WEEKLY"),
+    ("daily", "FREQ=DAILY"),
+];
+
+/// The RRULE a label implies, if any of its words means the date repeats
+pub fn recurrence_for_label(label: &str) -> Option<&'static str> {
+    label
+        .split(|c: char| !c.is_alphanumeric())
+        .find_map(|word| {
+            RECURRING_LABELS
+                .iter()
+                .find(|(recurring, _)| word.eq_ignore_ascii_case(recurring))
+        })
+        .map(|(_, rule)| *rule)
+}
+
+/// Renders the dates of the given notes as a calendar, ready to be served as `text/calendar`
+pub fn to_ical(notes: &[NoteWithDetails], name: &str) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//rember//rember//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{}", escape(name)),
+    ];
+
+    for details in notes {
+        let note = &details.note;
+        let title = note.display_title();
+
+        for note_date in &details.note_dates {
+            let summary = match &note_date.label {
+                Some(label) => format!("{label}: {title}"),
+                None => title.to_string(),
+            };
+
+            lines.push("BEGIN:VEVENT".to_string());
+            lines.push(format!("UID:{}@rember", note_date.id));
+            lines.push(format!(
+                "DTSTAMP:{}",
+                note.created_at.format("%Y%m%dT%H%M%SZ")
+            ));
+            lines.push(format!(
+                "DTSTART;VALUE=DATE:{}",
+                note_date.date.format("%Y%m%d")
+            ));
+            if let Some(end) = note_date.date.checked_add_days(Days::new(1)) {
+                lines.push(format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")));
+            }
+            // an explicit rule wins over one guessed from the label
+            let rule = note_date
+                .rrule
+                .as_deref()
+                .or_else(|| note_date.label.as_deref().and_then(recurrence_for_label));
+            if let Some(rule) = rule {
+                lines.push(format!("RRULE:{rule}"));
+            }
+            lines.push(format!("SUMMARY:{}", escape(&summary)));
+            lines.push(format!("DESCRIPTION:{}", escape(&note.text)));
+            if !details.tags.is_empty() {
+                let tags = details
+                    .tags
+                    .iter()
+                    .map(|tag| escape(&tag.name))
+                    .collect::<Vec<_>>();
+                lines.push(format!("CATEGORIES:{}", tags.join(",")));
+            }
+            lines.push("END:VEVENT".to_string());
+        }
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+/// Dates of every note, or only of the notes carrying `tag`
+pub async fn export(store: &dyn NoteStore, tag: Option<&str>) -> Result<String> {
+    let notes = store
+        .get_all_note_details()
+        .await?
+        .into_iter()
+        .filter(|details| {
+            tag.is_none_or(|tag| details.tags.iter().any(|note_tag| note_tag.name == tag))
+        })
+        .collect::<Vec<_>>();
+
+    let name = match tag {
+        Some(tag) => format!("rember #{tag}"),
+        None => "rember".to_string(),
+    };
+    Ok(to_ical(&notes, &name))
+}
+
+/// Escapes a TEXT value
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Splits lines longer than 75 bytes, continuing them on lines starting with a space
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut length = 0;
+
+    for c in line.chars() {
+        // continuation lines lose one byte to the leading space
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
@@ -0,0 +1,422 @@
+//! One Markdown file per note, with its metadata in YAML front matter:
+//!
+//! ```markdown
+//! ---
+//! id: 0198a2c4-8f3e-7b41-9d52-3c6e1f0a7b21
+//! created_at: 2025-08-08T07:20:01.123456Z
+//! title: "Database design"
+//! tags:
+//!   - "work"
+//! dates:
+//!   - label: "deadline"
+//!     date: 2025-09-01
+//!   - label: "standup"
+//!     date: 2025-08-11
+//!     rrule: "FREQ=WEEKLY;BYDAY=MO,TH"
+//! links:
+//!   - "[[0198a2c4-9a10-7c02-8e11-5b4d2f6c9e30]]"
+//! ---
+//!
+//! the note text, exactly as stored
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::Path,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use uuid::Uuid;
+
+use rust_shared::response::{ImportReport, MarkdownFile};
+
+use super::front_matter::{self, Value};
+use crate::engine::{
+    core::{Limits, distinct_tags},
+    database::{
+        NoteStore,
+        error::Result,
+        notes::{NewNoteDate, NoteImport, NoteWithDetails, UpsertOutcome, link_key},
+    },
+    recurrence::Recurrence,
+};
+
+const CREATED_AT_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.fZ";
+
+// imports sent a file at a time that may be open at once, and how many files each may hold,
+// every file being capped by the message size already
+const MAX_PENDING_IMPORTS: usize = 16;
+const MAX_PENDING_FILES: usize = 100_000;
+// bytes of names and contents each import may hold, and all of them together
+pub const MAX_PENDING_BYTES: usize = 64 * 1024 * 1024;
+pub const MAX_PENDING_TOTAL_BYTES: usize = 128 * 1024 * 1024;
+// an import that goes this long without a file is given up on
+const PENDING_IMPORT_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+pub fn to_markdown(details: &NoteWithDetails) -> MarkdownFile {
+    let note = &details.note;
+    let mut content = String::from("---\n");
+
+    content += &format!("id: {}\n", note.id);
+    content += &format!(
+        "created_at: {}\n",
+        note.created_at.format(CREATED_AT_FORMAT)
+    );
+    // only given titles, derived ones follow the text
+    if let Some(title) = &note.title {
+        content += &format!("title: {}\n", front_matter::quote(title));
+    }
+
+    if details.tags.is_empty() {
+        content += "tags: []\n";
+    } else {
+        content += "tags:\n";
+        for tag in &details.tags {
+            content += &format!("  - {}\n", front_matter::quote(&tag.name));
+        }
+    }
+
+    if details.note_dates.is_empty() {
+        content += "dates: []\n";
+    } else {
+        content += "dates:\n";
+        for note_date in &details.note_dates {
+            match &note_date.label {
+                Some(label) => {
+                    content += &format!("  - label: {}\n", front_matter::quote(label));
+                    content += &format!("    date: {}\n", note_date.date);
+                }
+                None => content += &format!("  - date: {}\n", note_date.date),
+            }
+            if let Some(rrule) = &note_date.rrule {
+                content += &format!("    rrule: {}\n", front_matter::quote(rrule));
+            }
+        }
+    }
+
+    if details.note_links.is_empty() {
+        content += "links: []\n";
+    } else {
+        content += "links:\n";
+        for linked in &details.note_links {
+            content += &format!("  - \"[[{}]]\"\n", linked.id);
+        }
+    }
+
+    content += "---\n\n";
+    content += &note.text;
+
+    MarkdownFile {
+        name: format!("{}.md", note.id),
+        content,
+    }
+}
+
+/// Parses a file written by [`to_markdown`], returning the note and the ids it links to
+pub fn from_markdown(content: &str) -> std::result::Result<(NoteImport, Vec<Uuid>), String> {
+    let (front, body) = front_matter::split(content).ok_or("missing front matter")?;
+    let entries = front_matter::parse(front)?;
+
+    let field = |key: &str| {
+        front_matter::get(&entries, key)
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("missing `{key}`"))
+    };
+
+    let id = field("id")?;
+    let id = Uuid::parse_str(id).map_err(|e| format!("invalid id {id}: {e}"))?;
+
+    let created_at = field("created_at")?;
+    let created_at = DateTime::parse_from_rfc3339(created_at)
+        .map_err(|e| format!("invalid created_at {created_at}: {e}"))?
+        .with_timezone(&Utc);
+
+    // a blank title is no title, like when creating a note
+    let title = match front_matter::get(&entries, "title") {
+        Some(title) => Some(title.as_str().ok_or("title must be a string")?.trim())
+            .filter(|title| !title.is_empty())
+            .map(str::to_string),
+        None => None,
+    };
+
+    let list = |key: &str| {
+        front_matter::get(&entries, key)
+            .map(Value::items)
+            .unwrap_or_default()
+    };
+
+    let tags = list("tags")
+        .into_iter()
+        .map(|tag| {
+            tag.as_str()
+                .map(str::to_string)
+                .ok_or("tags must be strings")
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let tags = distinct_tags(tags);
+
+    let dates = list("dates")
+        .into_iter()
+        .map(parse_date)
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let links = list("links")
+        .into_iter()
+        .map(|link| {
+            let link = link.as_str().ok_or("links must be strings")?;
+            let id = link
+                .strip_prefix("[[")
+                .and_then(|link| link.strip_suffix("]]"))
+                .unwrap_or(link);
+            Uuid::parse_str(id).map_err(|e| format!("invalid link {link}: {e}"))
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    // the blank line after the front matter is part of the format, not the note
+    let text = body
+        .strip_prefix("\r\n")
+        .or_else(|| body.strip_prefix('\n'))
+        .unwrap_or(body);
+
+    let note = NoteImport {
+        id,
+        text: text.to_string(),
+        title,
+        created_at,
+        tags,
+        dates,
+    };
+    Ok((note, links))
+}
+
+fn parse_date(value: &Value) -> std::result::Result<NewNoteDate, String> {
+    let (label, date, rrule) = match value {
+        Value::Scalar(date) => (None, date.as_str(), None),
+        Value::Map(_) => (
+            value.get("label").and_then(Value::as_str),
+            value
+                .get("date")
+                .and_then(Value::as_str)
+                .ok_or("dates need a `date`")?,
+            value.get("rrule").and_then(Value::as_str),
+        ),
+        Value::List(_) => return Err("dates must be dates or maps".to_string()),
+    };
+
+    Ok(NewNoteDate {
+        label: label.map(str::to_string),
+        date: NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|e| format!("invalid date {date}: {e}"))?,
+        rrule: rrule
+            .map(|rrule| {
+                Recurrence::from_str(rrule)
+                    .map(|rule| rule.to_string())
+                    .map_err(|e| format!("invalid rrule {rrule}: {e}"))
+            })
+            .transpose()?,
+    })
+}
+
+/// Every note in the store as a Markdown file, oldest first
+pub async fn export(store: &dyn NoteStore) -> Result<Vec<MarkdownFile>> {
+    Ok(store
+        .get_all_note_details()
+        .await?
+        .iter()
+        .map(to_markdown)
+        .collect())
+}
+
+/// Creates or overwrites each note by id, then restores the links between them, dropping the
+/// links of imported notes that no file lists anymore
+///
+/// Files that fail to parse or store are skipped and listed in the report's errors, so one bad
+/// file doesn't stop the rest. Importing the same files twice leaves the store unchanged.
+pub async fn import(
+    store: &dyn NoteStore,
+    limits: &Limits,
+    files: &[MarkdownFile],
+) -> Result<ImportReport> {
+    let mut report = ImportReport::default();
+    let mut links = Vec::new();
+    let mut imported = HashSet::new();
+
+    for file in files {
+        let (note, note_links) = match from_markdown(&file.content) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                report.errors.push(format!("{}: {e}", file.name));
+                continue;
+            }
+        };
+
+        if let Err(e) = limits.check_note(&note.text, note.title.as_deref(), &note.tags) {
+            report.errors.push(format!("{}: {e}", file.name));
+            continue;
+        }
+
+        match store.upsert_note(&note).await {
+            Ok(UpsertOutcome::Created) => report.created += 1,
+            Ok(UpsertOutcome::Updated) => report.updated += 1,
+            Err(e) => {
+                report.errors.push(format!("{}: {e}", file.name));
+                continue;
+            }
+        }
+
+        imported.insert(note.id);
+        links.extend(note_links.into_iter().map(|linked| (note.id, linked)));
+    }
+
+    // links go last, so they can point at notes further down the list
+    let known = store
+        .get_all_notes()
+        .await?
+        .into_iter()
+        .map(|note| note.id)
+        .collect::<HashSet<_>>();
+
+    let mut linked = HashSet::new();
+    for (a, b) in links {
+        if a == b {
+            report
+                .errors
+                .push(format!("{a}: a note can't link to itself"));
+        } else if !known.contains(&b) {
+            report
+                .errors
+                .push(format!("{a}: linked note {b} not found"));
+        } else if linked.insert(link_key(a, b)) {
+            store.link_notes(a, b).await?;
+        }
+    }
+    report.linked = linked.len();
+
+    // a link between two notes is kept as long as either file lists it
+    let mut unlinked = HashSet::new();
+    for details in store.get_all_note_details().await? {
+        if !imported.contains(&details.note.id) {
+            continue;
+        }
+        for other in &details.note_links {
+            let key = link_key(details.note.id, other.id);
+            if !linked.contains(&key) && unlinked.insert(key) {
+                store.unlink_notes(key.0, key.1).await?;
+            }
+        }
+    }
+    report.unlinked = unlinked.len();
+
+    Ok(report)
+}
+
+/// Imports sent a file at a time, for archives too large for a single message, held until
+/// they're finished
+#[derive(Debug, Default)]
+pub struct PendingImports {
+    imports: Mutex<HashMap<Uuid, PendingImport>>,
+}
+
+#[derive(Debug)]
+struct PendingImport {
+    files: Vec<MarkdownFile>,
+    bytes: usize,
+    last_file: Instant,
+}
+
+impl PendingImports {
+    /// Opens an import, returning the id its files are sent with
+    pub fn begin(&self) -> std::result::Result<Uuid, String> {
+        let mut imports = self.imports.lock().expect("failed to lock pending imports");
+        imports.retain(|_, import| import.last_file.elapsed() < PENDING_IMPORT_TIMEOUT);
+        if imports.len() >= MAX_PENDING_IMPORTS {
+            return Err(format!(
+                "too many imports in progress, the limit is {MAX_PENDING_IMPORTS}"
+            ));
+        }
+
+        let id = Uuid::new_v4();
+        imports.insert(
+            id,
+            PendingImport {
+                files: Vec::new(),
+                bytes: 0,
+                last_file: Instant::now(),
+            },
+        );
+        Ok(id)
+    }
+
+    /// Adds a file to import `id`, returning how many it holds now
+    pub fn add(&self, id: Uuid, file: MarkdownFile) -> std::result::Result<usize, String> {
+        let mut imports = self.imports.lock().expect("failed to lock pending imports");
+        let total_bytes = imports.values().map(|import| import.bytes).sum::<usize>();
+        let import = imports
+            .get_mut(&id)
+            .ok_or_else(|| format!("no import {id} in progress"))?;
+        if import.files.len() >= MAX_PENDING_FILES {
+            return Err(format!(
+                "import {id} has too many files, the limit is {MAX_PENDING_FILES}"
+            ));
+        }
+        let bytes = file.name.len() + file.content.len();
+        if import.bytes + bytes > MAX_PENDING_BYTES {
+            return Err(format!(
+                "import {id} is too large, the limit is {MAX_PENDING_BYTES} bytes"
+            ));
+        }
+        if total_bytes + bytes > MAX_PENDING_TOTAL_BYTES {
+            return Err(format!(
+                "imports in progress are too large, the limit is {MAX_PENDING_TOTAL_BYTES} bytes together"
+            ));
+        }
+
+        import.files.push(file);
+        import.bytes += bytes;
+        import.last_file = Instant::now();
+        Ok(import.files.len())
+    }
+
+    /// Closes import `id`, returning its files to [`import`]
+    pub fn finish(&self, id: Uuid) -> std::result::Result<Vec<MarkdownFile>, String> {
+        self.imports
+            .lock()
+            .expect("failed to lock pending imports")
+            .remove(&id)
+            .map(|import| import.files)
+            .ok_or_else(|| format!("no import {id} in progress"))
+    }
+}
+
+/// Writes each file into `dir`, creating it if needed
+pub fn write_dir(dir: &Path, files: &[MarkdownFile]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for file in files {
+        fs::write(dir.join(&file.name), &file.content)?;
+    }
+    Ok(())
+}
+
+/// Reads every `.md` file directly inside `dir`, sorted by name
+pub fn read_dir(dir: &Path) -> io::Result<Vec<MarkdownFile>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if !path.is_file() || path.extension().is_none_or(|extension| extension != "md") {
+            continue;
+        }
+
+        files.push(MarkdownFile {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            content: fs::read_to_string(&path)?,
+        });
+    }
+
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
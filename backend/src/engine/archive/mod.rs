@@ -0,0 +1,5 @@
+pub mod backup;
+pub mod front_matter;
+pub mod ical;
+pub mod markdown;
+pub mod vault;
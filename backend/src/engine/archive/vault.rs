@@ -0,0 +1,513 @@
+//! Imports an Obsidian vault or a Logseq graph, one note per Markdown file
+//!
+//! - the file name, or Logseq's `title::` property, becomes the note's title
+//! - tags come from the front matter, Logseq's `tags::` property, and `#tags` in the text
+//! - `[[wikilinks]]` to other files of the vault become note links
+//! - `created_at` is the file's creation or modification time, whichever is earlier
+//!
+//! Note ids are derived from the vault's name and the file's path, so importing the same vault
+//! again updates the notes instead of duplicating them, keeping the dates added to them since.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::front_matter::{self, Value};
+use crate::engine::{
+    core::Limits,
+    database::{
+        NoteStore,
+        error::Result,
+        notes::{NewNoteDate, NoteImport, link_key},
+    },
+};
+
+/// Namespace of the v5 ids given to vault notes
+const VAULT_NAMESPACE: Uuid = Uuid::from_u128(0x5d0c_1f2e_8a47_4b6e_9c3d_71a0_e4b2_f658);
+
+/// A vault read from disk, not yet imported
+#[derive(Debug, Default)]
+pub struct Vault {
+    pub notes: Vec<VaultNote>,
+    /// Links to pages that aren't in the vault, as `path: [[target]]`
+    pub unresolved_links: Vec<String>,
+    /// Files that were skipped or only partly understood
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct VaultNote {
+    /// Path relative to the vault, with `/` separators
+    pub path: String,
+    pub note: NoteImport,
+    pub links: Vec<Uuid>,
+}
+
+/// What an import did, or would do on a dry run
+#[derive(Debug, Default)]
+pub struct VaultReport {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub new_tags: Vec<String>,
+    pub links: usize,
+    pub unresolved_links: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// One file of the vault before its links are resolved
+struct Page {
+    path: String,
+    title: String,
+    aliases: Vec<String>,
+    text: String,
+    tags: Vec<String>,
+    links: Vec<String>,
+    created_at: DateTime<Utc>,
+}
+
+pub fn read_vault(dir: &Path) -> io::Result<Vault> {
+    let root = dir.canonicalize()?;
+    let vault_name = root
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let logseq = root.join("logseq").join("config.edn").is_file();
+
+    let mut vault = Vault::default();
+    let mut paths = Vec::new();
+    walk(&root, &root, logseq, &mut paths)?;
+    paths.sort();
+
+    let mut pages = Vec::new();
+    for path in paths {
+        let relative = path
+            .strip_prefix(&root)
+            .expect("walked outside the vault")
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                vault.warnings.push(format!("{relative}: {e}"));
+                continue;
+            }
+        };
+        // a file that was just read but can't be looked at again still makes a note, dated now
+        let created_at = match fs::metadata(&path) {
+            Ok(metadata) => [metadata.created(), metadata.modified()]
+                .into_iter()
+                .filter_map(io::Result::ok)
+                .min()
+                .unwrap_or_else(SystemTime::now),
+            Err(e) => {
+                vault
+                    .warnings
+                    .push(format!("{relative}: no file times, dating it now: {e}"));
+                SystemTime::now()
+            }
+        };
+
+        pages.push(parse_page(
+            relative,
+            &content,
+            DateTime::<Utc>::from(created_at),
+            logseq,
+            &mut vault.warnings,
+        ));
+    }
+
+    // exact paths win over titles and file names, which win over aliases
+    let mut targets = HashMap::new();
+    let ids = pages
+        .iter()
+        .map(|page| {
+            Uuid::new_v5(
+                &VAULT_NAMESPACE,
+                format!("{vault_name}/{}", page.path).as_bytes(),
+            )
+        })
+        .collect::<Vec<_>>();
+    for (page, id) in pages.iter().zip(&ids) {
+        let path = page.path.strip_suffix(".md").unwrap_or(&page.path);
+        targets.entry(path.to_lowercase()).or_insert(*id);
+    }
+    for (page, id) in pages.iter().zip(&ids) {
+        targets.entry(page.title.to_lowercase()).or_insert(*id);
+        targets
+            .entry(stem(&page.path).to_lowercase())
+            .or_insert(*id);
+    }
+    for (page, id) in pages.iter().zip(&ids) {
+        for alias in &page.aliases {
+            targets.entry(alias.to_lowercase()).or_insert(*id);
+        }
+    }
+
+    for (page, id) in pages.into_iter().zip(ids) {
+        let mut links = Vec::new();
+        for target in &page.links {
+            match targets.get(&target.to_lowercase()) {
+                Some(linked) if !links.contains(linked) => links.push(*linked),
+                Some(_) => {}
+                None => vault
+                    .unresolved_links
+                    .push(format!("{}: [[{target}]]", page.path)),
+            }
+        }
+
+        vault.notes.push(VaultNote {
+            path: page.path,
+            note: NoteImport {
+                id,
+                text: page.text,
+                title: Some(page.title),
+                created_at: page.created_at,
+                tags: page.tags,
+                dates: Vec::new(),
+            },
+            links,
+        });
+    }
+
+    Ok(vault)
+}
+
+/// Collects every Markdown file below `dir`, skipping hidden folders like `.obsidian` and
+/// Logseq's own `logseq` folder
+fn walk(root: &Path, dir: &Path, logseq: bool, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+
+        if name.starts_with('.') {
+            continue;
+        }
+
+        if entry.file_type()?.is_dir() {
+            if !(logseq && dir == root && name == "logseq") {
+                walk(root, &path, logseq, paths)?;
+            }
+        } else if path.extension().is_some_and(|extension| extension == "md") {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn parse_page(
+    path: String,
+    content: &str,
+    created_at: DateTime<Utc>,
+    logseq: bool,
+    warnings: &mut Vec<String>,
+) -> Page {
+    let (front, body) = front_matter::split(content).unwrap_or(("", content));
+
+    // vaults hold all kinds of YAML, so an unreadable header only loses its tags
+    let entries = front_matter::parse(front).unwrap_or_else(|e| {
+        warnings.push(format!("{path}: ignoring front matter, {e}"));
+        Vec::new()
+    });
+
+    let mut tags = Vec::new();
+    let mut aliases = Vec::new();
+    for (key, value) in &entries {
+        let words = value.items().into_iter().filter_map(Value::as_str);
+        match key.as_str() {
+            "tags" | "tag" => tags.extend(
+                words
+                    .flat_map(|tags| tags.split([',', ' ']))
+                    .map(str::to_string),
+            ),
+            "aliases" | "alias" => aliases.extend(
+                words
+                    .flat_map(|aliases| aliases.split(','))
+                    .map(str::to_string),
+            ),
+            _ => {}
+        }
+    }
+
+    let (properties, body) = logseq_properties(body);
+    let mut title = None;
+    for (key, value) in properties {
+        let values = value.split(',').map(|value| {
+            value
+                .trim()
+                .trim_start_matches('#')
+                .trim_start_matches("[[")
+                .trim_end_matches("]]")
+                .to_string()
+        });
+        match key {
+            "tags" => tags.extend(values),
+            "alias" => aliases.extend(values),
+            "title" => title = Some(value.trim().to_string()).filter(|title| !title.is_empty()),
+            _ => {}
+        }
+    }
+
+    let stem = stem(&path);
+    // Logseq writes the namespace page `a/b` as `a___b.md`
+    let title = title.unwrap_or_else(|| {
+        if logseq {
+            stem.replace("___", "/")
+        } else {
+            stem.to_string()
+        }
+    });
+
+    let (inline_tags, links) = scan(body);
+    tags.extend(inline_tags);
+
+    // tags differing only in case are the same tag in both apps
+    let mut seen = HashSet::new();
+    let tags = tags
+        .into_iter()
+        .map(|tag| tag.trim().trim_start_matches('#').to_string())
+        .filter(|tag| !tag.is_empty() && seen.insert(tag.to_lowercase()))
+        .collect();
+
+    let text = body.trim_start_matches(['\r', '\n']).to_string();
+
+    Page {
+        path,
+        title,
+        aliases: aliases
+            .into_iter()
+            .map(|alias| alias.trim().to_string())
+            .filter(|alias| !alias.is_empty())
+            .collect(),
+        text,
+        tags,
+        links,
+        created_at,
+    }
+}
+
+fn stem(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.strip_suffix(".md").unwrap_or(name)
+}
+
+/// Splits Logseq's leading `key:: value` page properties off the body
+fn logseq_properties(body: &str) -> (Vec<(&str, &str)>, &str) {
+    let mut properties = Vec::new();
+    let mut offset = 0;
+
+    for line in body.split_inclusive('\n') {
+        let Some((key, value)) = line.trim_end().split_once(":: ") else {
+            break;
+        };
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            break;
+        }
+        properties.push((key, value));
+        offset += line.len();
+    }
+
+    (properties, &body[offset..])
+}
+
+/// Finds `#tags`, `#[[tags]]` and `[[links]]` outside of code
+fn scan(body: &str) -> (Vec<String>, Vec<String>) {
+    let mut tags = Vec::new();
+    let mut links = Vec::new();
+    let mut fenced = false;
+
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") {
+            fenced = !fenced;
+            continue;
+        }
+        if fenced {
+            continue;
+        }
+
+        // every other piece between backticks is inline code
+        for segment in line.split('`').step_by(2) {
+            scan_segment(segment, &mut tags, &mut links);
+        }
+    }
+
+    (tags, links)
+}
+
+fn scan_segment(segment: &str, tags: &mut Vec<String>, links: &mut Vec<String>) {
+    let mut rest = segment;
+    let mut previous = None;
+
+    while let Some(c) = rest.chars().next() {
+        if let Some(inner) = rest.strip_prefix("[[")
+            && let Some(end) = inner.find("]]")
+        {
+            let target = &inner[..end];
+            if previous == Some('#') {
+                tags.push(target.to_string());
+            } else if let Some(target) = link_target(target) {
+                links.push(target);
+            }
+            rest = &inner[end + 2..];
+            previous = Some(']');
+            continue;
+        }
+
+        if c == '#' && previous.is_none_or(char::is_whitespace) {
+            let tag_end = rest[1..]
+                .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '/')))
+                .map_or(rest.len(), |end| end + 1);
+            let tag = rest[1..tag_end].trim_end_matches('/');
+
+            // `#123` is an issue number, not a tag
+            if tag.chars().any(|c| !c.is_ascii_digit()) {
+                tags.push(tag.to_string());
+                previous = rest[..tag_end].chars().last();
+                rest = &rest[tag_end..];
+                continue;
+            }
+        }
+
+        previous = Some(c);
+        rest = &rest[c.len_utf8()..];
+    }
+}
+
+/// The page a wikilink points at, without its alias, heading or `.md`, or nothing for attachments
+fn link_target(link: &str) -> Option<String> {
+    let target = link.split('|').next().unwrap_or(link);
+    let target = target.split('#').next().unwrap_or(target).trim();
+    let target = target.strip_suffix(".md").unwrap_or(target);
+
+    let is_attachment = Path::new(target).extension().is_some_and(|extension| {
+        let extension = extension.to_string_lossy();
+        extension.len() <= 4 && extension.chars().all(|c| c.is_ascii_alphanumeric())
+    });
+    (!target.is_empty() && !is_attachment).then(|| target.to_string())
+}
+
+/// Creates or updates every note of the vault, or only reports what it would do
+///
+/// Tags are matched case-insensitively against existing ones, so `#Idea` lands on `idea` if
+/// that tag exists already.
+pub async fn import_vault(
+    store: &dyn NoteStore,
+    limits: &Limits,
+    vault: &Vault,
+    dry_run: bool,
+) -> Result<VaultReport> {
+    let mut report = VaultReport {
+        unresolved_links: vault.unresolved_links.clone(),
+        warnings: vault.warnings.clone(),
+        ..Default::default()
+    };
+
+    let mut known = store
+        .get_all_notes()
+        .await?
+        .into_iter()
+        .map(|note| note.id)
+        .collect::<HashSet<_>>();
+    let existing = known.clone();
+
+    let mut tag_names = store
+        .get_all_tags()
+        .await?
+        .into_iter()
+        .map(|tag| (tag.name.to_lowercase(), tag.name))
+        .collect::<HashMap<_, _>>();
+
+    let mut stored_dates = HashMap::new();
+    if !dry_run {
+        for details in store.get_all_note_details().await? {
+            let dates = details
+                .note_dates
+                .into_iter()
+                .map(|date| NewNoteDate {
+                    label: date.label,
+                    date: date.date,
+                    rrule: date.rrule,
+                })
+                .collect::<Vec<_>>();
+            stored_dates.insert(details.note.id, dates);
+        }
+    }
+
+    let mut imported = Vec::new();
+    for vault_note in &vault.notes {
+        // tags only count as new once a note that uses them made it in
+        let mut note = vault_note.note.clone();
+        let mut new_tags = Vec::new();
+        note.tags = note
+            .tags
+            .iter()
+            .map(|tag| match tag_names.get(&tag.to_lowercase()) {
+                Some(existing) => existing.clone(),
+                None => {
+                    new_tags.push(tag.clone());
+                    tag.clone()
+                }
+            })
+            .collect();
+
+        if let Err(e) = limits.check_note(&note.text, note.title.as_deref(), &note.tags) {
+            report.warnings.push(format!("{}: {e}", vault_note.path));
+            continue;
+        }
+
+        // vault files carry no dates, the ones added since the last import stay
+        if note.dates.is_empty()
+            && let Some(dates) = stored_dates.remove(&note.id)
+        {
+            note.dates = dates;
+        }
+
+        if !dry_run && let Err(e) = store.upsert_note(&note).await {
+            report.warnings.push(format!("{}: {e}", vault_note.path));
+            continue;
+        }
+
+        for tag in new_tags {
+            tag_names.insert(tag.to_lowercase(), tag.clone());
+            report.new_tags.push(tag);
+        }
+        if existing.contains(&note.id) {
+            report.updated.push(vault_note.path.clone());
+        } else {
+            report.created.push(vault_note.path.clone());
+        }
+        known.insert(note.id);
+        imported.push(vault_note);
+    }
+
+    let mut linked = HashSet::new();
+    for vault_note in imported {
+        let id = vault_note.note.id;
+        for &other in &vault_note.links {
+            // a page linking to one of its own headings, or to a page that was skipped
+            if other == id || !known.contains(&other) || !linked.insert(link_key(id, other)) {
+                continue;
+            }
+            if !dry_run {
+                store.link_notes(id, other).await?;
+            }
+        }
+    }
+    report.links = linked.len();
+
+    Ok(report)
+}
@@ -1,19 +1,211 @@
-use crate::engine::database::Database;
+use std::{ops::Range, sync::Arc};
+
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use futures::{
+    StreamExt,
+    stream::{self, BoxStream},
+};
 use sqlx::PgPool;
+use tracing::{info, warn};
+
+use rust_shared::{
+    dates,
+    request::{self, DateRange},
+    response,
+};
+
+use crate::{
+    engine::{
+        archive::markdown::{self, PendingImports},
+        database::{Database, NoteStore, memory::MemoryStore, notes::NewNoteDate},
+        recurrence,
+        reminders::{self, ReminderConfig},
+    },
+    env_or,
+};
+
+/// A stream of just `response`, how most requests are answered
+fn single(response: response::Message) -> BoxStream<'static, response::Message> {
+    stream::once(async { response }).boxed()
+}
+
+/// The IANA timezone called `name`, UTC if there's no name
+fn timezone(name: Option<&str>) -> Result<Tz, String> {
+    match name {
+        Some(name) => name.parse().map_err(|_| format!("unknown timezone {name}")),
+        None => Ok(Tz::UTC),
+    }
+}
+
+/// From the start of `days.from` to the end of `days.to`, both days as seen in `timezone` (UTC if
+/// unset)
+pub fn created_between(
+    days: DateRange,
+    timezone: Option<&str>,
+) -> Result<Range<DateTime<Utc>>, String> {
+    let timezone = self::timezone(timezone)?;
+    let start_of_day = |day: NaiveDate| {
+        let midnight = day.and_time(NaiveTime::MIN);
+        // a daylight saving change can skip midnight, the day then starts that much later
+        timezone
+            .from_local_datetime(&midnight)
+            .earliest()
+            .unwrap_or_else(|| timezone.from_utc_datetime(&midnight))
+            .with_timezone(&Utc)
+    };
+
+    let after_to = days
+        .to
+        .succ_opt()
+        .ok_or_else(|| format!("date out of range {}", days.to))?;
+    Ok(start_of_day(days.from)..start_of_day(after_to))
+}
 
-use rust_shared::{request, response};
+/// The note dates for the date phrases in `text`, read in the writer's `timezone` (UTC if unset)
+/// as of `now`. Only the day is kept of phrases with a time.
+pub fn extracted_dates(
+    text: &str,
+    timezone: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<Vec<NewNoteDate>, String> {
+    let local_now = now.with_timezone(&self::timezone(timezone)?).naive_local();
+
+    Ok(dates::extract_dates(text, local_now)
+        .into_iter()
+        .map(|detected| NewNoteDate {
+            label: detected.label,
+            date: detected.date,
+            rrule: None,
+        })
+        .collect())
+}
+
+/// Bounds on what a single request may store
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// Maximum note length in characters
+    pub max_note_length: usize,
+    /// Maximum length of a given title in characters, derived ones are always short
+    pub max_title_length: usize,
+    pub max_tags_per_note: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_note_length: 10_000,
+            max_title_length: 200,
+            max_tags_per_note: 32,
+        }
+    }
+}
+
+impl Limits {
+    /// Reads `MAX_NOTE_LENGTH`, `MAX_TITLE_LENGTH` and `MAX_TAGS_PER_NOTE`, using the defaults
+    /// for anything unset
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            max_note_length: env_or("MAX_NOTE_LENGTH", default.max_note_length),
+            max_title_length: env_or("MAX_TITLE_LENGTH", default.max_title_length),
+            max_tags_per_note: env_or("MAX_TAGS_PER_NOTE", default.max_tags_per_note),
+        }
+    }
+
+    pub(crate) fn check_note(
+        &self,
+        text: &str,
+        title: Option<&str>,
+        tags: &[String],
+    ) -> Result<(), String> {
+        let length = text.chars().count();
+        if length > self.max_note_length {
+            return Err(format!(
+                "note is too long: {length} characters, the limit is {}",
+                self.max_note_length
+            ));
+        }
+
+        let length = title.map_or(0, |title| title.chars().count());
+        if length > self.max_title_length {
+            return Err(format!(
+                "title is too long: {length} characters, the limit is {}",
+                self.max_title_length
+            ));
+        }
+
+        if tags.len() > self.max_tags_per_note {
+            return Err(format!(
+                "note has too many tags: {}, the limit is {}",
+                tags.len(),
+                self.max_tags_per_note
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// `tags` with each name once, in the order first given, which is how the stores take them
+pub fn distinct_tags(tags: Vec<String>) -> Vec<String> {
+    let mut distinct = Vec::with_capacity(tags.len());
+    for tag in tags {
+        if !distinct.contains(&tag) {
+            distinct.push(tag);
+        }
+    }
+    distinct
+}
 
 #[derive(Clone)]
 pub struct Engine {
-    database: Database,
+    store: Arc<dyn NoteStore>,
+    limits: Limits,
+    reminders: ReminderConfig,
+    imports: Arc<PendingImports>,
 }
 
 impl Engine {
-    pub fn new(database: Database) -> Self {
-        Self { database }
+    pub fn new(store: impl NoteStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+            limits: Limits::default(),
+            reminders: ReminderConfig::default(),
+            imports: Arc::default(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_reminders(mut self, reminders: ReminderConfig) -> Self {
+        self.reminders = reminders;
+        self
     }
 
+    /// `memory://` keeps everything in process, `sqlite:` urls need the `sqlite` feature,
+    /// anything else is treated as a Postgres url
     pub async fn with_database_url(db_url: &str) -> Result<Self, sqlx::Error> {
+        if db_url.starts_with("memory:") {
+            return Ok(Self::new(MemoryStore::new()));
+        }
+
+        #[cfg(feature = "sqlite")]
+        if db_url.starts_with("sqlite:") {
+            let store = crate::engine::database::sqlite::SqliteStore::connect(db_url).await?;
+            return Ok(Self::new(store));
+        }
+
+        #[cfg(not(feature = "sqlite"))]
+        if db_url.starts_with("sqlite:") {
+            return Err(sqlx::Error::Configuration(
+                "sqlite urls need rember-backend built with the `sqlite` feature".into(),
+            ));
+        }
+
         let db_pool = PgPool::connect(db_url).await?;
         sqlx::migrate!().run(&db_pool).await?;
         let database = Database::with_pool(db_pool);
@@ -30,57 +222,229 @@ impl Engine {
     ///     }
     /// }
     /// ```
-    pub async fn handle_message(&self, msg: request::Message) -> response::Message {
-        match msg {
+    ///
+    /// Most requests get a single response, exports stream one message per note.
+    pub fn handle_message(&self, msg: request::Message) -> BoxStream<'static, response::Message> {
+        let engine = self.clone();
+        stream::once(async move { engine.respond(msg).await })
+            .flatten()
+            .boxed()
+    }
+
+    /// Every file, then how many there were
+    async fn export_markdown(&self) -> BoxStream<'static, response::Message> {
+        match markdown::export(self.store()).await {
+            Ok(files) => {
+                let finished = response::ExportFinished { count: files.len() };
+                stream::iter(files)
+                    .map(response::Message::MarkdownFile)
+                    .chain(stream::once(async {
+                        response::Message::ExportFinished(finished)
+                    }))
+                    .boxed()
+            }
+            Err(e) => single(response::Message::Unknown(e.to_string())),
+        }
+    }
+
+    async fn respond(&self, msg: request::Message) -> BoxStream<'static, response::Message> {
+        single(match msg {
             request::Message::CreateNote(create_note) => {
+                // a blank title is no title, the note gets a derived one
+                let title = create_note
+                    .title
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|title| !title.is_empty());
+                let tags = distinct_tags(create_note.tags);
+
+                if let Err(e) = self.limits.check_note(&create_note.text, title, &tags) {
+                    return single(response::Message::Unknown(e));
+                }
+
+                let dates = if create_note.extract_dates {
+                    match extracted_dates(
+                        &create_note.text,
+                        create_note.timezone.as_deref(),
+                        Utc::now(),
+                    ) {
+                        Ok(dates) => dates,
+                        Err(e) => return single(response::Message::Unknown(e)),
+                    }
+                } else {
+                    Vec::new()
+                };
+
                 match self
-                    .database
-                    .create_note(&create_note.text, &create_note.tags)
+                    .store
+                    .create_note(&create_note.text, title, &tags, &dates)
                     .await
                 {
-                    Ok(note) => response::Message::Notes(vec![note]),
+                    Ok(note) => response::Message::NoteCreated(note),
+                    Err(e) => response::Message::Unknown(e.to_string()),
+                }
+            }
+            request::Message::UpdateNote(request::UpdateNote { id, text, tags }) => {
+                let tags = distinct_tags(tags);
+                if let Err(e) = self.limits.check_note(&text, None, &tags) {
+                    return single(response::Message::Unknown(e));
+                }
+
+                match self.store.update_note(id, &text, &tags).await {
+                    Ok(note) => response::Message::NoteUpdated(note),
+                    Err(e) => response::Message::Unknown(e.to_string()),
+                }
+            }
+            request::Message::DeleteNote(request::DeleteNote { id }) => {
+                match self.store.delete_note(id).await {
+                    Ok(()) => response::Message::NoteDeleted(response::NoteDeleted { id }),
                     Err(e) => response::Message::Unknown(e.to_string()),
                 }
             }
             request::Message::CreateTag(create_tag) => {
-                match self.database.create_tag(&create_tag.name).await {
+                match self.store.create_tag(&create_tag.name).await {
                     Ok(tag) => response::Message::Tags(vec![tag]),
                     Err(e) => response::Message::Unknown(e.to_string()),
                 }
             }
-            request::Message::GetNotes(_get_notes) => match self.database.get_all_notes().await {
+            request::Message::GetNotes(_get_notes) => match self.store.get_all_notes().await {
                 Ok(notes) => response::Message::Notes(notes),
                 Err(e) => response::Message::Unknown(e.to_string()),
             },
-            request::Message::GetTags => match self.database.get_all_tags().await {
+            request::Message::GetTags => match self.store.get_all_tags().await {
                 Ok(tags) => response::Message::Tags(tags),
                 Err(e) => response::Message::Unknown(e.to_string()),
             },
             request::Message::GetNotesFiltered(request::GetNotesFiltered {
                 search_text,
                 tags,
+                dates,
+                created,
+                timezone,
                 limit,
                 offset,
-            }) => match self
-                .database
-                .get_notes_filtered(search_text, tags, limit, offset)
-                .await
-            {
-                Ok(notes) => response::Message::Notes(notes),
-                Err(e) => response::Message::Unknown(e.to_string()),
+            }) => {
+                let created = match created
+                    .map(|created| created_between(created, timezone.as_deref()))
+                    .transpose()
+                {
+                    Ok(created) => created,
+                    Err(e) => return single(response::Message::Unknown(e)),
+                };
+
+                if let Some(Err(e)) = dates.map(recurrence::check_window) {
+                    return single(response::Message::Unknown(e));
+                }
+
+                let note_ids = match dates {
+                    Some(dates) => match recurrence::notes_between(self.store(), dates).await {
+                        Ok(note_ids) => Some(note_ids),
+                        Err(e) => return single(response::Message::Unknown(e.to_string())),
+                    },
+                    None => None,
+                };
+
+                match self
+                    .store
+                    .get_notes_filtered(search_text, tags, note_ids, created, limit, offset)
+                    .await
+                {
+                    Ok(notes) => response::Message::Notes(notes),
+                    Err(e) => response::Message::Unknown(e.to_string()),
+                }
+            }
+            request::Message::GetNoteDetails(request::GetNoteDetails { id }) => {
+                match self.store.get_note_details(id).await {
+                    Ok(details) => response::Message::NoteDetails(details),
+                    Err(e) => response::Message::Unknown(e.to_string()),
+                }
+            }
+            request::Message::GetOccurrences(request::GetOccurrences { dates, tags }) => {
+                if let Err(e) = recurrence::check_window(dates) {
+                    return single(response::Message::Unknown(e));
+                }
+
+                match recurrence::occurrences(self.store(), dates, tags).await {
+                    Ok(occurrences) => response::Message::Occurrences(occurrences),
+                    Err(e) => response::Message::Unknown(e.to_string()),
+                }
+            }
+            request::Message::ImportMarkdown(request::ImportMarkdown { files }) => {
+                match markdown::import(self.store(), &self.limits, &files).await {
+                    Ok(report) => response::Message::Imported(report),
+                    Err(e) => response::Message::Unknown(e.to_string()),
+                }
+            }
+            request::Message::ImportBegin => match self.imports.begin() {
+                Ok(id) => response::Message::ImportStarted(response::ImportStarted { id }),
+                Err(e) => response::Message::Unknown(e),
             },
+            request::Message::ImportFile(request::ImportFile { id, file }) => {
+                match self.imports.add(id, file) {
+                    Ok(count) => {
+                        response::Message::ImportFileReceived(response::ImportFileReceived {
+                            id,
+                            count,
+                        })
+                    }
+                    Err(e) => response::Message::Unknown(e),
+                }
+            }
+            request::Message::ImportFinish(request::ImportFinish { id }) => {
+                let files = match self.imports.finish(id) {
+                    Ok(files) => files,
+                    Err(e) => return single(response::Message::Unknown(e)),
+                };
+                match markdown::import(self.store(), &self.limits, &files).await {
+                    Ok(report) => response::Message::Imported(report),
+                    Err(e) => response::Message::Unknown(e.to_string()),
+                }
+            }
+            request::Message::GetReminders => self.due_reminders().await,
+            request::Message::AcknowledgeReminder(request::AcknowledgeReminder { id, date }) => {
+                let now = Utc::now();
+                match self.store.acknowledge_reminder(id, date, now).await {
+                    Ok(()) => self.due_reminders().await,
+                    Err(e) => response::Message::Unknown(e.to_string()),
+                }
+            }
+            request::Message::SnoozeReminder(request::SnoozeReminder { id, date, minutes }) => {
+                let until = Utc::now() + TimeDelta::minutes(minutes.into());
+                match self.store.snooze_reminder(id, date, until).await {
+                    Ok(()) => self.due_reminders().await,
+                    Err(e) => response::Message::Unknown(e.to_string()),
+                }
+            }
+            request::Message::ExportMarkdown => return self.export_markdown().await,
             request::Message::Test(test_struct) => {
-                println!("Received test message: {test_struct:?}");
+                info!("received test message: {test_struct:?}");
                 response::Message::Unknown("test message received".to_string())
             }
             request::Message::Unknown(msg_type) => {
-                println!("Unknown message type: {msg_type}");
+                warn!("unknown message type: {msg_type}");
                 response::Message::Unknown(msg_type)
             }
+        })
+    }
+
+    async fn due_reminders(&self) -> response::Message {
+        match reminders::due(self.store(), &self.reminders, Utc::now()).await {
+            Ok(due) => response::Message::Reminders(
+                due.into_iter().map(|pending| pending.reminder).collect(),
+            ),
+            Err(e) => response::Message::Unknown(e.to_string()),
         }
     }
 
-    pub fn _database(&self) -> &Database {
-        &self.database
+    pub fn store(&self) -> &dyn NoteStore {
+        self.store.as_ref()
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    pub fn reminders(&self) -> &ReminderConfig {
+        &self.reminders
     }
 }
@@ -0,0 +1,34 @@
+use std::fmt;
+
+use uuid::Uuid;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Sqlx(sqlx::Error),
+    NoteNotFound(Uuid),
+    ReminderNotFound(Uuid),
+    TagNotFound(String),
+    TagExists(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sqlx(e) => write!(f, "{e}"),
+            Self::NoteNotFound(id) => write!(f, "note not found: {id}"),
+            Self::ReminderNotFound(id) => write!(f, "reminder not found: {id}"),
+            Self::TagNotFound(name) => write!(f, "tag not found: {name}"),
+            Self::TagExists(name) => write!(f, "tag already exists: {name}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<sqlx::Error> for Error {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Sqlx(e)
+    }
+}
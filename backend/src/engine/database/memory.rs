@@ -0,0 +1,654 @@
+use std::{
+    collections::HashMap,
+    ops::Range,
+    sync::{Arc, RwLock},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use uuid::Uuid;
+
+use super::{
+    NoteStore,
+    error::{Error, Result},
+    notes::{
+        NewNoteDate, Note, NoteDate, NoteImport, NoteWithDetails, UpsertOutcome, assemble_details,
+        link_key, reused_date_ids, search_score,
+    },
+    reminders::ReminderDate,
+    snapshot::{self, NoteLink, NoteTag, Snapshot},
+    tags::Tag,
+};
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ReminderState {
+    notified_at: Option<DateTime<Utc>>,
+    snoozed_until: Option<DateTime<Utc>>,
+    acknowledged_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    notes: Vec<Note>,
+    tags: Vec<Tag>,
+    note_tags: Vec<(Uuid, Uuid)>,
+    note_dates: Vec<NoteDate>,
+    note_links: Vec<NoteLink>,
+    /// By note date and occurrence
+    reminder_states: HashMap<(Uuid, NaiveDate), ReminderState>,
+}
+
+impl State {
+    fn tag_by_name(&self, name: &str) -> Option<&Tag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    /// Fails with [`Error::TagNotFound`] on the first unknown tag
+    fn tag_ids(&self, names: &[String]) -> Result<Vec<Uuid>> {
+        names
+            .iter()
+            .map(|name| {
+                self.tag_by_name(name)
+                    .map(|tag| tag.id)
+                    .ok_or_else(|| Error::TagNotFound(name.clone()))
+            })
+            .collect()
+    }
+
+    fn get_or_create_tag(&mut self, name: &str) -> Uuid {
+        if let Some(tag) = self.tag_by_name(name) {
+            return tag.id;
+        }
+
+        let tag = Tag {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+        };
+        let id = tag.id;
+        self.tags.push(tag);
+        id
+    }
+
+    fn note_tag_names(&self, note_id: Uuid) -> Vec<&str> {
+        self.note_tags
+            .iter()
+            .filter(|(tagged_note_id, _)| *tagged_note_id == note_id)
+            .filter_map(|(_, tag_id)| self.tags.iter().find(|tag| tag.id == *tag_id))
+            .map(|tag| tag.name.as_str())
+            .collect()
+    }
+
+    /// Like `ON DELETE CASCADE`, drops the states of note dates that are gone
+    fn forget_deleted_reminders(&mut self) {
+        let note_dates = &self.note_dates;
+        self.reminder_states
+            .retain(|(id, _), _| note_dates.iter().any(|note_date| note_date.id == *id));
+    }
+
+    fn reminder_state(&mut self, id: Uuid, date: NaiveDate) -> Result<&mut ReminderState> {
+        if !self.note_dates.iter().any(|note_date| note_date.id == id) {
+            return Err(Error::ReminderNotFound(id));
+        }
+        Ok(self.reminder_states.entry((id, date)).or_default())
+    }
+}
+
+/// Keeps everything in process memory, mirroring the Postgres store's behavior
+///
+/// Nothing survives a restart, so this is meant for tests and throwaway sessions.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    state: Arc<RwLock<State>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl NoteStore for MemoryStore {
+    async fn create_note(
+        &self,
+        text: &str,
+        title: Option<&str>,
+        tags: &[String],
+        dates: &[NewNoteDate],
+    ) -> Result<Note> {
+        let mut state = self.state.write().expect("failed to lock store");
+
+        // resolve every tag first, so an unknown one doesn't leave a half-created note behind
+        let tag_ids = state.tag_ids(tags)?;
+
+        let note = Note {
+            id: Uuid::new_v4(),
+            text: text.to_string(),
+            title: title.map(str::to_string),
+            created_at: Utc::now(),
+        };
+        state.notes.push(note.clone());
+        state
+            .note_tags
+            .extend(tag_ids.into_iter().map(|tag_id| (note.id, tag_id)));
+        for date in dates {
+            state.note_dates.push(NoteDate {
+                id: Uuid::new_v4(),
+                note_id: note.id,
+                label: date.label.clone(),
+                date: date.date,
+                rrule: date.rrule.clone(),
+            });
+        }
+
+        Ok(note)
+    }
+
+    async fn update_note(&self, id: Uuid, text: &str, tags: &[String]) -> Result<Note> {
+        let mut state = self.state.write().expect("failed to lock store");
+
+        // resolve every tag first, so an unknown one leaves the note as it was
+        let tag_ids = state.tag_ids(tags)?;
+        let note = state
+            .notes
+            .iter_mut()
+            .find(|note| note.id == id)
+            .ok_or(Error::NoteNotFound(id))?;
+        note.text = text.to_string();
+        let note = note.clone();
+
+        state.note_tags.retain(|(note_id, _)| *note_id != id);
+        state
+            .note_tags
+            .extend(tag_ids.into_iter().map(|tag_id| (id, tag_id)));
+
+        Ok(note)
+    }
+
+    async fn delete_note(&self, id: Uuid) -> Result<()> {
+        let mut state = self.state.write().expect("failed to lock store");
+
+        if !state.notes.iter().any(|note| note.id == id) {
+            return Err(Error::NoteNotFound(id));
+        }
+
+        // like the other stores' `ON DELETE CASCADE`s
+        state.notes.retain(|note| note.id != id);
+        state.note_tags.retain(|(note_id, _)| *note_id != id);
+        state.note_dates.retain(|note_date| note_date.note_id != id);
+        state
+            .note_links
+            .retain(|link| link.note1_id != id && link.note2_id != id);
+        state.forget_deleted_reminders();
+
+        Ok(())
+    }
+
+    async fn create_tag(&self, name: &str) -> Result<Tag> {
+        let mut state = self.state.write().expect("failed to lock store");
+
+        if state.tag_by_name(name).is_some() {
+            return Err(Error::TagExists(name.to_string()));
+        }
+
+        let tag = Tag {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+        };
+        state.tags.push(tag.clone());
+
+        Ok(tag)
+    }
+
+    async fn get_all_notes(&self) -> Result<Vec<Note>> {
+        let state = self.state.read().expect("failed to lock store");
+        Ok(state.notes.clone())
+    }
+
+    async fn get_all_tags(&self) -> Result<Vec<Tag>> {
+        let state = self.state.read().expect("failed to lock store");
+        Ok(state.tags.clone())
+    }
+
+    async fn get_notes_filtered(
+        &self,
+        search_text: Option<String>,
+        tags: Option<Vec<String>>,
+        note_ids: Option<Vec<Uuid>>,
+        created: Option<Range<DateTime<Utc>>>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Vec<Note>> {
+        let state = self.state.read().expect("failed to lock store");
+        let search_text = search_text.unwrap_or_default();
+        let tags = tags.unwrap_or_default();
+
+        let mut matches = state
+            .notes
+            .iter()
+            .filter(|note| note_ids.as_ref().is_none_or(|ids| ids.contains(&note.id)))
+            .filter(|note| {
+                created
+                    .as_ref()
+                    .is_none_or(|created| created.contains(&note.created_at))
+            })
+            .filter(|note| {
+                let note_tags = state.note_tag_names(note.id);
+                tags.iter().all(|tag| note_tags.contains(&tag.as_str()))
+            })
+            .filter_map(|note| {
+                if search_text.is_empty() {
+                    return Some((0.0, note));
+                }
+                search_score(note, &search_text).map(|score| (score, note))
+            })
+            .collect::<Vec<_>>();
+
+        // imported notes can be older than ones created before them, so order by date first
+        matches.sort_by_key(|(_, note)| note.created_at);
+        // stable, so equally scored notes stay oldest first
+        matches.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        Ok(matches
+            .into_iter()
+            .map(|(_, note)| note.clone())
+            .skip(offset.unwrap_or(0) as usize)
+            .take(limit.map_or(usize::MAX, |limit| limit as usize))
+            .collect())
+    }
+
+    async fn get_note_dates_until(&self, until: NaiveDate) -> Result<Vec<NoteDate>> {
+        let state = self.state.read().expect("failed to lock store");
+
+        let mut note_dates = state
+            .note_dates
+            .iter()
+            .filter(|note_date| note_date.date <= until)
+            .cloned()
+            .collect::<Vec<_>>();
+        note_dates.sort_by_key(|note_date| (note_date.date, note_date.id));
+        Ok(note_dates)
+    }
+
+    async fn get_all_note_details(&self) -> Result<Vec<NoteWithDetails>> {
+        let state = self.state.read().expect("failed to lock store");
+
+        let mut notes = state.notes.clone();
+        notes.sort_by_key(|note| note.created_at);
+
+        let mut note_tags = state
+            .note_tags
+            .iter()
+            .filter_map(|(note_id, tag_id)| {
+                let tag = state.tags.iter().find(|tag| tag.id == *tag_id)?;
+                Some((*note_id, tag.clone()))
+            })
+            .collect::<Vec<_>>();
+        note_tags.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
+
+        let mut note_dates = state.note_dates.clone();
+        note_dates.sort_by_key(|note_date| note_date.date);
+
+        Ok(assemble_details(
+            notes,
+            note_tags,
+            note_dates,
+            state
+                .note_links
+                .iter()
+                .map(|link| (link.note1_id, link.note2_id))
+                .collect(),
+        ))
+    }
+
+    async fn get_note_details(&self, id: Uuid) -> Result<NoteWithDetails> {
+        let state = self.state.read().expect("failed to lock store");
+
+        let note = state
+            .notes
+            .iter()
+            .find(|note| note.id == id)
+            .cloned()
+            .ok_or(Error::NoteNotFound(id))?;
+
+        let mut tags = state
+            .note_tags
+            .iter()
+            .filter(|(note_id, _)| *note_id == id)
+            .filter_map(|(_, tag_id)| state.tags.iter().find(|tag| tag.id == *tag_id).cloned())
+            .collect::<Vec<_>>();
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut note_dates = state
+            .note_dates
+            .iter()
+            .filter(|note_date| note_date.note_id == id)
+            .cloned()
+            .collect::<Vec<_>>();
+        note_dates.sort_by_key(|note_date| note_date.date);
+
+        let mut note_links = state
+            .note_links
+            .iter()
+            .filter_map(|link| match (link.note1_id, link.note2_id) {
+                (a, b) if a == id => Some(b),
+                (a, b) if b == id => Some(a),
+                _ => None,
+            })
+            .filter_map(|linked| state.notes.iter().find(|note| note.id == linked).cloned())
+            .collect::<Vec<_>>();
+        note_links.sort_by_key(|note| note.created_at);
+
+        Ok(NoteWithDetails {
+            note,
+            tags,
+            note_dates,
+            note_links,
+        })
+    }
+
+    async fn upsert_note(&self, note: &NoteImport) -> Result<UpsertOutcome> {
+        let mut state = self.state.write().expect("failed to lock store");
+
+        let stored = Note {
+            id: note.id,
+            text: note.text.clone(),
+            title: note.title.clone(),
+            created_at: note.created_at,
+        };
+        let outcome = match state
+            .notes
+            .iter_mut()
+            .find(|existing| existing.id == note.id)
+        {
+            Some(existing) => {
+                *existing = stored;
+                UpsertOutcome::Updated
+            }
+            None => {
+                state.notes.push(stored);
+                UpsertOutcome::Created
+            }
+        };
+
+        state.note_tags.retain(|(note_id, _)| *note_id != note.id);
+        for name in &note.tags {
+            let tag_id = state.get_or_create_tag(name);
+            if !state.note_tags.contains(&(note.id, tag_id)) {
+                state.note_tags.push((note.id, tag_id));
+            }
+        }
+
+        let existing = state
+            .note_dates
+            .iter()
+            .filter(|note_date| note_date.note_id == note.id)
+            .cloned()
+            .collect::<Vec<_>>();
+        let reused = reused_date_ids(
+            &existing,
+            note.dates
+                .iter()
+                .map(|date| (None, date.label.as_deref(), date.date)),
+        );
+        state
+            .note_dates
+            .retain(|note_date| note_date.note_id != note.id);
+        for (date, reused) in note.dates.iter().zip(reused) {
+            state.note_dates.push(NoteDate {
+                id: reused.unwrap_or_else(Uuid::new_v4),
+                note_id: note.id,
+                label: date.label.clone(),
+                date: date.date,
+                rrule: date.rrule.clone(),
+            });
+        }
+        state.forget_deleted_reminders();
+
+        Ok(outcome)
+    }
+
+    async fn link_notes(&self, a: Uuid, b: Uuid) -> Result<()> {
+        let mut state = self.state.write().expect("failed to lock store");
+
+        for id in [a, b] {
+            if !state.notes.iter().any(|note| note.id == id) {
+                return Err(Error::NoteNotFound(id));
+            }
+        }
+
+        let (note1_id, note2_id) = link_key(a, b);
+        if !state
+            .note_links
+            .iter()
+            .any(|link| (link.note1_id, link.note2_id) == (note1_id, note2_id))
+        {
+            state.note_links.push(NoteLink {
+                note1_id,
+                note2_id,
+                created_at: Some(Utc::now()),
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn unlink_notes(&self, a: Uuid, b: Uuid) -> Result<()> {
+        let mut state = self.state.write().expect("failed to lock store");
+        let key = link_key(a, b);
+        state
+            .note_links
+            .retain(|link| (link.note1_id, link.note2_id) != key);
+        Ok(())
+    }
+
+    async fn snapshot(&self) -> Result<Snapshot> {
+        let state = self.state.read().expect("failed to lock store");
+
+        let mut snapshot = Snapshot {
+            notes: state.notes.clone(),
+            tags: state.tags.clone(),
+            note_tags: state
+                .note_tags
+                .iter()
+                .map(|&(note_id, tag_id)| NoteTag { note_id, tag_id })
+                .collect(),
+            note_dates: state.note_dates.clone(),
+            note_links: state.note_links.clone(),
+            reminder_states: state
+                .reminder_states
+                .iter()
+                .map(
+                    |(&(note_date_id, occurrence), reminder_state)| snapshot::ReminderState {
+                        note_date_id,
+                        occurrence,
+                        notified_at: reminder_state.notified_at,
+                        snoozed_until: reminder_state.snoozed_until,
+                        acknowledged_at: reminder_state.acknowledged_at,
+                    },
+                )
+                .collect(),
+        };
+
+        // the same order the other stores use
+        snapshot
+            .notes
+            .sort_by_key(|note| (note.created_at, note.id));
+        snapshot.tags.sort_by(|a, b| a.name.cmp(&b.name));
+        snapshot
+            .note_tags
+            .sort_by_key(|note_tag| (note_tag.note_id, note_tag.tag_id));
+        snapshot
+            .note_dates
+            .sort_by_key(|note_date| (note_date.note_id, note_date.date, note_date.id));
+        snapshot
+            .note_links
+            .sort_by_key(|link| (link.note1_id, link.note2_id));
+        snapshot
+            .reminder_states
+            .sort_by_key(|reminder_state| (reminder_state.note_date_id, reminder_state.occurrence));
+
+        Ok(snapshot)
+    }
+
+    async fn restore_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
+        let mut state = self.state.write().expect("failed to lock store");
+
+        for tag in &snapshot.tags {
+            if !state.tags.iter().any(|existing| existing.id == tag.id) {
+                state.tags.push(tag.clone());
+            }
+        }
+
+        let existing = state
+            .note_dates
+            .iter()
+            .filter(|note_date| {
+                snapshot
+                    .notes
+                    .iter()
+                    .any(|note| note.id == note_date.note_id)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let date_ids = snapshot.restored_date_ids(&existing);
+
+        for note in &snapshot.notes {
+            match state
+                .notes
+                .iter_mut()
+                .find(|existing| existing.id == note.id)
+            {
+                Some(existing) => *existing = note.clone(),
+                None => state.notes.push(note.clone()),
+            }
+            state.note_tags.retain(|(note_id, _)| *note_id != note.id);
+            state
+                .note_dates
+                .retain(|note_date| note_date.note_id != note.id);
+        }
+
+        for note_tag in &snapshot.note_tags {
+            let pair = (note_tag.note_id, note_tag.tag_id);
+            if !state.note_tags.contains(&pair) {
+                state.note_tags.push(pair);
+            }
+        }
+
+        for note_date in &snapshot.note_dates {
+            let id = *date_ids.get(&note_date.id).unwrap_or(&note_date.id);
+            state.note_dates.retain(|existing| existing.id != id);
+            state.note_dates.push(NoteDate {
+                id,
+                ..note_date.clone()
+            });
+        }
+        state.forget_deleted_reminders();
+
+        for reminder_state in &snapshot.reminder_states {
+            if let Some(&id) = date_ids.get(&reminder_state.note_date_id) {
+                state.reminder_states.insert(
+                    (id, reminder_state.occurrence),
+                    ReminderState {
+                        notified_at: reminder_state.notified_at,
+                        snoozed_until: reminder_state.snoozed_until,
+                        acknowledged_at: reminder_state.acknowledged_at,
+                    },
+                );
+            }
+        }
+
+        for link in &snapshot.note_links {
+            if !state.note_links.iter().any(|existing| {
+                (existing.note1_id, existing.note2_id) == (link.note1_id, link.note2_id)
+            }) {
+                state.note_links.push(link.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get_reminder_dates(
+        &self,
+        labels: &[String],
+        until: NaiveDate,
+    ) -> Result<Vec<ReminderDate>> {
+        let state = self.state.read().expect("failed to lock store");
+
+        Ok(state
+            .note_dates
+            .iter()
+            .filter(|note_date| note_date.date <= until)
+            .filter(|note_date| {
+                note_date
+                    .label
+                    .as_ref()
+                    .is_some_and(|label| labels.contains(&label.to_lowercase()))
+            })
+            .filter_map(|note_date| {
+                let note = state
+                    .notes
+                    .iter()
+                    .find(|note| note.id == note_date.note_id)?;
+                Some(ReminderDate {
+                    note: note.clone(),
+                    note_date: note_date.clone(),
+                })
+            })
+            .collect())
+    }
+
+    async fn get_reminder_states(&self, ids: &[Uuid]) -> Result<Vec<snapshot::ReminderState>> {
+        let state = self.state.read().expect("failed to lock store");
+
+        let mut states = state
+            .reminder_states
+            .iter()
+            .filter(|((note_date_id, _), _)| ids.contains(note_date_id))
+            .map(
+                |(&(note_date_id, occurrence), reminder_state)| snapshot::ReminderState {
+                    note_date_id,
+                    occurrence,
+                    notified_at: reminder_state.notified_at,
+                    snoozed_until: reminder_state.snoozed_until,
+                    acknowledged_at: reminder_state.acknowledged_at,
+                },
+            )
+            .collect::<Vec<_>>();
+        states
+            .sort_by_key(|reminder_state| (reminder_state.note_date_id, reminder_state.occurrence));
+        Ok(states)
+    }
+
+    async fn acknowledge_reminder(
+        &self,
+        id: Uuid,
+        date: NaiveDate,
+        at: DateTime<Utc>,
+    ) -> Result<()> {
+        let mut state = self.state.write().expect("failed to lock store");
+        state.reminder_state(id, date)?.acknowledged_at = Some(at);
+        Ok(())
+    }
+
+    async fn snooze_reminder(&self, id: Uuid, date: NaiveDate, until: DateTime<Utc>) -> Result<()> {
+        let mut state = self.state.write().expect("failed to lock store");
+        state.reminder_state(id, date)?.snoozed_until = Some(until);
+        Ok(())
+    }
+
+    async fn mark_reminders_notified(
+        &self,
+        occurrences: &[(Uuid, NaiveDate)],
+        at: DateTime<Utc>,
+    ) -> Result<()> {
+        let mut state = self.state.write().expect("failed to lock store");
+        for &(id, date) in occurrences {
+            if let Ok(reminder_state) = state.reminder_state(id, date) {
+                reminder_state.notified_at = Some(at);
+            }
+        }
+        Ok(())
+    }
+}
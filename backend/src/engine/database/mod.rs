@@ -1,15 +1,147 @@
+pub mod error;
+pub mod memory;
 pub mod notes;
+pub mod reminders;
 pub mod service;
+pub mod snapshot;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 pub mod tags;
+pub mod trigram;
 
-use notes::NotesRepository;
+use std::ops::Range;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use notes::{
+    NewNoteDate, Note, NoteDate, NoteImport, NoteWithDetails, NotesRepository, UpsertOutcome,
+};
+use reminders::{ReminderDate, RemindersRepository};
+use snapshot::{ReminderState, Snapshot, SnapshotRepository};
 use sqlx::PgPool;
-use tags::TagsRepository;
+use tags::{Tag, TagsRepository};
+use uuid::Uuid;
+
+use error::Result;
+
+/// Storage operations the engine relies on
+///
+/// Implementations must behave the same way, in particular search ranks notes by trigram
+/// similarity (see [`trigram`]) and only returns notes carrying every requested tag.
+#[async_trait]
+pub trait NoteStore: Send + Sync {
+    /// Fails with [`error::Error::TagNotFound`] if any tag doesn't exist yet. The engine passes
+    /// each tag once, see [`crate::engine::core::distinct_tags`].
+    async fn create_note(
+        &self,
+        text: &str,
+        title: Option<&str>,
+        tags: &[String],
+        dates: &[NewNoteDate],
+    ) -> Result<Note>;
+
+    /// Replaces the note's text and tags, keeping its title, dates and links
+    ///
+    /// Fails with [`error::Error::NoteNotFound`] if the note doesn't exist and with
+    /// [`error::Error::TagNotFound`] if any tag doesn't exist yet.
+    async fn update_note(&self, id: Uuid, text: &str, tags: &[String]) -> Result<Note>;
+
+    /// Deletes the note along with its tags, dates and links
+    ///
+    /// Fails with [`error::Error::NoteNotFound`] if the note doesn't exist.
+    async fn delete_note(&self, id: Uuid) -> Result<()>;
+
+    async fn create_tag(&self, name: &str) -> Result<Tag>;
+
+    async fn get_all_notes(&self) -> Result<Vec<Note>>;
+
+    async fn get_all_tags(&self) -> Result<Vec<Tag>>;
+
+    /// Notes matching the search text and carrying every tag, among `note_ids` if given and
+    /// created within `created` if given
+    async fn get_notes_filtered(
+        &self,
+        search_text: Option<String>,
+        tags: Option<Vec<String>>,
+        note_ids: Option<Vec<Uuid>>,
+        created: Option<Range<DateTime<Utc>>>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Vec<Note>>;
+
+    /// Every note date on or before `until`, which includes every recurring date that may have
+    /// an occurrence by then
+    async fn get_note_dates_until(&self, until: NaiveDate) -> Result<Vec<NoteDate>>;
+
+    /// Every note with its tags, dates and links, oldest first
+    async fn get_all_note_details(&self) -> Result<Vec<NoteWithDetails>>;
+
+    /// The note with its tags, dates and links, linked notes oldest first
+    ///
+    /// Fails with [`error::Error::NoteNotFound`] if the note doesn't exist.
+    async fn get_note_details(&self, id: Uuid) -> Result<NoteWithDetails>;
+
+    /// Creates the note, or overwrites the one with the same id along with its tags and dates
+    ///
+    /// Unlike [`NoteStore::create_note`], missing tags are created on the way.
+    async fn upsert_note(&self, note: &NoteImport) -> Result<UpsertOutcome>;
+
+    /// Links two different existing notes, doing nothing if they're already linked
+    async fn link_notes(&self, a: Uuid, b: Uuid) -> Result<()>;
+
+    /// Removes the link between two notes, doing nothing if they aren't linked
+    async fn unlink_notes(&self, a: Uuid, b: Uuid) -> Result<()>;
+
+    /// Every row of every table, ids and all
+    async fn snapshot(&self) -> Result<Snapshot>;
+
+    /// Writes the snapshot's rows in one transaction, overwriting notes with the same ids and
+    /// replacing their tags and dates
+    ///
+    /// Dates that stay keep their ids and reminder states, unless the snapshot has states for
+    /// them (see [`Snapshot::restored_date_ids`]).
+    ///
+    /// Tags are matched by id, so the caller must map tags whose name already exists onto the
+    /// existing ids first (see [`crate::engine::archive::backup::restore`]).
+    async fn restore_snapshot(&self, snapshot: &Snapshot) -> Result<()>;
+
+    /// Dates up to `until` whose label is one of `labels` (lowercase, compared
+    /// case-insensitively), with their notes
+    async fn get_reminder_dates(
+        &self,
+        labels: &[String],
+        until: NaiveDate,
+    ) -> Result<Vec<ReminderDate>>;
+
+    /// The states of every occurrence of these note dates
+    async fn get_reminder_states(&self, ids: &[Uuid]) -> Result<Vec<ReminderState>>;
+
+    /// Fails with [`error::Error::ReminderNotFound`] if the note date doesn't exist
+    async fn acknowledge_reminder(
+        &self,
+        id: Uuid,
+        date: NaiveDate,
+        at: DateTime<Utc>,
+    ) -> Result<()>;
+
+    /// Fails with [`error::Error::ReminderNotFound`] if the note date doesn't exist
+    async fn snooze_reminder(&self, id: Uuid, date: NaiveDate, until: DateTime<Utc>) -> Result<()>;
+
+    /// Records that clients were told about these occurrences, skipping unknown note dates
+    async fn mark_reminders_notified(
+        &self,
+        occurrences: &[(Uuid, NaiveDate)],
+        at: DateTime<Utc>,
+    ) -> Result<()>;
+}
 
+/// Postgres-backed store
 #[derive(Clone)]
 pub struct Database {
     pub notes: NotesRepository,
     pub tags: TagsRepository,
+    pub snapshots: SnapshotRepository,
+    pub reminders: RemindersRepository,
 }
 
 impl Database {
@@ -17,6 +149,8 @@ impl Database {
         Self {
             notes: NotesRepository::new(pool.clone()),
             tags: TagsRepository::new(pool.clone()),
+            snapshots: SnapshotRepository::new(pool.clone()),
+            reminders: RemindersRepository::new(pool.clone()),
         }
     }
 }
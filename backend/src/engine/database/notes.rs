@@ -1,26 +1,169 @@
-use chrono::NaiveDateTime;
-use sqlx::{PgPool, Result};
+use std::{collections::HashMap, ops::Range};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
-pub use rust_shared::response::Note;
+pub use rust_shared::response::{Note, NoteDate, NoteWithDetails};
 
+use super::{
+    error::{Error, Result},
+    trigram,
+};
 // add import for tags
 use super::tags::Tag;
 
-#[allow(dead_code)]
-pub struct NoteDate {
+/// A labeled date to attach to a note, before it gets an id
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewNoteDate {
+    pub label: Option<String>,
+    pub date: NaiveDate,
+    pub rrule: Option<String>,
+}
+
+/// A complete note coming from outside, e.g. an import, which keeps its own id
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoteImport {
     pub id: Uuid,
-    pub note_id: Uuid,
-    pub label: String,
-    pub date: NaiveDateTime,
+    pub text: String,
+    pub title: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub tags: Vec<String>,
+    pub dates: Vec<NewNoteDate>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UpsertOutcome {
+    Created,
+    Updated,
+}
+
+/// What search weighs as a note's title, the given one or else its first line, like
+/// `queries/note_list_search.sql` does
+pub fn search_title(note: &Note) -> &str {
+    note.title.as_deref().unwrap_or_else(|| {
+        let text = note.text.trim_start_matches([' ', '\t', '\r', '\n', '#']);
+        text.split('\n').next().unwrap_or_default()
+    })
+}
+
+/// How well a note matches `search_text`, ranked like `queries/note_list_search.sql` does, or
+/// `None` if neither its text nor its title is similar enough
+pub fn search_score(note: &Note, search_text: &str) -> Option<f32> {
+    let text = trigram::similarity(&note.text, search_text);
+    let title = trigram::similarity(search_title(note), search_text);
+    (text >= trigram::SIMILARITY_THRESHOLD || title >= trigram::SIMILARITY_THRESHOLD)
+        .then_some(text + title)
+}
+
+/// Orders a link's notes the way `note_links` stores them
+pub fn link_key(a: Uuid, b: Uuid) -> (Uuid, Uuid) {
+    if a < b { (a, b) } else { (b, a) }
 }
 
-#[allow(dead_code)]
-pub struct NoteWithDetails {
-    pub note: Note,
-    pub tags: Vec<Tag>,
-    pub note_dates: Vec<NoteDate>,
-    pub note_links: Vec<Note>,
+/// Which of a note's `existing` dates each of its new `dates` replaces, if any: the one with
+/// the same id, or else one with the same label and date
+///
+/// Replacing a date keeps its id, and with it its reminder state, rather than starting over.
+/// Only dates from a backup come with ids of their own.
+pub fn reused_date_ids<'a>(
+    existing: &[NoteDate],
+    dates: impl IntoIterator<Item = (Option<Uuid>, Option<&'a str>, NaiveDate)>,
+) -> Vec<Option<Uuid>> {
+    let dates = dates.into_iter().collect::<Vec<_>>();
+    let mut unused = existing.iter().collect::<Vec<_>>();
+    let mut take = |matches: &dyn Fn(&NoteDate) -> bool| {
+        let i = unused.iter().position(|&note_date| matches(note_date))?;
+        Some(unused.remove(i).id)
+    };
+
+    // ids first, so a same labeled date doesn't take the one a later date has the id of
+    let mut reused = dates
+        .iter()
+        .map(|&(id, _, _)| take(&|note_date| Some(note_date.id) == id))
+        .collect::<Vec<_>>();
+    for (reused, &(_, label, date)) in reused.iter_mut().zip(&dates) {
+        if reused.is_none() {
+            *reused =
+                take(&|note_date| note_date.label.as_deref() == label && note_date.date == date);
+        }
+    }
+    reused
+}
+
+/// Groups flat rows from the note tables into one entry per note, in the order of `notes`
+pub fn assemble_details(
+    notes: Vec<Note>,
+    note_tags: Vec<(Uuid, Tag)>,
+    note_dates: Vec<NoteDate>,
+    note_links: Vec<(Uuid, Uuid)>,
+) -> Vec<NoteWithDetails> {
+    let notes_by_id = notes
+        .iter()
+        .map(|note| (note.id, note.clone()))
+        .collect::<HashMap<_, _>>();
+
+    let mut details = notes
+        .into_iter()
+        .map(|note| {
+            (
+                note.id,
+                NoteWithDetails {
+                    note,
+                    tags: Vec::new(),
+                    note_dates: Vec::new(),
+                    note_links: Vec::new(),
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+    let index = details
+        .iter()
+        .enumerate()
+        .map(|(i, (id, _))| (*id, i))
+        .collect::<HashMap<_, _>>();
+
+    for (note_id, tag) in note_tags {
+        if let Some(&i) = index.get(&note_id) {
+            details[i].1.tags.push(tag);
+        }
+    }
+
+    for note_date in note_dates {
+        if let Some(&i) = index.get(&note_date.note_id) {
+            details[i].1.note_dates.push(note_date);
+        }
+    }
+
+    // links are bidirectional, so each one shows up on both notes
+    for (a, b) in note_links {
+        for (from, to) in [(a, b), (b, a)] {
+            if let (Some(&i), Some(linked)) = (index.get(&from), notes_by_id.get(&to)) {
+                details[i].1.note_links.push(linked.clone());
+            }
+        }
+    }
+
+    details.into_iter().map(|(_, details)| details).collect()
+}
+
+/// Tags the note with every tag, failing with [`Error::TagNotFound`] on the first unknown one
+async fn tag_note(conn: &mut PgConnection, note_id: Uuid, tags: &[String]) -> Result<()> {
+    for tag in tags {
+        let tag = sqlx::query_as!(Tag, "SELECT * FROM tags WHERE name = $1", tag)
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or_else(|| Error::TagNotFound(tag.clone()))?;
+
+        sqlx::query!(
+            "INSERT INTO note_tags (note_id, tag_id) VALUES ($1, $2)",
+            note_id,
+            tag.id
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
 }
 
 #[derive(Clone)]
@@ -33,52 +176,326 @@ impl NotesRepository {
         Self { pool }
     }
 
-    pub async fn create(&self, text: &str, tags: &Vec<String>) -> Result<Note> {
+    pub async fn create(
+        &self,
+        text: &str,
+        title: Option<&str>,
+        tags: &[String],
+        dates: &[NewNoteDate],
+    ) -> Result<Note> {
+        // an unknown tag rolls back the whole note
+        let mut tx = self.pool.begin().await?;
+
         let note = sqlx::query_as!(
             Note,
-            "INSERT INTO notes (text) VALUES ($1) RETURNING *",
-            text
+            "INSERT INTO notes (text, title) VALUES ($1, $2) RETURNING *",
+            text,
+            title
         )
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *tx)
         .await?;
 
-        for tag in tags {
-            let tag = sqlx::query_as!(Tag, "SELECT * FROM tags WHERE name = $1", tag)
-                .fetch_one(&self.pool)
-                .await?;
+        tag_note(&mut tx, note.id, tags).await?;
 
+        for date in dates {
             sqlx::query!(
-                "INSERT INTO note_tags (note_id, tag_id) VALUES ($1, $2)",
+                "INSERT INTO note_dates (note_id, label, date, rrule) VALUES ($1, $2, $3, $4)",
                 note.id,
-                tag.id
+                date.label,
+                date.date,
+                date.rrule
             )
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
         }
 
+        tx.commit().await?;
+        Ok(note)
+    }
+
+    pub async fn update(&self, id: Uuid, text: &str, tags: &[String]) -> Result<Note> {
+        // an unknown tag rolls back the whole edit
+        let mut tx = self.pool.begin().await?;
+
+        let note = sqlx::query_as!(
+            Note,
+            "UPDATE notes SET text = $2 WHERE id = $1 RETURNING *",
+            id,
+            text
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(Error::NoteNotFound(id))?;
+
+        sqlx::query!("DELETE FROM note_tags WHERE note_id = $1", id)
+            .execute(&mut *tx)
+            .await?;
+        tag_note(&mut tx, id, tags).await?;
+
+        tx.commit().await?;
         Ok(note)
     }
 
+    pub async fn delete(&self, id: Uuid) -> Result<()> {
+        // tags, dates, links and reminder states go with it, see the schema's `ON DELETE CASCADE`s
+        let deleted = sqlx::query!("DELETE FROM notes WHERE id = $1", id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        if deleted == 0 {
+            return Err(Error::NoteNotFound(id));
+        }
+        Ok(())
+    }
+
     pub async fn get_all(&self) -> Result<Vec<Note>> {
-        sqlx::query_as!(Note, "SELECT * FROM notes")
+        Ok(sqlx::query_as!(Note, "SELECT * FROM notes")
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    pub async fn get_all_details(&self) -> Result<Vec<NoteWithDetails>> {
+        let notes = sqlx::query_as!(Note, "SELECT * FROM notes ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let note_tags = sqlx::query!(
+            "SELECT nt.note_id, t.id, t.name
+             FROM note_tags nt
+             JOIN tags t ON nt.tag_id = t.id
+             ORDER BY t.name"
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| {
+            (
+                row.note_id,
+                Tag {
+                    id: row.id,
+                    name: row.name,
+                },
+            )
+        })
+        .collect();
+
+        let note_dates = sqlx::query_as!(NoteDate, "SELECT * FROM note_dates ORDER BY date")
             .fetch_all(&self.pool)
-            .await
+            .await?;
+
+        let note_links = sqlx::query!("SELECT note1_id, note2_id FROM note_links")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|row| (row.note1_id, row.note2_id))
+            .collect();
+
+        Ok(assemble_details(notes, note_tags, note_dates, note_links))
+    }
+
+    pub async fn get_details(&self, id: Uuid) -> Result<NoteWithDetails> {
+        let note = sqlx::query_as!(Note, "SELECT * FROM notes WHERE id = $1", id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(Error::NoteNotFound(id))?;
+
+        let tags = sqlx::query_as!(
+            Tag,
+            "SELECT t.id, t.name
+             FROM note_tags nt
+             JOIN tags t ON nt.tag_id = t.id
+             WHERE nt.note_id = $1
+             ORDER BY t.name",
+            id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let note_dates = sqlx::query_as!(
+            NoteDate,
+            "SELECT * FROM note_dates WHERE note_id = $1 ORDER BY date",
+            id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        // links are stored once, with either note on either side
+        let note_links = sqlx::query_as!(
+            Note,
+            "SELECT n.*
+             FROM note_links l
+             JOIN notes n ON n.id = CASE WHEN l.note1_id = $1 THEN l.note2_id ELSE l.note1_id END
+             WHERE $1 IN (l.note1_id, l.note2_id)
+             ORDER BY n.created_at",
+            id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(NoteWithDetails {
+            note,
+            tags,
+            note_dates,
+            note_links,
+        })
+    }
+
+    pub async fn upsert(&self, note: &NoteImport) -> Result<UpsertOutcome> {
+        let mut tx = self.pool.begin().await?;
+
+        // xmax is only 0 for freshly inserted rows
+        let inserted = sqlx::query_scalar!(
+            r#"INSERT INTO notes (id, text, title, created_at) VALUES ($1, $2, $3, $4)
+               ON CONFLICT (id) DO UPDATE
+               SET text = EXCLUDED.text, title = EXCLUDED.title, created_at = EXCLUDED.created_at
+               RETURNING (xmax = 0) AS "inserted!""#,
+            note.id,
+            note.text,
+            note.title,
+            note.created_at
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query!("DELETE FROM note_tags WHERE note_id = $1", note.id)
+            .execute(&mut *tx)
+            .await?;
+
+        for tag in &note.tags {
+            // the no-op update makes RETURNING work for existing tags too
+            let tag_id = sqlx::query_scalar!(
+                "INSERT INTO tags (name) VALUES ($1)
+                 ON CONFLICT (name) DO UPDATE SET name = EXCLUDED.name
+                 RETURNING id",
+                tag
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            sqlx::query!(
+                "INSERT INTO note_tags (note_id, tag_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+                note.id,
+                tag_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let existing = sqlx::query_as!(
+            NoteDate,
+            "SELECT * FROM note_dates WHERE note_id = $1",
+            note.id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let reused = reused_date_ids(
+            &existing,
+            note.dates
+                .iter()
+                .map(|date| (None, date.label.as_deref(), date.date)),
+        );
+        let kept = reused.iter().flatten().copied().collect::<Vec<_>>();
+        sqlx::query!(
+            "DELETE FROM note_dates WHERE note_id = $1 AND id <> ALL($2)",
+            note.id,
+            &kept
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        for (date, reused) in note.dates.iter().zip(reused) {
+            match reused {
+                Some(id) => {
+                    sqlx::query!(
+                        "UPDATE note_dates SET rrule = $2 WHERE id = $1",
+                        id,
+                        date.rrule
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                None => {
+                    sqlx::query!(
+                        "INSERT INTO note_dates (note_id, label, date, rrule)
+                         VALUES ($1, $2, $3, $4)",
+                        note.id,
+                        date.label,
+                        date.date,
+                        date.rrule
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+        }
+
+        tx.commit().await?;
+        Ok(if inserted {
+            UpsertOutcome::Created
+        } else {
+            UpsertOutcome::Updated
+        })
+    }
+
+    pub async fn get_dates_until(&self, until: NaiveDate) -> Result<Vec<NoteDate>> {
+        Ok(sqlx::query_as!(
+            NoteDate,
+            "SELECT * FROM note_dates WHERE date <= $1 ORDER BY date, id",
+            until
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    pub async fn link(&self, a: Uuid, b: Uuid) -> Result<()> {
+        let (note1_id, note2_id) = link_key(a, b);
+        sqlx::query!(
+            "INSERT INTO note_links (note1_id, note2_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            note1_id,
+            note2_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn unlink(&self, a: Uuid, b: Uuid) -> Result<()> {
+        let (note1_id, note2_id) = link_key(a, b);
+        sqlx::query!(
+            "DELETE FROM note_links WHERE note1_id = $1 AND note2_id = $2",
+            note1_id,
+            note2_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
     }
 
     pub async fn get_filtered(
         &self,
         search_text: Option<String>,
         tags: Vec<String>,
-        _limit: Option<u32>,
-        _offset: Option<u32>,
+        note_ids: Option<Vec<Uuid>>,
+        created: Option<Range<DateTime<Utc>>>,
+        limit: Option<u32>,
+        offset: Option<u32>,
     ) -> Result<Vec<Note>> {
-        sqlx::query_file_as!(
+        let (created_from, created_until) =
+            created.map(|created| (created.start, created.end)).unzip();
+
+        Ok(sqlx::query_file_as!(
             Note,
             "queries/note_list_search.sql",
             search_text.unwrap_or_default(),
             &tags,
+            limit.map(i64::from),
+            offset.map(i64::from),
+            note_ids.as_deref(),
+            created_from,
+            created_until,
         )
         .fetch_all(&self.pool)
-        .await
+        .await?)
     }
 }
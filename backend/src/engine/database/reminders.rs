@@ -0,0 +1,166 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+pub use rust_shared::response::Reminder;
+
+use super::{
+    error::{Error, Result},
+    notes::{Note, NoteDate},
+    snapshot::ReminderState,
+};
+
+/// A reminder that hasn't been acknowledged, with when clients last heard of it
+#[derive(Debug, Clone)]
+pub struct PendingReminder {
+    pub reminder: Reminder,
+    pub notified_at: Option<DateTime<Utc>>,
+}
+
+/// A reminder-labeled note date with its note
+#[derive(Debug, Clone)]
+pub struct ReminderDate {
+    pub note: Note,
+    pub note_date: NoteDate,
+}
+
+/// A note date joined with its note
+#[derive(Debug, FromRow)]
+pub(super) struct ReminderDateRow {
+    pub id: Uuid,
+    pub note_id: Uuid,
+    pub text: String,
+    pub title: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub label: Option<String>,
+    pub date: NaiveDate,
+    pub rrule: Option<String>,
+}
+
+impl From<ReminderDateRow> for ReminderDate {
+    fn from(row: ReminderDateRow) -> Self {
+        Self {
+            note: Note {
+                id: row.note_id,
+                text: row.text,
+                title: row.title,
+                created_at: row.created_at,
+            },
+            note_date: NoteDate {
+                id: row.id,
+                note_id: row.note_id,
+                label: row.label,
+                date: row.date,
+                rrule: row.rrule,
+            },
+        }
+    }
+}
+
+/// Maps a foreign key violation on `reminder_states` to the missing note date
+pub(super) fn reminder_not_found(id: Uuid) -> impl FnOnce(sqlx::Error) -> Error {
+    move |e| match e {
+        sqlx::Error::Database(db_error) if db_error.is_foreign_key_violation() => {
+            Error::ReminderNotFound(id)
+        }
+        e => e.into(),
+    }
+}
+
+#[derive(Clone)]
+pub struct RemindersRepository {
+    pool: PgPool,
+}
+
+impl RemindersRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get_dates(
+        &self,
+        labels: &[String],
+        until: NaiveDate,
+    ) -> Result<Vec<ReminderDate>> {
+        let rows = sqlx::query_as!(
+            ReminderDateRow,
+            "SELECT nd.id, nd.note_id, n.text, n.title, n.created_at, nd.label, nd.date, nd.rrule
+            FROM note_dates nd
+            JOIN notes n ON n.id = nd.note_id
+            WHERE nd.date <= $1
+            AND lower(nd.label) = ANY($2)
+            ORDER BY nd.date, n.created_at, nd.id",
+            until,
+            labels
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(ReminderDate::from).collect())
+    }
+
+    pub async fn get_states(&self, ids: &[Uuid]) -> Result<Vec<ReminderState>> {
+        let states = sqlx::query_as!(
+            ReminderState,
+            "SELECT * FROM reminder_states WHERE note_date_id = ANY($1)
+             ORDER BY note_date_id, occurrence",
+            ids
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(states)
+    }
+
+    pub async fn acknowledge(&self, id: Uuid, date: NaiveDate, at: DateTime<Utc>) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO reminder_states (note_date_id, occurrence, acknowledged_at)
+             VALUES ($1, $2, $3)
+             ON CONFLICT (note_date_id, occurrence)
+             DO UPDATE SET acknowledged_at = EXCLUDED.acknowledged_at",
+            id,
+            date,
+            at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(reminder_not_found(id))?;
+        Ok(())
+    }
+
+    pub async fn snooze(&self, id: Uuid, date: NaiveDate, until: DateTime<Utc>) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO reminder_states (note_date_id, occurrence, snoozed_until)
+             VALUES ($1, $2, $3)
+             ON CONFLICT (note_date_id, occurrence)
+             DO UPDATE SET snoozed_until = EXCLUDED.snoozed_until",
+            id,
+            date,
+            until
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(reminder_not_found(id))?;
+        Ok(())
+    }
+
+    pub async fn mark_notified(
+        &self,
+        occurrences: &[(Uuid, NaiveDate)],
+        at: DateTime<Utc>,
+    ) -> Result<()> {
+        let (ids, dates): (Vec<_>, Vec<_>) = occurrences.iter().copied().unzip();
+        sqlx::query!(
+            "INSERT INTO reminder_states (note_date_id, occurrence, notified_at)
+             SELECT nd.id, occurrence.date, $3
+             FROM UNNEST($1::uuid[], $2::date[]) AS occurrence (id, date)
+             JOIN note_dates nd ON nd.id = occurrence.id
+             ON CONFLICT (note_date_id, occurrence) DO UPDATE SET notified_at = EXCLUDED.notified_at",
+            &ids,
+            &dates,
+            at
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
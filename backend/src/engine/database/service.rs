@@ -1,33 +1,133 @@
-use sqlx::Result;
+use std::ops::Range;
 
-use super::{Database, notes::Note, tags::Tag};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use uuid::Uuid;
 
-impl Database {
-    pub async fn create_note(&self, text: &str, tags: &Vec<String>) -> Result<Note> {
-        self.notes.create(text, tags).await
+use super::{
+    Database, NoteStore,
+    error::Result,
+    notes::{NewNoteDate, Note, NoteDate, NoteImport, NoteWithDetails, UpsertOutcome},
+    reminders::ReminderDate,
+    snapshot::{ReminderState, Snapshot},
+    tags::Tag,
+};
+
+#[async_trait]
+impl NoteStore for Database {
+    async fn create_note(
+        &self,
+        text: &str,
+        title: Option<&str>,
+        tags: &[String],
+        dates: &[NewNoteDate],
+    ) -> Result<Note> {
+        self.notes.create(text, title, tags, dates).await
     }
 
-    pub async fn create_tag(&self, name: &str) -> Result<Tag> {
+    async fn update_note(&self, id: Uuid, text: &str, tags: &[String]) -> Result<Note> {
+        self.notes.update(id, text, tags).await
+    }
+
+    async fn delete_note(&self, id: Uuid) -> Result<()> {
+        self.notes.delete(id).await
+    }
+
+    async fn create_tag(&self, name: &str) -> Result<Tag> {
         self.tags.create(name).await
     }
 
-    pub async fn get_all_notes(&self) -> Result<Vec<Note>> {
+    async fn get_all_notes(&self) -> Result<Vec<Note>> {
         self.notes.get_all().await
     }
 
-    pub async fn get_all_tags(&self) -> Result<Vec<Tag>> {
+    async fn get_all_tags(&self) -> Result<Vec<Tag>> {
         self.tags.get_all().await
     }
 
-    pub async fn get_notes_filtered(
+    async fn get_notes_filtered(
         &self,
         search_text: Option<String>,
         tags: Option<Vec<String>>,
+        note_ids: Option<Vec<Uuid>>,
+        created: Option<Range<DateTime<Utc>>>,
         limit: Option<u32>,
         offset: Option<u32>,
     ) -> Result<Vec<Note>> {
         self.notes
-            .get_filtered(search_text, tags.unwrap_or_default(), limit, offset)
+            .get_filtered(
+                search_text,
+                tags.unwrap_or_default(),
+                note_ids,
+                created,
+                limit,
+                offset,
+            )
             .await
     }
+
+    async fn get_note_dates_until(&self, until: NaiveDate) -> Result<Vec<NoteDate>> {
+        self.notes.get_dates_until(until).await
+    }
+
+    async fn get_all_note_details(&self) -> Result<Vec<NoteWithDetails>> {
+        self.notes.get_all_details().await
+    }
+
+    async fn get_note_details(&self, id: Uuid) -> Result<NoteWithDetails> {
+        self.notes.get_details(id).await
+    }
+
+    async fn upsert_note(&self, note: &NoteImport) -> Result<UpsertOutcome> {
+        self.notes.upsert(note).await
+    }
+
+    async fn link_notes(&self, a: Uuid, b: Uuid) -> Result<()> {
+        self.notes.link(a, b).await
+    }
+
+    async fn unlink_notes(&self, a: Uuid, b: Uuid) -> Result<()> {
+        self.notes.unlink(a, b).await
+    }
+
+    async fn snapshot(&self) -> Result<Snapshot> {
+        self.snapshots.take().await
+    }
+
+    async fn restore_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
+        self.snapshots.restore(snapshot).await
+    }
+
+    async fn get_reminder_dates(
+        &self,
+        labels: &[String],
+        until: NaiveDate,
+    ) -> Result<Vec<ReminderDate>> {
+        self.reminders.get_dates(labels, until).await
+    }
+
+    async fn get_reminder_states(&self, ids: &[Uuid]) -> Result<Vec<ReminderState>> {
+        self.reminders.get_states(ids).await
+    }
+
+    async fn acknowledge_reminder(
+        &self,
+        id: Uuid,
+        date: NaiveDate,
+        at: DateTime<Utc>,
+    ) -> Result<()> {
+        self.reminders.acknowledge(id, date, at).await
+    }
+
+    async fn snooze_reminder(&self, id: Uuid, date: NaiveDate, until: DateTime<Utc>) -> Result<()> {
+        self.reminders.snooze(id, date, until).await
+    }
+
+    async fn mark_reminders_notified(
+        &self,
+        occurrences: &[(Uuid, NaiveDate)],
+        at: DateTime<Utc>,
+    ) -> Result<()> {
+        self.reminders.mark_notified(occurrences, at).await
+    }
 }
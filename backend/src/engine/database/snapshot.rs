@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use super::{
+    error::Result,
+    notes::{Note, NoteDate, reused_date_ids},
+    tags::Tag,
+};
+
+#[derive(Debug, Clone, PartialEq, FromRow, Serialize, Deserialize)]
+pub struct NoteTag {
+    pub note_id: Uuid,
+    pub tag_id: Uuid,
+}
+
+#[derive(Debug, Clone, PartialEq, FromRow, Serialize, Deserialize)]
+pub struct NoteLink {
+    pub note1_id: Uuid,
+    pub note2_id: Uuid,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, FromRow, Serialize, Deserialize)]
+pub struct ReminderState {
+    pub note_date_id: Uuid,
+    /// Which occurrence of the note date this is the state of
+    pub occurrence: NaiveDate,
+    pub notified_at: Option<DateTime<Utc>>,
+    pub snoozed_until: Option<DateTime<Utc>>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+}
+
+/// Every row of every table, as stored
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    pub notes: Vec<Note>,
+    pub tags: Vec<Tag>,
+    pub note_tags: Vec<NoteTag>,
+    pub note_dates: Vec<NoteDate>,
+    pub note_links: Vec<NoteLink>,
+    pub reminder_states: Vec<ReminderState>,
+}
+
+impl Snapshot {
+    /// The id each of the snapshot's note dates is restored under, given the `existing` dates of
+    /// the notes it restores: that of the date it replaces (see [`reused_date_ids`]), or else its
+    /// own
+    pub fn restored_date_ids(&self, existing: &[NoteDate]) -> HashMap<Uuid, Uuid> {
+        let mut ids = HashMap::new();
+        for note in &self.notes {
+            let existing = existing
+                .iter()
+                .filter(|note_date| note_date.note_id == note.id)
+                .cloned()
+                .collect::<Vec<_>>();
+            let dates = self
+                .note_dates
+                .iter()
+                .filter(|note_date| note_date.note_id == note.id)
+                .collect::<Vec<_>>();
+            let reused = reused_date_ids(
+                &existing,
+                dates.iter().map(|note_date| {
+                    (
+                        Some(note_date.id),
+                        note_date.label.as_deref(),
+                        note_date.date,
+                    )
+                }),
+            );
+            for (note_date, reused) in dates.into_iter().zip(reused) {
+                ids.insert(note_date.id, reused.unwrap_or(note_date.id));
+            }
+        }
+        ids
+    }
+}
+
+#[derive(Clone)]
+pub struct SnapshotRepository {
+    pool: PgPool,
+}
+
+impl SnapshotRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn take(&self) -> Result<Snapshot> {
+        // one transaction, so the tables agree with each other
+        let mut tx = self.pool.begin().await?;
+
+        let notes = sqlx::query_as!(Note, "SELECT * FROM notes ORDER BY created_at, id")
+            .fetch_all(&mut *tx)
+            .await?;
+        let tags = sqlx::query_as!(Tag, "SELECT * FROM tags ORDER BY name")
+            .fetch_all(&mut *tx)
+            .await?;
+        let note_tags =
+            sqlx::query_as!(NoteTag, "SELECT * FROM note_tags ORDER BY note_id, tag_id")
+                .fetch_all(&mut *tx)
+                .await?;
+        let note_dates = sqlx::query_as!(
+            NoteDate,
+            "SELECT * FROM note_dates ORDER BY note_id, date, id"
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let note_links = sqlx::query_as!(
+            NoteLink,
+            "SELECT * FROM note_links ORDER BY note1_id, note2_id"
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let reminder_states = sqlx::query_as!(
+            ReminderState,
+            "SELECT * FROM reminder_states ORDER BY note_date_id, occurrence"
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(Snapshot {
+            notes,
+            tags,
+            note_tags,
+            note_dates,
+            note_links,
+            reminder_states,
+        })
+    }
+
+    pub async fn restore(&self, snapshot: &Snapshot) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for tag in &snapshot.tags {
+            sqlx::query!(
+                "INSERT INTO tags (id, name) VALUES ($1, $2) ON CONFLICT (id) DO NOTHING",
+                tag.id,
+                tag.name
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let note_ids = snapshot
+            .notes
+            .iter()
+            .map(|note| note.id)
+            .collect::<Vec<_>>();
+        for note in &snapshot.notes {
+            sqlx::query!(
+                "INSERT INTO notes (id, text, title, created_at) VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (id) DO UPDATE
+                 SET text = EXCLUDED.text, title = EXCLUDED.title, created_at = EXCLUDED.created_at",
+                note.id,
+                note.text,
+                note.title,
+                note.created_at
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // the restored notes get exactly the snapshot's tags and dates, the dates they keep
+        // keep their ids and reminder states
+        let existing = sqlx::query_as!(
+            NoteDate,
+            "SELECT * FROM note_dates WHERE note_id = ANY($1)",
+            &note_ids
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let date_ids = snapshot.restored_date_ids(&existing);
+        let kept = date_ids.values().copied().collect::<Vec<_>>();
+        sqlx::query!("DELETE FROM note_tags WHERE note_id = ANY($1)", &note_ids)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            "DELETE FROM note_dates WHERE note_id = ANY($1) AND id <> ALL($2)",
+            &note_ids,
+            &kept
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        for note_tag in &snapshot.note_tags {
+            sqlx::query!(
+                "INSERT INTO note_tags (note_id, tag_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+                note_tag.note_id,
+                note_tag.tag_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for note_date in &snapshot.note_dates {
+            sqlx::query!(
+                "INSERT INTO note_dates (id, note_id, label, date, rrule) VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (id) DO UPDATE
+                 SET note_id = EXCLUDED.note_id, label = EXCLUDED.label, date = EXCLUDED.date,
+                     rrule = EXCLUDED.rrule",
+                date_ids.get(&note_date.id).unwrap_or(&note_date.id),
+                note_date.note_id,
+                note_date.label,
+                note_date.date,
+                note_date.rrule
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for reminder_state in &snapshot.reminder_states {
+            let Some(note_date_id) = date_ids.get(&reminder_state.note_date_id) else {
+                continue;
+            };
+            sqlx::query!(
+                "INSERT INTO reminder_states
+                 (note_date_id, occurrence, notified_at, snoozed_until, acknowledged_at)
+                 VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (note_date_id, occurrence) DO UPDATE
+                 SET notified_at = EXCLUDED.notified_at, snoozed_until = EXCLUDED.snoozed_until,
+                     acknowledged_at = EXCLUDED.acknowledged_at",
+                note_date_id,
+                reminder_state.occurrence,
+                reminder_state.notified_at,
+                reminder_state.snoozed_until,
+                reminder_state.acknowledged_at
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for note_link in &snapshot.note_links {
+            sqlx::query!(
+                "INSERT INTO note_links (note1_id, note2_id, created_at) VALUES ($1, $2, $3)
+                 ON CONFLICT DO NOTHING",
+                note_link.note1_id,
+                note_link.note2_id,
+                note_link.created_at
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,765 @@
+use std::{collections::HashSet, ops::Range, str::FromStr};
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{
+    SqlitePool,
+    sqlite::{SqliteConnectOptions, SqliteConnection},
+};
+use uuid::Uuid;
+
+use super::{
+    NoteStore,
+    error::{Error, Result},
+    notes::{
+        NewNoteDate, Note, NoteDate, NoteImport, NoteWithDetails, UpsertOutcome, assemble_details,
+        link_key, reused_date_ids, search_score,
+    },
+    reminders::{ReminderDate, ReminderDateRow, reminder_not_found},
+    snapshot::{NoteLink, NoteTag, ReminderState, Snapshot},
+    tags::Tag,
+    trigram,
+};
+
+/// SQLite-backed store for single-user installs that don't want to run Postgres
+#[derive(Clone)]
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    /// Opens (creating it if needed) and migrates the database at a `sqlite:` url
+    pub async fn connect(db_url: &str) -> std::result::Result<Self, sqlx::Error> {
+        let options = SqliteConnectOptions::from_str(db_url)?
+            .create_if_missing(true)
+            .foreign_keys(true);
+        let pool = SqlitePool::connect_with(options).await?;
+        sqlx::migrate!("./migrations_sqlite").run(&pool).await?;
+        Ok(Self { pool })
+    }
+
+    pub fn with_pool(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Trigrams as FTS5 tokens, with the padding spaces written as '_'
+fn trigram_tokens(text: &str) -> Vec<String> {
+    trigram::trigrams(text)
+        .into_iter()
+        .map(|trigram| {
+            trigram
+                .iter()
+                .map(|&c| if c == ' ' { '_' } else { c })
+                .collect()
+        })
+        .collect()
+}
+
+async fn index_note(conn: &mut SqliteConnection, note: &Note) -> Result<()> {
+    // explicit titles are searched too, derived ones are part of the text already
+    let searched = format!(
+        "{} {}",
+        note.text,
+        note.title.as_deref().unwrap_or_default()
+    );
+    sqlx::query(
+        "INSERT INTO notes_trigrams (rowid, trigrams)
+         SELECT rowid, ?2 FROM notes WHERE id = ?1",
+    )
+    .bind(note.id)
+    .bind(trigram_tokens(&searched).join(" "))
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn unindex_note(conn: &mut SqliteConnection, id: Uuid) -> Result<()> {
+    sqlx::query("DELETE FROM notes_trigrams WHERE rowid = (SELECT rowid FROM notes WHERE id = ?)")
+        .bind(id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Tags the note with every tag, failing with [`Error::TagNotFound`] on the first unknown one
+async fn tag_note(conn: &mut SqliteConnection, note_id: Uuid, tags: &[String]) -> Result<()> {
+    for tag in tags {
+        let tag = sqlx::query_as::<_, Tag>("SELECT * FROM tags WHERE name = ?")
+            .bind(tag)
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or_else(|| Error::TagNotFound(tag.clone()))?;
+
+        sqlx::query("INSERT INTO note_tags (note_id, tag_id) VALUES (?, ?)")
+            .bind(note_id)
+            .bind(tag.id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn get_or_create_tag(conn: &mut SqliteConnection, name: &str) -> Result<Uuid> {
+    sqlx::query("INSERT INTO tags (id, name) VALUES (?, ?) ON CONFLICT (name) DO NOTHING")
+        .bind(Uuid::new_v4())
+        .bind(name)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(sqlx::query_scalar("SELECT id FROM tags WHERE name = ?")
+        .bind(name)
+        .fetch_one(&mut *conn)
+        .await?)
+}
+
+#[async_trait]
+impl NoteStore for SqliteStore {
+    async fn create_note(
+        &self,
+        text: &str,
+        title: Option<&str>,
+        tags: &[String],
+        dates: &[NewNoteDate],
+    ) -> Result<Note> {
+        // an unknown tag rolls back the whole note
+        let mut tx = self.pool.begin().await?;
+
+        let note = Note {
+            id: Uuid::new_v4(),
+            text: text.to_string(),
+            title: title.map(str::to_string),
+            created_at: Utc::now(),
+        };
+        sqlx::query("INSERT INTO notes (id, text, title, created_at) VALUES (?, ?, ?, ?)")
+            .bind(note.id)
+            .bind(&note.text)
+            .bind(&note.title)
+            .bind(note.created_at)
+            .execute(&mut *tx)
+            .await?;
+        index_note(&mut tx, &note).await?;
+
+        tag_note(&mut tx, note.id, tags).await?;
+
+        for date in dates {
+            sqlx::query(
+                "INSERT INTO note_dates (id, note_id, label, date, rrule) VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(Uuid::new_v4())
+            .bind(note.id)
+            .bind(&date.label)
+            .bind(date.date)
+            .bind(&date.rrule)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(note)
+    }
+
+    async fn update_note(&self, id: Uuid, text: &str, tags: &[String]) -> Result<Note> {
+        // an unknown tag rolls back the whole edit
+        let mut tx = self.pool.begin().await?;
+
+        unindex_note(&mut tx, id).await?;
+        let note = sqlx::query_as("UPDATE notes SET text = ? WHERE id = ? RETURNING *")
+            .bind(text)
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(Error::NoteNotFound(id))?;
+        index_note(&mut tx, &note).await?;
+
+        sqlx::query("DELETE FROM note_tags WHERE note_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tag_note(&mut tx, id, tags).await?;
+
+        tx.commit().await?;
+        Ok(note)
+    }
+
+    async fn delete_note(&self, id: Uuid) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        unindex_note(&mut tx, id).await?;
+        // tags, dates, links and reminder states go with it, see the schema's `ON DELETE CASCADE`s
+        let deleted = sqlx::query("DELETE FROM notes WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        if deleted == 0 {
+            return Err(Error::NoteNotFound(id));
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn create_tag(&self, name: &str) -> Result<Tag> {
+        let tag = Tag {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+        };
+
+        sqlx::query("INSERT INTO tags (id, name) VALUES (?, ?)")
+            .bind(tag.id)
+            .bind(&tag.name)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(db_error) if db_error.is_unique_violation() => {
+                    Error::TagExists(name.to_string())
+                }
+                e => e.into(),
+            })?;
+
+        Ok(tag)
+    }
+
+    async fn get_all_notes(&self) -> Result<Vec<Note>> {
+        Ok(sqlx::query_as("SELECT * FROM notes")
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn get_all_tags(&self) -> Result<Vec<Tag>> {
+        Ok(sqlx::query_as("SELECT * FROM tags")
+            .fetch_all(&self.pool)
+            .await?)
+    }
+
+    async fn get_notes_filtered(
+        &self,
+        search_text: Option<String>,
+        tags: Option<Vec<String>>,
+        note_ids: Option<Vec<Uuid>>,
+        created: Option<Range<DateTime<Utc>>>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Vec<Note>> {
+        let search_text = search_text.unwrap_or_default();
+        let tags = tags
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        // like `n.text % $1` in Postgres, only notes sharing a trigram are candidates
+        let trigram_match = if search_text.is_empty() {
+            None
+        } else {
+            let tokens = trigram_tokens(&search_text);
+            if tokens.is_empty() {
+                return Ok(Vec::new());
+            }
+            Some(
+                tokens
+                    .iter()
+                    .map(|token| format!("\"{token}\""))
+                    .collect::<Vec<_>>()
+                    .join(" OR "),
+            )
+        };
+
+        let candidates = sqlx::query_as::<_, Note>(
+            "SELECT n.id, n.text, n.title, n.created_at
+             FROM notes n
+             WHERE (
+                 SELECT COUNT(*)
+                 FROM note_tags nt
+                 JOIN tags t ON nt.tag_id = t.id
+                 WHERE nt.note_id = n.id AND t.name IN (SELECT value FROM json_each(?1))
+             ) = json_array_length(?1)
+             AND (?2 IS NULL OR n.rowid IN (
+                 SELECT rowid FROM notes_trigrams WHERE notes_trigrams MATCH ?2
+             ))
+             ORDER BY n.created_at",
+        )
+        .bind(serde_json::to_string(&tags).expect("failed to serialize tags"))
+        .bind(trigram_match)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut matches = candidates
+            .into_iter()
+            .filter(|note| note_ids.as_ref().is_none_or(|ids| ids.contains(&note.id)))
+            .filter(|note| {
+                created
+                    .as_ref()
+                    .is_none_or(|created| created.contains(&note.created_at))
+            })
+            .filter_map(|note| {
+                if search_text.is_empty() {
+                    return Some((0.0, note));
+                }
+                search_score(&note, &search_text).map(|score| (score, note))
+            })
+            .collect::<Vec<_>>();
+
+        // stable, so equally scored notes stay oldest first
+        matches.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        Ok(matches
+            .into_iter()
+            .map(|(_, note)| note)
+            .skip(offset.unwrap_or(0) as usize)
+            .take(limit.map_or(usize::MAX, |limit| limit as usize))
+            .collect())
+    }
+
+    async fn get_note_dates_until(&self, until: NaiveDate) -> Result<Vec<NoteDate>> {
+        Ok(
+            sqlx::query_as("SELECT * FROM note_dates WHERE date <= ? ORDER BY date, id")
+                .bind(until)
+                .fetch_all(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn get_all_note_details(&self) -> Result<Vec<NoteWithDetails>> {
+        let notes = sqlx::query_as("SELECT * FROM notes ORDER BY created_at")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let note_tags = sqlx::query_as::<_, (Uuid, Uuid, String)>(
+            "SELECT nt.note_id, t.id, t.name
+             FROM note_tags nt
+             JOIN tags t ON nt.tag_id = t.id
+             ORDER BY t.name",
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|(note_id, id, name)| (note_id, Tag { id, name }))
+        .collect();
+
+        let note_dates = sqlx::query_as::<_, NoteDate>("SELECT * FROM note_dates ORDER BY date")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let note_links = sqlx::query_as("SELECT note1_id, note2_id FROM note_links")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(assemble_details(notes, note_tags, note_dates, note_links))
+    }
+
+    async fn get_note_details(&self, id: Uuid) -> Result<NoteWithDetails> {
+        let note = sqlx::query_as("SELECT * FROM notes WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(Error::NoteNotFound(id))?;
+
+        let tags = sqlx::query_as(
+            "SELECT t.id, t.name
+             FROM note_tags nt
+             JOIN tags t ON nt.tag_id = t.id
+             WHERE nt.note_id = ?
+             ORDER BY t.name",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let note_dates = sqlx::query_as("SELECT * FROM note_dates WHERE note_id = ? ORDER BY date")
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        // links are stored once, with either note on either side
+        let note_links = sqlx::query_as(
+            "SELECT n.*
+             FROM note_links l
+             JOIN notes n ON n.id = CASE WHEN l.note1_id = ?1 THEN l.note2_id ELSE l.note1_id END
+             WHERE ?1 IN (l.note1_id, l.note2_id)
+             ORDER BY n.created_at",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(NoteWithDetails {
+            note,
+            tags,
+            note_dates,
+            note_links,
+        })
+    }
+
+    async fn upsert_note(&self, note: &NoteImport) -> Result<UpsertOutcome> {
+        let mut tx = self.pool.begin().await?;
+
+        let exists = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM notes WHERE id = ?)")
+            .bind(note.id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        if exists {
+            unindex_note(&mut tx, note.id).await?;
+            sqlx::query("UPDATE notes SET text = ?, title = ?, created_at = ? WHERE id = ?")
+                .bind(&note.text)
+                .bind(&note.title)
+                .bind(note.created_at)
+                .bind(note.id)
+                .execute(&mut *tx)
+                .await?;
+        } else {
+            sqlx::query("INSERT INTO notes (id, text, title, created_at) VALUES (?, ?, ?, ?)")
+                .bind(note.id)
+                .bind(&note.text)
+                .bind(&note.title)
+                .bind(note.created_at)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let stored = Note {
+            id: note.id,
+            text: note.text.clone(),
+            title: note.title.clone(),
+            created_at: note.created_at,
+        };
+        index_note(&mut tx, &stored).await?;
+
+        sqlx::query("DELETE FROM note_tags WHERE note_id = ?")
+            .bind(note.id)
+            .execute(&mut *tx)
+            .await?;
+
+        for tag in &note.tags {
+            let tag_id = get_or_create_tag(&mut tx, tag).await?;
+            sqlx::query("INSERT OR IGNORE INTO note_tags (note_id, tag_id) VALUES (?, ?)")
+                .bind(note.id)
+                .bind(tag_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let existing = sqlx::query_as::<_, NoteDate>("SELECT * FROM note_dates WHERE note_id = ?")
+            .bind(note.id)
+            .fetch_all(&mut *tx)
+            .await?;
+        let reused = reused_date_ids(
+            &existing,
+            note.dates
+                .iter()
+                .map(|date| (None, date.label.as_deref(), date.date)),
+        );
+        for note_date in &existing {
+            if !reused.contains(&Some(note_date.id)) {
+                sqlx::query("DELETE FROM note_dates WHERE id = ?")
+                    .bind(note_date.id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+
+        for (date, reused) in note.dates.iter().zip(reused) {
+            match reused {
+                Some(id) => {
+                    sqlx::query("UPDATE note_dates SET rrule = ? WHERE id = ?")
+                        .bind(&date.rrule)
+                        .bind(id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                None => {
+                    sqlx::query(
+                        "INSERT INTO note_dates (id, note_id, label, date, rrule)
+                         VALUES (?, ?, ?, ?, ?)",
+                    )
+                    .bind(Uuid::new_v4())
+                    .bind(note.id)
+                    .bind(&date.label)
+                    .bind(date.date)
+                    .bind(&date.rrule)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+        }
+
+        tx.commit().await?;
+        Ok(if exists {
+            UpsertOutcome::Updated
+        } else {
+            UpsertOutcome::Created
+        })
+    }
+
+    async fn link_notes(&self, a: Uuid, b: Uuid) -> Result<()> {
+        let (note1_id, note2_id) = link_key(a, b);
+        sqlx::query(
+            "INSERT OR IGNORE INTO note_links (note1_id, note2_id, created_at) VALUES (?, ?, ?)",
+        )
+        .bind(note1_id)
+        .bind(note2_id)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn unlink_notes(&self, a: Uuid, b: Uuid) -> Result<()> {
+        let (note1_id, note2_id) = link_key(a, b);
+        sqlx::query("DELETE FROM note_links WHERE note1_id = ? AND note2_id = ?")
+            .bind(note1_id)
+            .bind(note2_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn snapshot(&self) -> Result<Snapshot> {
+        // one transaction, so the tables agree with each other
+        let mut tx = self.pool.begin().await?;
+
+        let notes = sqlx::query_as("SELECT * FROM notes ORDER BY created_at, id")
+            .fetch_all(&mut *tx)
+            .await?;
+        let tags = sqlx::query_as("SELECT * FROM tags ORDER BY name")
+            .fetch_all(&mut *tx)
+            .await?;
+        let note_tags =
+            sqlx::query_as::<_, NoteTag>("SELECT * FROM note_tags ORDER BY note_id, tag_id")
+                .fetch_all(&mut *tx)
+                .await?;
+        let note_dates =
+            sqlx::query_as::<_, NoteDate>("SELECT * FROM note_dates ORDER BY note_id, date, id")
+                .fetch_all(&mut *tx)
+                .await?;
+        let note_links =
+            sqlx::query_as::<_, NoteLink>("SELECT * FROM note_links ORDER BY note1_id, note2_id")
+                .fetch_all(&mut *tx)
+                .await?;
+        let reminder_states = sqlx::query_as::<_, ReminderState>(
+            "SELECT * FROM reminder_states ORDER BY note_date_id, occurrence",
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(Snapshot {
+            notes,
+            tags,
+            note_tags,
+            note_dates,
+            note_links,
+            reminder_states,
+        })
+    }
+
+    async fn restore_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let mut existing = Vec::new();
+        for note in &snapshot.notes {
+            existing.extend(
+                sqlx::query_as::<_, NoteDate>("SELECT * FROM note_dates WHERE note_id = ?")
+                    .bind(note.id)
+                    .fetch_all(&mut *tx)
+                    .await?,
+            );
+        }
+        let date_ids = snapshot.restored_date_ids(&existing);
+
+        for tag in &snapshot.tags {
+            sqlx::query("INSERT INTO tags (id, name) VALUES (?, ?) ON CONFLICT (id) DO NOTHING")
+                .bind(tag.id)
+                .bind(&tag.name)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        for note in &snapshot.notes {
+            unindex_note(&mut tx, note.id).await?;
+            sqlx::query(
+                "INSERT INTO notes (id, text, title, created_at) VALUES (?, ?, ?, ?)
+                 ON CONFLICT (id) DO UPDATE
+                 SET text = excluded.text, title = excluded.title, created_at = excluded.created_at",
+            )
+            .bind(note.id)
+            .bind(&note.text)
+            .bind(&note.title)
+            .bind(note.created_at)
+            .execute(&mut *tx)
+            .await?;
+            index_note(&mut tx, note).await?;
+
+            // the restored notes get exactly the snapshot's tags and dates, the dates they keep
+            // keep their ids and reminder states
+            sqlx::query("DELETE FROM note_tags WHERE note_id = ?")
+                .bind(note.id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        for note_date in &existing {
+            if !date_ids.values().any(|&id| id == note_date.id) {
+                sqlx::query("DELETE FROM note_dates WHERE id = ?")
+                    .bind(note_date.id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+
+        for note_tag in &snapshot.note_tags {
+            sqlx::query("INSERT OR IGNORE INTO note_tags (note_id, tag_id) VALUES (?, ?)")
+                .bind(note_tag.note_id)
+                .bind(note_tag.tag_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        for note_date in &snapshot.note_dates {
+            sqlx::query(
+                "INSERT INTO note_dates (id, note_id, label, date, rrule) VALUES (?, ?, ?, ?, ?)
+                 ON CONFLICT (id) DO UPDATE
+                 SET note_id = excluded.note_id, label = excluded.label, date = excluded.date,
+                     rrule = excluded.rrule",
+            )
+            .bind(date_ids.get(&note_date.id).unwrap_or(&note_date.id))
+            .bind(note_date.note_id)
+            .bind(&note_date.label)
+            .bind(note_date.date)
+            .bind(&note_date.rrule)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for reminder_state in &snapshot.reminder_states {
+            let Some(note_date_id) = date_ids.get(&reminder_state.note_date_id) else {
+                continue;
+            };
+            sqlx::query(
+                "INSERT INTO reminder_states
+                 (note_date_id, occurrence, notified_at, snoozed_until, acknowledged_at)
+                 VALUES (?, ?, ?, ?, ?)
+                 ON CONFLICT (note_date_id, occurrence) DO UPDATE
+                 SET notified_at = excluded.notified_at, snoozed_until = excluded.snoozed_until,
+                     acknowledged_at = excluded.acknowledged_at",
+            )
+            .bind(note_date_id)
+            .bind(reminder_state.occurrence)
+            .bind(reminder_state.notified_at)
+            .bind(reminder_state.snoozed_until)
+            .bind(reminder_state.acknowledged_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for note_link in &snapshot.note_links {
+            sqlx::query(
+                "INSERT OR IGNORE INTO note_links (note1_id, note2_id, created_at) VALUES (?, ?, ?)",
+            )
+            .bind(note_link.note1_id)
+            .bind(note_link.note2_id)
+            .bind(note_link.created_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_reminder_dates(
+        &self,
+        labels: &[String],
+        until: NaiveDate,
+    ) -> Result<Vec<ReminderDate>> {
+        let rows = sqlx::query_as::<_, ReminderDateRow>(
+            "SELECT nd.id, nd.note_id, n.text, n.title, n.created_at, nd.label, nd.date, nd.rrule
+             FROM note_dates nd
+             JOIN notes n ON n.id = nd.note_id
+             WHERE nd.date <= ?1
+             AND lower(nd.label) IN (SELECT value FROM json_each(?2))
+             ORDER BY nd.date, n.created_at, nd.id",
+        )
+        .bind(until)
+        .bind(serde_json::to_string(labels).expect("failed to serialize labels"))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(ReminderDate::from).collect())
+    }
+
+    async fn get_reminder_states(&self, ids: &[Uuid]) -> Result<Vec<ReminderState>> {
+        let mut conn = self.pool.acquire().await?;
+        let mut states = Vec::new();
+        for &id in ids {
+            states.extend(
+                sqlx::query_as::<_, ReminderState>(
+                    "SELECT * FROM reminder_states WHERE note_date_id = ? ORDER BY occurrence",
+                )
+                .bind(id)
+                .fetch_all(&mut *conn)
+                .await?,
+            );
+        }
+        Ok(states)
+    }
+
+    async fn acknowledge_reminder(
+        &self,
+        id: Uuid,
+        date: NaiveDate,
+        at: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO reminder_states (note_date_id, occurrence, acknowledged_at) VALUES (?, ?, ?)
+             ON CONFLICT (note_date_id, occurrence)
+             DO UPDATE SET acknowledged_at = excluded.acknowledged_at",
+        )
+        .bind(id)
+        .bind(date)
+        .bind(at)
+        .execute(&self.pool)
+        .await
+        .map_err(reminder_not_found(id))?;
+        Ok(())
+    }
+
+    async fn snooze_reminder(&self, id: Uuid, date: NaiveDate, until: DateTime<Utc>) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO reminder_states (note_date_id, occurrence, snoozed_until) VALUES (?, ?, ?)
+             ON CONFLICT (note_date_id, occurrence)
+             DO UPDATE SET snoozed_until = excluded.snoozed_until",
+        )
+        .bind(id)
+        .bind(date)
+        .bind(until)
+        .execute(&self.pool)
+        .await
+        .map_err(reminder_not_found(id))?;
+        Ok(())
+    }
+
+    async fn mark_reminders_notified(
+        &self,
+        occurrences: &[(Uuid, NaiveDate)],
+        at: DateTime<Utc>,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for &(id, date) in occurrences {
+            sqlx::query(
+                "INSERT INTO reminder_states (note_date_id, occurrence, notified_at)
+                 SELECT id, ?2, ?3 FROM note_dates WHERE id = ?1
+                 ON CONFLICT (note_date_id, occurrence) DO UPDATE SET notified_at = excluded.notified_at",
+            )
+            .bind(id)
+            .bind(date)
+            .bind(at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
@@ -1,7 +1,9 @@
-use sqlx::{PgPool, Result};
+use sqlx::PgPool;
 
 pub use rust_shared::response::Tag;
 
+use super::error::{Error, Result};
+
 #[derive(Clone)]
 pub struct TagsRepository {
     pool: PgPool,
@@ -16,11 +18,17 @@ impl TagsRepository {
         sqlx::query_as!(Tag, "INSERT INTO tags (name) VALUES ($1) RETURNING *", name)
             .fetch_one(&self.pool)
             .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(db_error) if db_error.is_unique_violation() => {
+                    Error::TagExists(name.to_string())
+                }
+                e => e.into(),
+            })
     }
 
     pub async fn get_all(&self) -> Result<Vec<Tag>> {
-        sqlx::query_as!(Tag, "SELECT * FROM tags")
+        Ok(sqlx::query_as!(Tag, "SELECT * FROM tags")
             .fetch_all(&self.pool)
-            .await
+            .await?)
     }
 }
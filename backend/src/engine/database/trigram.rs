@@ -0,0 +1,39 @@
+//! A port of `pg_trgm`'s similarity measure, so stores without the extension rank the same way
+
+use std::collections::HashSet;
+
+/// `pg_trgm.similarity_threshold` default, used by the `%` operator
+pub const SIMILARITY_THRESHOLD: f32 = 0.3;
+
+/// Trigrams of every alphanumeric word, lowercased and padded like `pg_trgm` does
+/// Example: "Cat" -> {"  c", " ca", "cat", "at "}
+pub fn trigrams(text: &str) -> HashSet<[char; 3]> {
+    let mut trigrams = HashSet::new();
+
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        let padded = ["  ", &word.to_lowercase(), " "].concat();
+        let chars = padded.chars().collect::<Vec<_>>();
+        for window in chars.windows(3) {
+            trigrams.insert([window[0], window[1], window[2]]);
+        }
+    }
+
+    trigrams
+}
+
+/// Shared trigrams over all distinct trigrams of both texts, same as `similarity(a, b)`
+pub fn similarity(a: &str, b: &str) -> f32 {
+    let a = trigrams(a);
+    let b = trigrams(b);
+
+    let shared = a.intersection(&b).count();
+    let total = a.len() + b.len() - shared;
+    if total == 0 {
+        return 0.0;
+    }
+
+    shared as f32 / total as f32
+}
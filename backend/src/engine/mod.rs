@@ -1,2 +1,5 @@
+pub mod archive;
 pub mod core;
-mod database;
+pub mod database;
+pub mod recurrence;
+pub mod reminders;
@@ -0,0 +1,637 @@
+//! Recurrence rules on note dates, a subset of iCalendar's RRULE (RFC 5545, section 3.3.10)
+//!
+//! Supported parts are `FREQ` (`DAILY`, `WEEKLY`, `MONTHLY` or `YEARLY`), `INTERVAL`, `COUNT`,
+//! `UNTIL`, `BYMONTH`, `BYMONTHDAY` and `BYDAY`, e.g. `FREQ=MONTHLY;BYDAY=1MO` for every first
+//! Monday. The note date is the rule's start, and like in iCalendar it's only an occurrence
+//! itself if it matches the rule.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    str::FromStr,
+};
+
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use uuid::Uuid;
+
+use rust_shared::{request::DateRange, response::Occurrence};
+
+use crate::engine::database::{NoteStore, error::Result, notes::NoteDate};
+
+/// Widest window occurrences are listed for at once, about ten years
+pub const MAX_WINDOW_DAYS: u64 = 3660;
+
+/// How long a rule can go without an occurrence before it's considered exhausted, the eight
+/// years between leap days around non-leap centuries (e.g. 1896 to 1904), so that
+/// `FREQ=DAILY;BYMONTH=2;BYMONTHDAY=29` gets past them as well as the yearly rule
+const MAX_GAP_DAYS: i64 = 8 * 366;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A `BYDAY` entry, `nth` picks e.g. the first (`1MO`) or last (`-1FR`) such weekday of the month
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeekdayRule {
+    pub nth: Option<i32>,
+    pub weekday: Weekday,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub count: Option<u32>,
+    /// Last possible occurrence, inclusive
+    pub until: Option<NaiveDate>,
+    pub by_month: Vec<u32>,
+    /// Days of the month, negative ones count from the end (`-1` is the last day)
+    pub by_month_day: Vec<i32>,
+    pub by_day: Vec<WeekdayRule>,
+}
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("MO", Weekday::Mon),
+    ("TU", Weekday::Tue),
+    ("WE", Weekday::Wed),
+    ("TH", Weekday::Thu),
+    ("FR", Weekday::Fri),
+    ("SA", Weekday::Sat),
+    ("SU", Weekday::Sun),
+];
+
+fn parse_list<T: FromStr>(value: &str, part: &str) -> std::result::Result<Vec<T>, String> {
+    value
+        .split(',')
+        .map(|item| {
+            item.parse()
+                .map_err(|_| format!("invalid {part} value {item}"))
+        })
+        .collect()
+}
+
+fn parse_weekday_rule(value: &str) -> std::result::Result<WeekdayRule, String> {
+    let split = value.len().saturating_sub(2);
+    let (nth, weekday) = value.split_at_checked(split).unwrap_or(("", value));
+    let weekday = WEEKDAYS
+        .iter()
+        .find(|(name, _)| *name == weekday)
+        .map(|(_, weekday)| *weekday)
+        .ok_or_else(|| format!("invalid BYDAY value {value}"))?;
+
+    let nth = match nth {
+        "" => None,
+        nth => {
+            let nth = nth
+                .trim_start_matches('+')
+                .parse::<i32>()
+                .map_err(|_| format!("invalid BYDAY value {value}"))?;
+            if nth == 0 || nth.abs() > 5 {
+                return Err(format!("BYDAY ordinals go from -5 to 5, got {value}"));
+            }
+            Some(nth)
+        }
+    };
+
+    Ok(WeekdayRule { nth, weekday })
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    /// Parses a rule, with or without the `RRULE:` prefix
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_prefix("RRULE:").unwrap_or(s);
+
+        let mut frequency = None;
+        let mut rule = Self {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_month: Vec::new(),
+            by_month_day: Vec::new(),
+            by_day: Vec::new(),
+        };
+
+        for part in s.split(';').filter(|part| !part.is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("invalid rule part {part}"))?;
+
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(format!("unsupported FREQ {value}")),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value
+                        .parse()
+                        .ok()
+                        .filter(|interval| *interval > 0)
+                        .ok_or_else(|| format!("invalid INTERVAL {value}"))?
+                }
+                "COUNT" => {
+                    rule.count = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|count| *count > 0)
+                            .ok_or_else(|| format!("invalid COUNT {value}"))?,
+                    )
+                }
+                "UNTIL" => {
+                    // date-times are cut down to their date, note dates have no time
+                    let date = value.get(..8).unwrap_or(value);
+                    rule.until = Some(
+                        NaiveDate::parse_from_str(date, "%Y%m%d")
+                            .map_err(|_| format!("invalid UNTIL {value}"))?,
+                    )
+                }
+                "BYMONTH" => {
+                    rule.by_month = parse_list(value, "BYMONTH")?;
+                    if let Some(month) = rule
+                        .by_month
+                        .iter()
+                        .find(|month| !(1..=12).contains(*month))
+                    {
+                        return Err(format!("invalid BYMONTH value {month}"));
+                    }
+                }
+                "BYMONTHDAY" => {
+                    rule.by_month_day = parse_list(value, "BYMONTHDAY")?;
+                    if let Some(day) = rule
+                        .by_month_day
+                        .iter()
+                        .find(|day| **day == 0 || day.abs() > 31)
+                    {
+                        return Err(format!("invalid BYMONTHDAY value {day}"));
+                    }
+                }
+                "BYDAY" => {
+                    rule.by_day = value
+                        .to_ascii_uppercase()
+                        .split(',')
+                        .map(parse_weekday_rule)
+                        .collect::<std::result::Result<_, _>>()?
+                }
+                _ => return Err(format!("unsupported rule part {name}")),
+            }
+        }
+
+        rule.frequency = frequency.ok_or("rules need a FREQ")?;
+
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err("rules can't have both COUNT and UNTIL".to_string());
+        }
+        let has_ordinals = rule.by_day.iter().any(|rule| rule.nth.is_some());
+        match rule.frequency {
+            Frequency::Daily | Frequency::Weekly if has_ordinals => {
+                return Err("BYDAY ordinals need FREQ=MONTHLY or FREQ=YEARLY".to_string());
+            }
+            Frequency::Weekly if !rule.by_month_day.is_empty() => {
+                return Err("BYMONTHDAY doesn't go with FREQ=WEEKLY".to_string());
+            }
+            Frequency::Yearly if !rule.by_day.is_empty() && rule.by_month.is_empty() => {
+                return Err("BYDAY with FREQ=YEARLY needs BYMONTH".to_string());
+            }
+            _ => {}
+        }
+
+        Ok(rule)
+    }
+}
+
+fn join<T>(items: &[T], to_string: impl Fn(&T) -> String) -> String {
+    items.iter().map(to_string).collect::<Vec<_>>().join(",")
+}
+
+impl fmt::Display for Recurrence {
+    /// The rule without the `RRULE:` prefix, with its parts in a fixed order
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={frequency}")?;
+
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={count}")?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%d"))?;
+        }
+        if !self.by_month.is_empty() {
+            write!(f, ";BYMONTH={}", join(&self.by_month, u32::to_string))?;
+        }
+        if !self.by_month_day.is_empty() {
+            write!(
+                f,
+                ";BYMONTHDAY={}",
+                join(&self.by_month_day, i32::to_string)
+            )?;
+        }
+        if !self.by_day.is_empty() {
+            let by_day = join(&self.by_day, |rule| {
+                let (name, _) = WEEKDAYS
+                    .iter()
+                    .find(|(_, weekday)| *weekday == rule.weekday)
+                    .expect("every weekday has a name");
+                match rule.nth {
+                    Some(nth) => format!("{nth}{name}"),
+                    None => name.to_string(),
+                }
+            });
+            write!(f, ";BYDAY={by_day}")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let first = NaiveDate::from_ymd_opt(year, month, 1).expect("month is valid");
+    let next = first + Months::new(1);
+    (next - first).num_days() as u32
+}
+
+impl Recurrence {
+    /// Every occurrence of the rule starting on `start`, in order
+    pub fn occurrences(&self, start: NaiveDate) -> Occurrences<'_> {
+        Occurrences {
+            rule: self,
+            start,
+            period: 0,
+            pending: VecDeque::new(),
+            seen: 0,
+            last: start,
+            done: false,
+        }
+    }
+
+    /// Occurrences of the rule starting on `start` that fall within `from..=to`
+    pub fn occurrences_between(
+        &self,
+        start: NaiveDate,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> impl Iterator<Item = NaiveDate> + '_ {
+        // a count has to be counted from the start, anything else can skip to `from`
+        let period = match self.count {
+            Some(_) => 0,
+            None => self.first_period(start, from),
+        };
+        Occurrences {
+            period,
+            last: self.period_start(start, period).unwrap_or(start),
+            ..self.occurrences(start)
+        }
+        .skip_while(move |date| *date < from)
+        .take_while(move |date| *date <= to)
+    }
+
+    /// The period `from` falls into, every period before it ends before `from`
+    fn first_period(&self, start: NaiveDate, from: NaiveDate) -> u32 {
+        if from <= start {
+            return 0;
+        }
+
+        let days = (from - start).num_days();
+        let periods = match self.frequency {
+            Frequency::Daily => days,
+            // weeks start on Monday
+            Frequency::Weekly => (days + i64::from(start.weekday().num_days_from_monday())) / 7,
+            Frequency::Monthly => {
+                i64::from(from.year() - start.year()) * 12 + i64::from(from.month())
+                    - i64::from(start.month())
+            }
+            Frequency::Yearly => i64::from(from.year() - start.year()),
+        };
+        // past the end of the calendar, where there are no more periods anyway
+        u32::try_from(periods / i64::from(self.interval)).unwrap_or(u32::MAX)
+    }
+
+    /// Candidate days of one month, before the rule's start and limits apply
+    fn month_days(&self, year: i32, month: u32, default_day: u32) -> Vec<u32> {
+        let length = days_in_month(year, month);
+
+        let month_days = self
+            .by_month_day
+            .iter()
+            .filter_map(|&day| {
+                let day = if day < 0 {
+                    length as i32 + 1 + day
+                } else {
+                    day
+                };
+                (1..=length as i32).contains(&day).then_some(day as u32)
+            })
+            .collect::<Vec<_>>();
+
+        let weekday_days = self
+            .by_day
+            .iter()
+            .flat_map(|rule| {
+                let matching = (1..=length)
+                    .filter(|&day| {
+                        NaiveDate::from_ymd_opt(year, month, day)
+                            .is_some_and(|date| date.weekday() == rule.weekday)
+                    })
+                    .collect::<Vec<_>>();
+                match rule.nth {
+                    None => matching,
+                    Some(nth) if nth > 0 => matching
+                        .get(nth as usize - 1)
+                        .copied()
+                        .into_iter()
+                        .collect(),
+                    Some(nth) => matching
+                        .len()
+                        .checked_sub(nth.unsigned_abs() as usize)
+                        .and_then(|i| matching.get(i).copied())
+                        .into_iter()
+                        .collect(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        match (self.by_month_day.is_empty(), self.by_day.is_empty()) {
+            (true, true) => (default_day <= length)
+                .then_some(default_day)
+                .into_iter()
+                .collect(),
+            (false, true) => month_days,
+            (true, false) => weekday_days,
+            (false, false) => month_days
+                .into_iter()
+                .filter(|day| weekday_days.contains(day))
+                .collect(),
+        }
+    }
+
+    /// First day of the `period`th period after `start`, or `None` past the end of the calendar
+    fn period_start(&self, start: NaiveDate, period: u32) -> Option<NaiveDate> {
+        let step = period.checked_mul(self.interval)?;
+        match self.frequency {
+            Frequency::Daily => start.checked_add_days(Days::new(step.into())),
+            Frequency::Weekly => start
+                .week(Weekday::Mon)
+                .checked_first_day()?
+                .checked_add_days(Days::new(u64::from(step) * 7)),
+            Frequency::Monthly => start.with_day(1)?.checked_add_months(Months::new(step)),
+            Frequency::Yearly => {
+                NaiveDate::from_ymd_opt(start.year().checked_add(step.try_into().ok()?)?, 1, 1)
+            }
+        }
+    }
+
+    /// Candidate dates of the `period`th period after `start`, sorted, or `None` past the end of
+    /// the calendar
+    fn period_dates(&self, start: NaiveDate, period: u32) -> Option<Vec<NaiveDate>> {
+        let first = self.period_start(start, period)?;
+
+        let mut dates = match self.frequency {
+            Frequency::Daily => {
+                let date = first;
+                let matches_day = self.by_day.is_empty()
+                    || self
+                        .by_day
+                        .iter()
+                        .any(|rule| rule.weekday == date.weekday());
+                let matches_month_day = self.by_month_day.is_empty()
+                    || self
+                        .month_days(date.year(), date.month(), 0)
+                        .contains(&date.day());
+                if matches_day && matches_month_day {
+                    vec![date]
+                } else {
+                    Vec::new()
+                }
+            }
+            Frequency::Weekly => {
+                let weekdays = if self.by_day.is_empty() {
+                    vec![start.weekday()]
+                } else {
+                    self.by_day.iter().map(|rule| rule.weekday).collect()
+                };
+                weekdays
+                    .into_iter()
+                    .filter_map(|weekday| {
+                        first.checked_add_days(Days::new(weekday.num_days_from_monday().into()))
+                    })
+                    .collect()
+            }
+            Frequency::Monthly => self
+                .month_days(first.year(), first.month(), start.day())
+                .into_iter()
+                .filter_map(|day| first.with_day(day))
+                .collect(),
+            Frequency::Yearly => {
+                let year = first.year();
+                let months = if self.by_month.is_empty() {
+                    vec![start.month()]
+                } else {
+                    self.by_month.clone()
+                };
+                months
+                    .into_iter()
+                    .flat_map(|month| {
+                        self.month_days(year, month, start.day())
+                            .into_iter()
+                            .filter_map(move |day| NaiveDate::from_ymd_opt(year, month, day))
+                    })
+                    .collect()
+            }
+        };
+
+        if self.frequency != Frequency::Yearly && !self.by_month.is_empty() {
+            dates.retain(|date| self.by_month.contains(&date.month()));
+        }
+        dates.sort();
+        dates.dedup();
+        Some(dates)
+    }
+}
+
+/// Iterator over a rule's occurrences, see [`Recurrence::occurrences`]
+pub struct Occurrences<'a> {
+    rule: &'a Recurrence,
+    start: NaiveDate,
+    period: u32,
+    pending: VecDeque<NaiveDate>,
+    seen: u32,
+    /// The latest occurrence, or where the search for one started
+    last: NaiveDate,
+    done: bool,
+}
+
+impl Iterator for Occurrences<'_> {
+    type Item = NaiveDate;
+
+    fn next(&mut self) -> Option<NaiveDate> {
+        while !self.done {
+            if let Some(date) = self.pending.pop_front() {
+                if self.rule.until.is_some_and(|until| date > until) {
+                    break;
+                }
+                self.seen += 1;
+                self.last = date;
+                if self.rule.count.is_some_and(|count| self.seen >= count) {
+                    self.done = true;
+                }
+                return Some(date);
+            }
+
+            let (Some(first), Some(dates)) = (
+                self.rule.period_start(self.start, self.period),
+                self.rule.period_dates(self.start, self.period),
+            ) else {
+                break;
+            };
+            if (first - self.last).num_days() > MAX_GAP_DAYS
+                || self.rule.until.is_some_and(|until| first > until)
+            {
+                break;
+            }
+            self.period += 1;
+
+            let start = self.start;
+            self.pending
+                .extend(dates.into_iter().filter(|date| *date >= start));
+        }
+
+        self.done = true;
+        None
+    }
+}
+
+fn note_date_rule(note_date: &NoteDate) -> Option<Recurrence> {
+    note_date.rrule.as_deref()?.parse().ok()
+}
+
+/// Occurrences of a note date within `from..=to`, just its date unless it has a rule
+///
+/// Stored rules are checked when they're written, one that doesn't parse anymore counts as no
+/// rule at all.
+pub fn note_date_occurrences(
+    note_date: &NoteDate,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<NaiveDate> {
+    match note_date_rule(note_date) {
+        Some(rule) => rule.occurrences_between(note_date.date, from, to).collect(),
+        None => (from..=to)
+            .contains(&note_date.date)
+            .then_some(note_date.date)
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// Whether a note date happens at least once within `from..=to`
+pub fn occurs_between(note_date: &NoteDate, from: NaiveDate, to: NaiveDate) -> bool {
+    match note_date_rule(note_date) {
+        Some(rule) => rule
+            .occurrences_between(note_date.date, from, to)
+            .next()
+            .is_some(),
+        None => (from..=to).contains(&note_date.date),
+    }
+}
+
+/// Ids of the notes with a date happening within the range
+pub async fn notes_between(store: &dyn NoteStore, range: DateRange) -> Result<Vec<Uuid>> {
+    let mut note_ids = store
+        .get_note_dates_until(range.to)
+        .await?
+        .iter()
+        .filter(|note_date| occurs_between(note_date, range.from, range.to))
+        .map(|note_date| note_date.note_id)
+        .collect::<Vec<_>>();
+    note_ids.sort();
+    note_ids.dedup();
+    Ok(note_ids)
+}
+
+/// Every occurrence of the dates of notes carrying all `tags`, ordered by date
+pub async fn occurrences(
+    store: &dyn NoteStore,
+    range: DateRange,
+    tags: Option<Vec<String>>,
+) -> Result<Vec<Occurrence>> {
+    let note_dates = store.get_note_dates_until(range.to).await?;
+    let note_ids = note_dates
+        .iter()
+        .map(|note_date| note_date.note_id)
+        .collect::<HashSet<_>>();
+    let notes = store
+        .get_notes_filtered(
+            None,
+            tags,
+            Some(note_ids.into_iter().collect()),
+            None,
+            None,
+            None,
+        )
+        .await?
+        .into_iter()
+        .map(|note| (note.id, note))
+        .collect::<HashMap<_, _>>();
+
+    let mut occurrences = note_dates
+        .iter()
+        .filter_map(|note_date| Some((note_date, notes.get(&note_date.note_id)?)))
+        .flat_map(|(note_date, note)| {
+            note_date_occurrences(note_date, range.from, range.to)
+                .into_iter()
+                .map(|date| Occurrence {
+                    note_date_id: note_date.id,
+                    note: note.clone(),
+                    label: note_date.label.clone(),
+                    date,
+                    rrule: note_date.rrule.clone(),
+                })
+        })
+        .collect::<Vec<_>>();
+    occurrences.sort_by_key(|occurrence| {
+        (
+            occurrence.date,
+            occurrence.note.created_at,
+            occurrence.note_date_id,
+        )
+    });
+    Ok(occurrences)
+}
+
+/// Rejects ranges that end before they start, or that are too wide to list occurrences for
+pub fn check_window(range: DateRange) -> std::result::Result<(), String> {
+    let days = (range.to - range.from).num_days();
+    if days < 0 {
+        return Err(format!(
+            "date range ends before it starts: {} to {}",
+            range.from, range.to
+        ));
+    }
+    if days as u64 > MAX_WINDOW_DAYS {
+        return Err(format!(
+            "date range is too wide: {} days, the limit is {MAX_WINDOW_DAYS}",
+            days + 1
+        ));
+    }
+    Ok(())
+}
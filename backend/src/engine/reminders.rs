@@ -0,0 +1,198 @@
+//! Reminders are note dates whose label marks them as such (`reminder` by default). Once a
+//! reminder's date comes, connected clients get it pushed, and it stays due until it's
+//! acknowledged. Snoozing hides it for a while, then it's pushed again.
+//!
+//! A recurring reminder comes due on each of its occurrences, which are acknowledged and snoozed
+//! one at a time. The latest occurrence before today stays due until it's acknowledged, while
+//! older missed ones are let go.
+
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use tokio::{sync::broadcast, time::MissedTickBehavior};
+use tracing::{debug, warn};
+
+use rust_shared::response;
+
+use crate::{
+    engine::{
+        core::Engine,
+        database::{
+            NoteStore,
+            error::Result,
+            notes::NoteDate,
+            reminders::{PendingReminder, Reminder},
+        },
+        recurrence,
+    },
+    env_or, env_or_valid,
+};
+
+#[derive(Debug, Clone)]
+pub struct ReminderConfig {
+    /// Lowercase labels marking a note date as a reminder
+    pub labels: Vec<String>,
+    /// How many days before its date a reminder is due
+    pub lead_days: u64,
+    /// How often due reminders are looked for
+    pub scan_interval: Duration,
+}
+
+impl Default for ReminderConfig {
+    fn default() -> Self {
+        Self {
+            labels: vec!["reminder".to_string()],
+            lead_days: 0,
+            scan_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl ReminderConfig {
+    /// Reads `REMINDER_LABELS` (comma separated), `REMINDER_LEAD_DAYS` and `REMINDER_SCAN_SECS`,
+    /// using the defaults for anything unset
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let labels = std::env::var("REMINDER_LABELS")
+            .map(|labels| {
+                labels
+                    .split(',')
+                    .map(|label| label.trim().to_lowercase())
+                    .filter(|label| !label.is_empty())
+                    .collect()
+            })
+            .unwrap_or(default.labels);
+
+        Self {
+            labels,
+            lead_days: env_or("REMINDER_LEAD_DAYS", default.lead_days),
+            scan_interval: Duration::from_secs(env_or_valid(
+                "REMINDER_SCAN_SECS",
+                default.scan_interval.as_secs(),
+                |&secs| secs > 0,
+            )),
+        }
+    }
+}
+
+/// How far back the missed occurrence of a recurring reminder is looked for
+const MISSED_OCCURRENCE_DAYS: u64 = 366;
+
+/// Occurrences of a reminder up to `until`: the latest one before `today`, if it was missed, and
+/// every one since
+fn due_occurrences(note_date: &NoteDate, today: NaiveDate, until: NaiveDate) -> Vec<NaiveDate> {
+    let from = match note_date.rrule {
+        Some(_) => today - Days::new(MISSED_OCCURRENCE_DAYS),
+        None => note_date.date,
+    };
+    let mut occurrences = recurrence::note_date_occurrences(note_date, from, until);
+
+    let missed = occurrences.iter().rposition(|&date| date < today);
+    occurrences.split_off(missed.unwrap_or(0))
+}
+
+/// Unacknowledged occurrences of reminders that have come and whose snooze, if any, is over,
+/// earliest first
+pub async fn due(
+    store: &dyn NoteStore,
+    config: &ReminderConfig,
+    now: DateTime<Utc>,
+) -> Result<Vec<PendingReminder>> {
+    let today = now.date_naive();
+    let until = today + Days::new(config.lead_days);
+    let dates = store.get_reminder_dates(&config.labels, until).await?;
+
+    let ids = dates
+        .iter()
+        .map(|reminder_date| reminder_date.note_date.id)
+        .collect::<Vec<_>>();
+    let states = store
+        .get_reminder_states(&ids)
+        .await?
+        .into_iter()
+        .map(|state| ((state.note_date_id, state.occurrence), state))
+        .collect::<HashMap<_, _>>();
+
+    let mut pending = Vec::new();
+    for reminder_date in dates {
+        let note_date = &reminder_date.note_date;
+        for date in due_occurrences(note_date, today, until) {
+            let state = states.get(&(note_date.id, date));
+            if state.is_some_and(|state| state.acknowledged_at.is_some()) {
+                continue;
+            }
+            let snoozed_until = state.and_then(|state| state.snoozed_until);
+            if snoozed_until.is_some_and(|snoozed_until| snoozed_until > now) {
+                continue;
+            }
+
+            pending.push(PendingReminder {
+                reminder: Reminder {
+                    id: note_date.id,
+                    note: reminder_date.note.clone(),
+                    label: note_date.label.clone(),
+                    date,
+                    snoozed_until,
+                },
+                notified_at: state.and_then(|state| state.notified_at),
+            });
+        }
+    }
+
+    pending.sort_by_key(|pending| {
+        let reminder = &pending.reminder;
+        (reminder.date, reminder.note.created_at, reminder.id)
+    });
+    Ok(pending)
+}
+
+/// Pushes the due reminders clients haven't heard of yet, or not since their snooze ran out,
+/// returning how many were pushed
+pub async fn notify_due(
+    store: &dyn NoteStore,
+    config: &ReminderConfig,
+    now: DateTime<Utc>,
+    pushes: &broadcast::Sender<response::Message>,
+) -> Result<usize> {
+    let unnotified = due(store, config, now)
+        .await?
+        .into_iter()
+        .filter(
+            |pending| match (pending.notified_at, pending.reminder.snoozed_until) {
+                (None, _) => true,
+                (Some(notified_at), Some(snoozed_until)) => notified_at < snoozed_until,
+                (Some(_), None) => false,
+            },
+        )
+        .map(|pending| pending.reminder)
+        .collect::<Vec<_>>();
+
+    let occurrences = unnotified
+        .iter()
+        .map(|reminder| (reminder.id, reminder.date))
+        .collect::<Vec<_>>();
+    for reminder in unnotified {
+        // nobody listening is fine, clients ask for the due reminders when they connect
+        if pushes.send(response::Message::Reminder(reminder)).is_err() {
+            debug!("no clients to notify of reminders");
+        }
+    }
+
+    store.mark_reminders_notified(&occurrences, now).await?;
+    Ok(occurrences.len())
+}
+
+/// Looks for due reminders every scan interval, pushing them through `pushes`
+pub async fn run(engine: Engine, pushes: broadcast::Sender<response::Message>) {
+    let config = engine.reminders().clone();
+    let mut interval = tokio::time::interval(config.scan_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+        let now = Utc::now();
+        if let Err(e) = notify_due(engine.store(), &config, now, &pushes).await {
+            warn!("failed to notify reminders: {e}");
+        }
+    }
+}
@@ -0,0 +1,38 @@
+pub mod engine;
+pub mod server;
+
+use std::{fmt::Display, str::FromStr, sync::LazyLock};
+
+use tracing::warn;
+
+// set develop flag
+pub static DEV: LazyLock<bool> =
+    LazyLock::new(|| std::env::var("RUST_BACKTRACE").unwrap_or("0".to_string()) == "1");
+
+/// Reads and parses an environment variable, falling back to `default` if it's unset or invalid
+pub(crate) fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Like [`env_or`], also falling back to `default` for values `valid` rejects, which would panic
+/// or make no sense further down, e.g. a zero interval
+pub(crate) fn env_or_valid<T: FromStr + Display>(
+    name: &str,
+    default: T,
+    valid: impl Fn(&T) -> bool,
+) -> T {
+    match std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+    {
+        Some(value) if valid(&value) => value,
+        Some(value) => {
+            warn!("{name}={value} is out of range, using the default {default}");
+            default
+        }
+        None => default,
+    }
+}
@@ -1,11 +1,87 @@
-mod engine;
-mod server;
+use std::{fs, io, path::PathBuf};
 
-use std::sync::LazyLock;
+use rember_backend::{
+    engine,
+    engine::archive::{
+        backup::{self, BackupSchedule, ConflictPolicy},
+        ical, markdown, vault,
+    },
+    server,
+};
 
-// set develop flag
-pub static DEV: LazyLock<bool> =
-    LazyLock::new(|| std::env::var("RUST_BACKTRACE").unwrap_or("0".to_string()) == "1");
+const USAGE: &str = "usage: rember-backend [command]
+
+commands:
+    serve                                   run the websocket server (default)
+    export <dir>                            write every note as markdown into <dir>
+    import <dir>                            read the markdown files in <dir> back
+    import-vault [--dry-run] <dir>          import an Obsidian vault or Logseq graph
+    backup <file>                           write a full backup to <file>
+    restore [--on-conflict <policy>] <file> restore a backup, policy is fail (default), skip or overwrite
+    ical [--tag <tag>] <file>               write note dates as an iCalendar file";
+
+enum Command {
+    Serve,
+    /// Writes every note as a Markdown file into the directory
+    Export(PathBuf),
+    /// Reads the Markdown files in the directory back, by note id
+    Import(PathBuf),
+    /// Imports an Obsidian vault or Logseq graph, only reporting what would change on a dry run
+    ImportVault {
+        dir: PathBuf,
+        dry_run: bool,
+    },
+    /// Writes a lossless JSON Lines backup of every table
+    Backup(PathBuf),
+    Restore {
+        file: PathBuf,
+        policy: ConflictPolicy,
+    },
+    /// Writes the dates of every note, or of the notes with a tag, as an iCalendar file
+    Ical {
+        file: PathBuf,
+        tag: Option<String>,
+    },
+}
+
+impl Command {
+    fn from_args() -> Option<Self> {
+        let args = std::env::args().skip(1).collect::<Vec<_>>();
+        match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            [] | ["serve"] => Some(Self::Serve),
+            ["export", dir] => Some(Self::Export(dir.into())),
+            ["import", dir] => Some(Self::Import(dir.into())),
+            ["import-vault", dir] => Some(Self::ImportVault {
+                dir: dir.into(),
+                dry_run: false,
+            }),
+            ["import-vault", "--dry-run", dir] | ["import-vault", dir, "--dry-run"] => {
+                Some(Self::ImportVault {
+                    dir: dir.into(),
+                    dry_run: true,
+                })
+            }
+            ["backup", file] => Some(Self::Backup(file.into())),
+            ["restore", file] => Some(Self::Restore {
+                file: file.into(),
+                policy: ConflictPolicy::default(),
+            }),
+            ["restore", "--on-conflict", policy, file] => Some(Self::Restore {
+                file: file.into(),
+                policy: policy.parse().ok()?,
+            }),
+            ["ical", file] => Some(Self::Ical {
+                file: file.into(),
+                tag: None,
+            }),
+            ["ical", "--tag", tag, file] => Some(Self::Ical {
+                file: file.into(),
+                tag: Some(tag.to_string()),
+            }),
+            _ => None,
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() {
@@ -13,6 +89,11 @@ async fn main() {
     // in production, the environment variables are set by the compose file
     dotenvy::dotenv().ok();
 
+    let Some(command) = Command::from_args() else {
+        eprintln!("{USAGE}");
+        std::process::exit(2);
+    };
+
     let log_level = std::env::var("LOG_LEVEL")
         .unwrap_or("info".to_string())
         .parse::<tracing::Level>()
@@ -26,12 +107,152 @@ async fn main() {
     let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
     let engine = engine::core::Engine::with_database_url(&db_url)
         .await
-        .expect("Failed to initialize engine with database");
+        .expect("Failed to initialize engine with database")
+        .with_limits(engine::core::Limits::from_env())
+        .with_reminders(engine::reminders::ReminderConfig::from_env());
+
+    match command {
+        Command::Serve => {}
+        Command::Export(dir) => {
+            let files = markdown::export(engine.store())
+                .await
+                .expect("Failed to read notes");
+            markdown::write_dir(&dir, &files).expect("Failed to write notes");
+            println!("exported {} notes to {}", files.len(), dir.display());
+            return;
+        }
+        Command::Import(dir) => {
+            let files = markdown::read_dir(&dir).expect("Failed to read notes");
+            let report = markdown::import(engine.store(), engine.limits(), &files)
+                .await
+                .expect("Failed to import notes");
+            for error in &report.errors {
+                eprintln!("skipped {error}");
+            }
+            println!(
+                "imported {} new and {} updated notes with {} links from {}",
+                report.created,
+                report.updated,
+                report.linked,
+                dir.display()
+            );
+            return;
+        }
+        Command::ImportVault { dir, dry_run } => {
+            let vault = vault::read_vault(&dir).expect("Failed to read vault");
+            let report = vault::import_vault(engine.store(), engine.limits(), &vault, dry_run)
+                .await
+                .expect("Failed to import vault");
+            print_vault_report(&report, dry_run);
+            return;
+        }
+        Command::Backup(file) => {
+            let snapshot = engine
+                .store()
+                .snapshot()
+                .await
+                .expect("Failed to read notes");
+            let writer =
+                io::BufWriter::new(fs::File::create(&file).expect("Failed to create backup"));
+            backup::write_backup(&snapshot, writer).expect("Failed to write backup");
+            println!(
+                "backed up {} notes and {} tags to {}",
+                snapshot.notes.len(),
+                snapshot.tags.len(),
+                file.display()
+            );
+            return;
+        }
+        Command::Restore { file, policy } => {
+            let reader = io::BufReader::new(fs::File::open(&file).expect("Failed to open backup"));
+            let snapshot = backup::read_backup(reader).unwrap_or_else(|e| {
+                eprintln!("can't read {}: {e}", file.display());
+                std::process::exit(1);
+            });
+            let report = backup::restore(engine.store(), &snapshot, policy)
+                .await
+                .unwrap_or_else(|e| {
+                    eprintln!("restore failed: {e}");
+                    std::process::exit(1);
+                });
+            println!(
+                "restored {} new notes, overwrote {}, skipped {}, with {} new tags and {} links",
+                report.created,
+                report.overwritten,
+                report.skipped,
+                report.tags_created,
+                report.links
+            );
+            return;
+        }
+        Command::Ical { file, tag } => {
+            let calendar = ical::export(engine.store(), tag.as_deref())
+                .await
+                .expect("Failed to read notes");
+            fs::write(&file, calendar).expect("Failed to write calendar");
+            println!("wrote calendar to {}", file.display());
+            return;
+        }
+    }
+
+    if let Some(schedule) = BackupSchedule::from_env() {
+        tokio::spawn(schedule.run(engine.clone()));
+    }
+
+    if let Some(config) = server::calendar::CalendarConfig::from_env() {
+        let calendar_engine = engine.clone();
+        tokio::spawn(server::calendar::start(config, move |tag| {
+            let engine = calendar_engine.clone();
+            async move {
+                ical::export(engine.store(), tag.as_deref())
+                    .await
+                    .map_err(|e| e.to_string())
+            }
+        }));
+    }
+
+    // due reminders are pushed to every connected client
+    let (pushes, _) = tokio::sync::broadcast::channel(64);
+    tokio::spawn(engine::reminders::run(engine.clone(), pushes.clone()));
 
     let addr = "0.0.0.0:3210";
-    server::listener::start(addr, move |msg| {
-        let engine = engine.clone();
-        async move { engine.handle_message(msg).await }
-    })
-    .await;
+    let config = server::websocket::SessionConfig::from_env();
+    server::listener::start(addr, config, pushes, move |msg| engine.handle_message(msg)).await;
+}
+
+fn print_vault_report(report: &vault::VaultReport, dry_run: bool) {
+    let (create, update) = if dry_run {
+        ("would create", "would update")
+    } else {
+        ("created", "updated")
+    };
+
+    for path in &report.created {
+        println!("{create} {path}");
+    }
+    for path in &report.updated {
+        println!("{update} {path}");
+    }
+    for tag in &report.new_tags {
+        println!("{create} tag #{tag}");
+    }
+    for link in &report.unresolved_links {
+        eprintln!("unresolved link {link}");
+    }
+    for warning in &report.warnings {
+        eprintln!("skipped {warning}");
+    }
+
+    println!(
+        "{} new and {} updated notes, {} new tags, {} links{}",
+        report.created.len(),
+        report.updated.len(),
+        report.new_tags.len(),
+        report.links,
+        if dry_run {
+            " (dry run, nothing was written)"
+        } else {
+            ""
+        }
+    );
 }
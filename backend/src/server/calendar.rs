@@ -0,0 +1,193 @@
+//! A tiny read-only HTTP server for calendar apps subscribing to note dates
+//!
+//! - `GET /<token>/calendar.ics` serves every note's dates
+//! - `GET /<token>/calendar/<tag>.ics` only serves the dates of notes tagged `<tag>`
+//!
+//! Calendar apps can't log in, so the secret token in the URL is all that keeps the notes
+//! private. Any other path, including the right one with a wrong token, is a 404.
+
+use std::{sync::Arc, time::Duration};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time::timeout,
+};
+use tracing::{debug, info, warn};
+
+/// Requests are a single line plus headers, anything bigger isn't a calendar app
+const MAX_REQUEST_SIZE: usize = 8 << 10;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct CalendarConfig {
+    pub addr: String,
+    /// The first path segment of every calendar's URL
+    pub token: Arc<str>,
+}
+
+impl CalendarConfig {
+    /// Reads `CALENDAR_ADDR` and `CALENDAR_TOKEN`, calendars are only served when both are set
+    ///
+    /// An address that's only a port is on 127.0.0.1.
+    pub fn from_env() -> Option<Self> {
+        let addr = std::env::var("CALENDAR_ADDR")
+            .ok()
+            .filter(|addr| !addr.is_empty())?;
+        let addr = match addr.parse::<u16>() {
+            Ok(port) => format!("127.0.0.1:{port}"),
+            Err(_) => addr,
+        };
+
+        let token = std::env::var("CALENDAR_TOKEN").unwrap_or_default();
+        if token.is_empty() || token.contains(['/', '?', ' ']) {
+            warn!("CALENDAR_ADDR is set without a usable CALENDAR_TOKEN, not serving calendars");
+            return None;
+        }
+
+        Some(Self {
+            addr,
+            token: token.into(),
+        })
+    }
+}
+
+pub async fn start<F, Fut>(config: CalendarConfig, render: F)
+where
+    F: Fn(Option<String>) -> Fut + Send + Sync + Clone + 'static,
+    Fut: Future<Output = Result<String, String>> + Send + 'static,
+{
+    let listener = TcpListener::bind(&config.addr).await.unwrap();
+    info!("serving calendars on {}", config.addr);
+
+    while let Ok((stream, _)) = listener.accept().await {
+        let token = config.token.clone();
+        let render = render.clone();
+        tokio::spawn(async move { handle_connection(stream, &token, render).await });
+    }
+}
+
+/// Answers a single request, then closes the connection
+pub async fn handle_connection<F, Fut>(mut stream: TcpStream, token: &str, render: F)
+where
+    F: Fn(Option<String>) -> Fut,
+    Fut: Future<Output = Result<String, String>>,
+{
+    let request = match timeout(REQUEST_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(request)) => request,
+        Ok(Err(e)) => {
+            debug!("failed to read calendar request: {e}");
+            return;
+        }
+        Err(_) => {
+            debug!("calendar request timed out");
+            return;
+        }
+    };
+
+    let mut words = request.split(' ');
+    let (method, target) = (
+        words.next().unwrap_or_default(),
+        words.next().unwrap_or_default(),
+    );
+    let path = target.split('?').next().unwrap_or_default();
+
+    let (status, content_type, body) = match method {
+        "GET" | "HEAD" => match route(path, token) {
+            Some(tag) => match render(tag).await {
+                Ok(calendar) => ("200 OK", "text/calendar; charset=utf-8", calendar),
+                Err(e) => {
+                    warn!("failed to render calendar {path}: {e}");
+                    let body = "failed to render calendar".to_string();
+                    ("500 Internal Server Error", "text/plain", body)
+                }
+            },
+            None => ("404 Not Found", "text/plain", "not found".to_string()),
+        },
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "only GET is supported".to_string(),
+        ),
+    };
+
+    let mut response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    // HEAD gets the same headers without the body
+    if method != "HEAD" {
+        response += &body;
+    }
+
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        debug!("failed to send calendar: {e}");
+    }
+    let _ = stream.shutdown().await;
+}
+
+/// Reads up to the end of the headers and returns the request line
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<String> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 1024];
+
+    while !buffer.windows(4).any(|window| window == b"\r\n\r\n") {
+        if buffer.len() > MAX_REQUEST_SIZE {
+            return Err(std::io::Error::other("request too large"));
+        }
+
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&buffer);
+    Ok(request.lines().next().unwrap_or_default().to_string())
+}
+
+/// `None` for unknown paths, `Some(None)` for every note and `Some(Some(tag))` for one tag
+fn route(path: &str, token: &str) -> Option<Option<String>> {
+    let (given, path) = path.strip_prefix('/')?.split_once('/')?;
+    if !same_token(given, token) {
+        return None;
+    }
+
+    if path == "calendar.ics" {
+        return Some(None);
+    }
+
+    let tag = path.strip_prefix("calendar/")?.strip_suffix(".ics")?;
+    let tag = percent_decode(tag)?;
+    (!tag.is_empty()).then_some(Some(tag))
+}
+
+/// Compares every byte whatever the first difference, so response times don't give the token
+/// away a byte at a time
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
@@ -1,22 +1,36 @@
-use tokio::net::TcpListener;
+use futures::Stream;
+use tokio::{net::TcpListener, sync::broadcast};
 use tracing::info;
 
 use rust_shared::{request, response};
 
-use crate::server::websocket::handle_websocket;
+use crate::server::{
+    rate_limit::UserRateLimiter,
+    websocket::{SessionConfig, handle_websocket},
+};
 
-pub async fn start<F, Fut>(addr: &str, handle_message: F)
-where
-    F: Fn(request::Message) -> Fut + Send + Sync + Clone + 'static,
-    Fut: Future<Output = response::Message> + Send + 'static,
+/// Serves websocket sessions, each also receiving every message sent through `pushes`
+pub async fn start<F, S>(
+    addr: &str,
+    config: SessionConfig,
+    pushes: broadcast::Sender<response::Message>,
+    handle_message: F,
+) where
+    F: Fn(request::Message) -> S + Send + Sync + Clone + 'static,
+    S: Stream<Item = response::Message> + Send + 'static,
 {
     let listener = TcpListener::bind(addr).await.unwrap();
     info!("listening on {}", addr);
 
-    while let Ok((stream, _)) = listener.accept().await {
+    let user_limiter = UserRateLimiter::new(config.rate_limit.per_user);
+
+    while let Ok((stream, peer)) = listener.accept().await {
         let handle = handle_message.clone();
+        let user_limiter = user_limiter.clone();
+        let pushes = pushes.subscribe();
         tokio::spawn(async move {
-            handle_websocket(stream, handle).await;
+            info!("connection from {peer}");
+            handle_websocket(stream, config, user_limiter, pushes, handle).await;
         });
     }
 }
@@ -1,2 +1,4 @@
+pub mod calendar;
 pub mod listener;
+pub mod rate_limit;
 pub mod websocket;
@@ -0,0 +1,171 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+use crate::env_or_valid;
+
+/// Sustained request rate plus how many requests may arrive in a burst
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub per_second: f64,
+    pub burst: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitConfig {
+    pub per_connection: RateLimit,
+    pub per_user: RateLimit,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_connection: RateLimit {
+                per_second: 10.0,
+                burst: 20.0,
+            },
+            per_user: RateLimit {
+                per_second: 20.0,
+                burst: 40.0,
+            },
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Reads `RATE_LIMIT_CONNECTION_PER_SEC`, `RATE_LIMIT_CONNECTION_BURST`,
+    /// `RATE_LIMIT_USER_PER_SEC` and `RATE_LIMIT_USER_BURST`, using the defaults for anything unset
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            per_connection: RateLimit {
+                per_second: env_or_valid(
+                    "RATE_LIMIT_CONNECTION_PER_SEC",
+                    default.per_connection.per_second,
+                    is_rate,
+                ),
+                burst: env_or_valid(
+                    "RATE_LIMIT_CONNECTION_BURST",
+                    default.per_connection.burst,
+                    is_burst,
+                ),
+            },
+            per_user: RateLimit {
+                per_second: env_or_valid(
+                    "RATE_LIMIT_USER_PER_SEC",
+                    default.per_user.per_second,
+                    is_rate,
+                ),
+                burst: env_or_valid("RATE_LIMIT_USER_BURST", default.per_user.burst, is_burst),
+            },
+        }
+    }
+}
+
+// a bucket that never refills would turn everyone away for good, and waits are divided by it
+fn is_rate(per_second: &f64) -> bool {
+    per_second.is_finite() && *per_second > 0.0
+}
+
+// a bucket that can't hold a whole token never has one to take
+fn is_burst(burst: &f64) -> bool {
+    burst.is_finite() && *burst >= 1.0
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Takes a token, or returns how long to wait until one becomes available
+    pub fn try_take(&mut self) -> Result<(), Duration> {
+        self.check()?;
+        self.tokens -= 1.0;
+        Ok(())
+    }
+
+    /// Whether a token is available, without taking it, or how long to wait until one is
+    fn check(&mut self) -> Result<(), Duration> {
+        self.refill();
+
+        if self.tokens >= 1.0 {
+            return Ok(());
+        }
+
+        let missing = 1.0 - self.tokens;
+        Err(Duration::from_secs_f64(missing / self.limit.per_second))
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.per_second).min(self.limit.burst);
+        self.last_refill = now;
+    }
+
+    fn is_full(&mut self) -> bool {
+        self.refill();
+        self.tokens >= self.limit.burst
+    }
+}
+
+/// Buckets shared by every connection of the same user
+///
+/// There are no accounts yet, so the client's IP address stands in for the user.
+#[derive(Debug, Clone)]
+pub struct UserRateLimiter {
+    limit: RateLimit,
+    buckets: Arc<Mutex<HashMap<IpAddr, TokenBucket>>>,
+}
+
+impl UserRateLimiter {
+    // past this many tracked users, idle buckets get dropped
+    const PRUNE_THRESHOLD: usize = 1024;
+
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: Default::default(),
+        }
+    }
+
+    /// Takes a token from both `user`'s bucket and `connection`, or from neither, so a request
+    /// turned away by one limit doesn't count against the other
+    pub fn try_take(&self, user: IpAddr, connection: &mut TokenBucket) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().expect("failed to lock rate limiter");
+
+        if buckets.len() > Self::PRUNE_THRESHOLD {
+            // a full bucket behaves exactly like a fresh one, so it's safe to forget
+            buckets.retain(|_, bucket| !bucket.is_full());
+        }
+
+        let user_bucket = buckets
+            .entry(user)
+            .or_insert_with(|| TokenBucket::new(self.limit));
+        match (connection.check(), user_bucket.check()) {
+            (Ok(()), Ok(())) => {
+                connection.tokens -= 1.0;
+                user_bucket.tokens -= 1.0;
+                Ok(())
+            }
+            (Err(wait), Ok(())) | (Ok(()), Err(wait)) => Err(wait),
+            (Err(connection_wait), Err(user_wait)) => Err(connection_wait.max(user_wait)),
+        }
+    }
+}
@@ -1,51 +1,264 @@
-use futures::{SinkExt, stream::StreamExt};
-use tokio::net::TcpStream;
-use tokio_tungstenite::{accept_async, tungstenite::protocol::Message};
+use std::{fmt, time::Duration};
+
+use futures::{
+    SinkExt, Stream,
+    stream::{self, StreamExt},
+};
+use tokio::{
+    net::TcpStream,
+    sync::broadcast,
+    time::{Instant, MissedTickBehavior},
+};
+use tokio_tungstenite::{
+    accept_async_with_config,
+    tungstenite::{
+        Error as WsError,
+        protocol::{CloseFrame, Message, WebSocketConfig, frame::coding::CloseCode},
+    },
+};
 use tracing::{debug, info, warn};
 
 use rust_shared::{deserialize, request, response, serialize};
 
-pub async fn handle_websocket<F, Fut>(raw_stream: TcpStream, handle_message: F)
-where
-    F: Fn(request::Message) -> Fut + Send + Sync + Clone + 'static,
-    Fut: Future<Output = response::Message> + Send + 'static,
+use crate::{
+    env_or, env_or_valid,
+    server::rate_limit::{RateLimitConfig, TokenBucket, UserRateLimiter},
+};
+
+/// Timers and size limits applied to every websocket session
+#[derive(Debug, Clone, Copy)]
+pub struct SessionConfig {
+    /// How often the server pings the client to keep the connection alive
+    pub ping_interval: Duration,
+    /// How long a client may stay silent (including not answering pings) before being dropped
+    pub idle_timeout: Duration,
+    /// Largest accepted message in bytes, after reassembling its frames
+    pub max_message_size: usize,
+    /// Largest accepted single frame in bytes
+    pub max_frame_size: usize,
+    pub rate_limit: RateLimitConfig,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            ping_interval: Duration::from_secs(30),
+            idle_timeout: Duration::from_secs(90),
+            max_message_size: 1 << 20,
+            max_frame_size: 256 << 10,
+            rate_limit: RateLimitConfig::default(),
+        }
+    }
+}
+
+impl SessionConfig {
+    /// Reads `WS_PING_INTERVAL_SECS`, `WS_IDLE_TIMEOUT_SECS`, `WS_MAX_MESSAGE_SIZE` and
+    /// `WS_MAX_FRAME_SIZE` plus the rate limit variables, using the defaults for anything unset
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            // pings can't go out continuously
+            ping_interval: Duration::from_secs(env_or_valid(
+                "WS_PING_INTERVAL_SECS",
+                default.ping_interval.as_secs(),
+                |&secs| secs > 0,
+            )),
+            idle_timeout: Duration::from_secs(env_or(
+                "WS_IDLE_TIMEOUT_SECS",
+                default.idle_timeout.as_secs(),
+            )),
+            max_message_size: env_or("WS_MAX_MESSAGE_SIZE", default.max_message_size),
+            max_frame_size: env_or("WS_MAX_FRAME_SIZE", default.max_frame_size),
+            rate_limit: RateLimitConfig::from_env(),
+        }
+    }
+
+    fn websocket_config(&self) -> WebSocketConfig {
+        WebSocketConfig::default()
+            .max_message_size(Some(self.max_message_size))
+            .max_frame_size(Some(self.max_frame_size))
+    }
+}
+
+/// Why a session ended
+#[derive(Debug)]
+enum Disconnect {
+    Closed(Option<CloseFrame>),
+    StreamEnded,
+    IdleTimeout,
+    MessageTooLarge(WsError),
+    Error(WsError),
+}
+
+impl fmt::Display for Disconnect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Closed(Some(frame)) => {
+                write!(f, "closed by client ({}: {})", frame.code, frame.reason)
+            }
+            Self::Closed(None) => write!(f, "closed by client"),
+            Self::StreamEnded => write!(f, "connection dropped"),
+            Self::IdleTimeout => write!(f, "idle timeout"),
+            Self::MessageTooLarge(e) => write!(f, "message too large: {e}"),
+            Self::Error(e) => write!(f, "websocket error: {e}"),
+        }
+    }
+}
+
+pub async fn handle_websocket<F, S>(
+    raw_stream: TcpStream,
+    config: SessionConfig,
+    user_limiter: UserRateLimiter,
+    mut pushes: broadcast::Receiver<response::Message>,
+    handle_message: F,
+) where
+    F: Fn(request::Message) -> S + Send + Sync + Clone + 'static,
+    S: Stream<Item = response::Message> + Send + 'static,
 {
-    let ws_stream = accept_async(raw_stream).await.expect("accept_async failed");
+    let peer = match raw_stream.peer_addr() {
+        Ok(peer) => peer.ip(),
+        Err(e) => {
+            warn!("failed to get peer address: {e}");
+            return;
+        }
+    };
+
+    let ws_stream =
+        match accept_async_with_config(raw_stream, Some(config.websocket_config())).await {
+            Ok(ws_stream) => ws_stream,
+            Err(e) => {
+                warn!("websocket handshake failed: {e}");
+                return;
+            }
+        };
     let (mut outgoing, mut incoming) = ws_stream.split();
 
-    loop {
-        let msg = incoming.next().await;
-        let Some(msg) = msg else {
-            debug!("socket closed");
-            break;
+    // the first ping goes out one interval after connecting, not immediately
+    let mut keepalive =
+        tokio::time::interval_at(Instant::now() + config.ping_interval, config.ping_interval);
+    keepalive.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut last_activity = Instant::now();
+    let mut connection_bucket = TokenBucket::new(config.rate_limit.per_connection);
+
+    let reason = 'session: loop {
+        let msg = tokio::select! {
+            msg = incoming.next() => msg,
+            Some(push) = next_push(&mut pushes) => {
+                let push_text = serialize(push, *crate::DEV);
+                if let Err(e) = outgoing.send(Message::Text(push_text.into())).await {
+                    break Disconnect::Error(e);
+                }
+                continue;
+            }
+            _ = keepalive.tick() => {
+                if let Err(e) = outgoing.send(Message::Ping(Default::default())).await {
+                    break Disconnect::Error(e);
+                }
+                continue;
+            }
+            _ = tokio::time::sleep_until(last_activity + config.idle_timeout) => {
+                let _ = outgoing.send(close_message(CloseCode::Policy, "idle timeout")).await;
+                break Disconnect::IdleTimeout;
+            }
         };
 
-        // only process text messages
-        let Ok(Message::Text(text)) = msg else {
-            continue;
+        // any frame from the client, including pongs, counts as activity
+        last_activity = Instant::now();
+
+        let msg = match msg {
+            Some(Ok(msg)) => msg,
+            Some(Err(e @ WsError::Capacity(_))) => {
+                let _ = outgoing
+                    .send(close_message(CloseCode::Size, "message too large"))
+                    .await;
+                break Disconnect::MessageTooLarge(e);
+            }
+            Some(Err(e)) => break Disconnect::Error(e),
+            None => break Disconnect::StreamEnded,
         };
-        debug!("received message: {}", text);
 
-        // parse message
-        let parsed_message = match deserialize(&text) {
-            Ok(parsed_message) => {
-                info!("parsed message: {parsed_message:?}");
-                parsed_message
+        let parsed_message = match msg {
+            Message::Text(text) => {
+                debug!("received message: {}", text);
+                parse_message(&text)
             }
-            Err(e) => {
-                warn!("error parsing message: {e:?}");
-                request::Message::Unknown(text.to_string())
+            Message::Binary(_) => {
+                warn!("received binary message, only text is supported");
+                request::Message::Unknown("binary messages are not supported".to_string())
+            }
+            Message::Ping(_) => {
+                // tungstenite queues the pong itself, this just pushes it out right away
+                if let Err(e) = outgoing.flush().await {
+                    break Disconnect::Error(e);
+                }
+                continue;
+            }
+            Message::Pong(_) | Message::Frame(_) => continue,
+            Message::Close(frame) => break Disconnect::Closed(frame),
+        };
+
+        let rate_limited = user_limiter.try_take(peer, &mut connection_bucket);
+        let mut responses = match rate_limited {
+            Ok(()) => handle_message(parsed_message).boxed(),
+            Err(retry_after) => {
+                debug!("rate limited {peer}, retry after {retry_after:?}");
+                stream::iter([response::Message::RateLimited(response::RateLimited {
+                    retry_after_ms: retry_after.as_millis() as u64,
+                })])
+                .boxed()
             }
         };
 
-        let response = handle_message(parsed_message).await;
+        while let Some(response) = responses.next().await {
+            // serialize response
+            let response_text = serialize(response, *crate::DEV);
 
-        // serialize response
-        let response_text = serialize(response, *crate::DEV);
+            if let Err(e) = outgoing.send(Message::Text(response_text.into())).await {
+                break 'session Disconnect::Error(e);
+            }
+        }
+    };
 
-        outgoing
-            .send(Message::Text(response_text.into()))
-            .await
-            .expect("failed to send message");
+    // flushes a pending close reply, errors just mean the socket is already gone
+    let _ = outgoing.close().await;
+
+    match reason {
+        Disconnect::Error(_) => warn!("session ended: {reason}"),
+        _ => info!("session ended: {reason}"),
+    }
+}
+
+/// The next message pushed to every client, `None` once nothing can be pushed anymore
+async fn next_push(
+    pushes: &mut broadcast::Receiver<response::Message>,
+) -> Option<response::Message> {
+    loop {
+        match pushes.recv().await {
+            Ok(push) => return Some(push),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("client is too slow, skipped {skipped} pushed messages");
+            }
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
     }
 }
+
+fn parse_message(text: &str) -> request::Message {
+    match deserialize(text) {
+        Ok(parsed_message) => {
+            info!("parsed message: {parsed_message:?}");
+            parsed_message
+        }
+        Err(e) => {
+            warn!("error parsing message: {e:?}");
+            request::Message::Unknown(text.to_string())
+        }
+    }
+}
+
+fn close_message(code: CloseCode, reason: &str) -> Message {
+    Message::Close(Some(CloseFrame {
+        code,
+        reason: reason.into(),
+    }))
+}
@@ -0,0 +1,2000 @@
+use std::{net::IpAddr, time::Duration};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use futures::{SinkExt, Stream, StreamExt, stream};
+use tokio::{net::TcpListener, sync::broadcast, time::timeout};
+use tokio_tungstenite::{
+    MaybeTlsStream, WebSocketStream, connect_async,
+    tungstenite::protocol::{Message, frame::coding::CloseCode},
+};
+
+use rember_backend::{
+    engine::{
+        archive::{
+            backup::{self, BackupSchedule, ConflictPolicy},
+            ical,
+            markdown::{MAX_PENDING_BYTES, MAX_PENDING_TOTAL_BYTES, PendingImports},
+            vault,
+        },
+        core::{Engine, Limits, extracted_dates},
+        database::{error::Error, memory::MemoryStore, snapshot::Snapshot},
+        recurrence::Recurrence,
+        reminders::{self, ReminderConfig},
+    },
+    server::{
+        calendar,
+        rate_limit::{RateLimit, RateLimitConfig, TokenBucket, UserRateLimiter},
+        websocket::{SessionConfig, handle_websocket},
+    },
+};
+use rust_shared::{deserialize, request, response, response::derive_title, serialize};
+
+type Client = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+fn echo(msg: request::Message) -> impl Stream<Item = response::Message> {
+    stream::iter([response::Message::Unknown(format!("{msg:?}"))])
+}
+
+/// Starts a server on a random local port and returns its url
+async fn spawn_server(
+    config: SessionConfig,
+    pushes: broadcast::Sender<response::Message>,
+) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let user_limiter = UserRateLimiter::new(config.rate_limit.per_user);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(handle_websocket(
+                stream,
+                config,
+                user_limiter.clone(),
+                pushes.subscribe(),
+                echo,
+            ));
+        }
+    });
+
+    format!("ws://{addr}")
+}
+
+/// Connects to a server that never pushes anything
+async fn connect(config: SessionConfig) -> Client {
+    let (pushes, _) = broadcast::channel(1);
+    let (client, _) = connect_async(spawn_server(config, pushes).await)
+        .await
+        .unwrap();
+    client
+}
+
+async fn send_request(client: &mut Client) {
+    client
+        .send(Message::Text(r#"{"type": "get_tags"}"#.into()))
+        .await
+        .unwrap();
+}
+
+async fn next_message(client: &mut Client) -> Option<Message> {
+    timeout(Duration::from_secs(2), client.next())
+        .await
+        .expect("timed out waiting for the server")
+        .map(|msg| msg.expect("websocket error"))
+}
+
+#[tokio::test]
+async fn text_message_gets_response() {
+    let mut client = connect(SessionConfig::default()).await;
+
+    send_request(&mut client).await;
+
+    let Some(Message::Text(text)) = next_message(&mut client).await else {
+        panic!("expected a text response");
+    };
+    assert!(text.contains("GetTags"));
+}
+
+#[tokio::test]
+async fn malformed_message_is_answered_as_unknown() {
+    let mut client = connect(SessionConfig::default()).await;
+
+    client.send(Message::Text("not json".into())).await.unwrap();
+
+    let Some(Message::Text(text)) = next_message(&mut client).await else {
+        panic!("expected a text response");
+    };
+    assert!(text.contains("Unknown"));
+    assert!(text.contains("not json"));
+}
+
+#[tokio::test]
+async fn binary_message_is_rejected_without_closing() {
+    let mut client = connect(SessionConfig::default()).await;
+
+    client
+        .send(Message::Binary(vec![1, 2, 3].into()))
+        .await
+        .unwrap();
+    let Some(Message::Text(text)) = next_message(&mut client).await else {
+        panic!("expected a text response");
+    };
+    assert!(text.contains("binary messages are not supported"));
+
+    // the session is still usable
+    send_request(&mut client).await;
+    assert!(matches!(
+        next_message(&mut client).await,
+        Some(Message::Text(_))
+    ));
+}
+
+#[tokio::test]
+async fn ping_is_answered_with_pong() {
+    let mut client = connect(SessionConfig::default()).await;
+
+    client
+        .send(Message::Ping(b"hello".to_vec().into()))
+        .await
+        .unwrap();
+
+    let Some(Message::Pong(payload)) = next_message(&mut client).await else {
+        panic!("expected a pong");
+    };
+    assert_eq!(payload.as_ref(), b"hello");
+}
+
+#[tokio::test]
+async fn server_sends_keepalive_pings() {
+    let mut client = connect(SessionConfig {
+        ping_interval: Duration::from_millis(50),
+        ..Default::default()
+    })
+    .await;
+
+    assert!(matches!(
+        next_message(&mut client).await,
+        Some(Message::Ping(_))
+    ));
+}
+
+#[tokio::test]
+async fn idle_socket_is_closed() {
+    let mut client = connect(SessionConfig {
+        idle_timeout: Duration::from_millis(100),
+        ..Default::default()
+    })
+    .await;
+
+    let Some(Message::Close(Some(frame))) = next_message(&mut client).await else {
+        panic!("expected a close frame");
+    };
+    assert_eq!(frame.code, CloseCode::Policy);
+}
+
+#[tokio::test]
+async fn oversized_message_closes_socket() {
+    let mut client = connect(SessionConfig {
+        max_message_size: 1024,
+        max_frame_size: 1024,
+        ..Default::default()
+    })
+    .await;
+
+    client
+        .send(Message::Text("a".repeat(4096).into()))
+        .await
+        .unwrap();
+
+    let Some(Message::Close(Some(frame))) = next_message(&mut client).await else {
+        panic!("expected a close frame");
+    };
+    assert_eq!(frame.code, CloseCode::Size);
+}
+
+#[tokio::test]
+async fn connection_is_rate_limited() {
+    let limit = RateLimit {
+        per_second: 1.0,
+        burst: 2.0,
+    };
+    let mut client = connect(SessionConfig {
+        rate_limit: RateLimitConfig {
+            per_connection: limit,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .await;
+
+    for _ in 0..2 {
+        send_request(&mut client).await;
+        let Some(Message::Text(text)) = next_message(&mut client).await else {
+            panic!("expected a text response");
+        };
+        assert!(text.contains("GetTags"));
+    }
+
+    send_request(&mut client).await;
+    let Some(Message::Text(text)) = next_message(&mut client).await else {
+        panic!("expected a text response");
+    };
+    let Ok(response::Message::RateLimited(rate_limited)) = deserialize(&text) else {
+        panic!("expected a rate limited response, got {text}");
+    };
+    assert!(rate_limited.retry_after_ms > 0 && rate_limited.retry_after_ms <= 1000);
+}
+
+#[tokio::test]
+async fn user_limit_is_shared_between_connections() {
+    let config = SessionConfig {
+        rate_limit: RateLimitConfig {
+            per_user: RateLimit {
+                per_second: 0.1,
+                burst: 1.0,
+            },
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let (pushes, _) = broadcast::channel(1);
+    let url = spawn_server(config, pushes).await;
+
+    let (mut first, _) = connect_async(&url).await.unwrap();
+    let (mut second, _) = connect_async(&url).await.unwrap();
+
+    send_request(&mut first).await;
+    let Some(Message::Text(text)) = next_message(&mut first).await else {
+        panic!("expected a text response");
+    };
+    assert!(text.contains("GetTags"));
+
+    send_request(&mut second).await;
+    let Some(Message::Text(text)) = next_message(&mut second).await else {
+        panic!("expected a text response");
+    };
+    assert!(text.contains("rate_limited"));
+}
+
+#[test]
+fn limited_requests_take_no_tokens() {
+    let user = IpAddr::from([127, 0, 0, 1]);
+    let limit = RateLimit {
+        per_second: 0.001,
+        burst: 1.0,
+    };
+    let user_limiter = UserRateLimiter::new(limit);
+
+    let mut connection = TokenBucket::new(limit);
+    assert!(user_limiter.try_take(user, &mut connection).is_ok());
+
+    // turned away by the user's limit, keeping the new connection's token
+    let mut other_connection = TokenBucket::new(limit);
+    assert!(user_limiter.try_take(user, &mut other_connection).is_err());
+    assert!(other_connection.try_take().is_ok());
+
+    // and by the connection's, keeping the other user's token
+    let other_user = IpAddr::from([127, 0, 0, 2]);
+    assert!(user_limiter.try_take(other_user, &mut connection).is_err());
+    assert!(
+        user_limiter
+            .try_take(other_user, &mut TokenBucket::new(limit))
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn pushes_reach_every_session() {
+    let (pushes, _) = broadcast::channel(16);
+    let url = spawn_server(SessionConfig::default(), pushes.clone()).await;
+
+    let (mut first, _) = connect_async(&url).await.unwrap();
+    let (mut second, _) = connect_async(&url).await.unwrap();
+    // a request round trip makes sure both sessions are subscribed
+    for client in [&mut first, &mut second] {
+        send_request(client).await;
+        next_message(client).await.unwrap();
+    }
+
+    pushes
+        .send(response::Message::Unknown("pushed".to_string()))
+        .unwrap();
+    for client in [&mut first, &mut second] {
+        let Some(Message::Text(text)) = next_message(client).await else {
+            panic!("expected the pushed message");
+        };
+        assert!(text.contains("pushed"));
+    }
+}
+
+/// Sends a raw json request through the engine, the same way the server does
+async fn engine_stream(engine: &Engine, json: &str) -> Vec<response::Message> {
+    let request = deserialize(json).expect("invalid request");
+    engine
+        .handle_message(request)
+        .map(|response| deserialize(&serialize(response, false)).unwrap())
+        .collect()
+        .await
+}
+
+async fn engine_request(engine: &Engine, json: &str) -> response::Message {
+    let mut responses = engine_stream(engine, json).await;
+    assert_eq!(responses.len(), 1, "{responses:?}");
+    responses.remove(0)
+}
+
+async fn create_note(engine: &Engine, text: &str, tags: &[&str]) {
+    let tags = tags.iter().map(|tag| tag.to_string()).collect();
+    let response = engine
+        .handle_message(request::Message::CreateNote(request::CreateNote {
+            text: text.to_string(),
+            title: None,
+            tags,
+            extract_dates: false,
+            timezone: None,
+        }))
+        .next()
+        .await
+        .unwrap();
+    assert!(
+        matches!(response, response::Message::NoteCreated(_)),
+        "{response:?}"
+    );
+}
+
+async fn seed(engine: Engine) -> Engine {
+    for tag in ["idea", "work"] {
+        engine
+            .handle_message(request::Message::CreateTag(request::CreateTag {
+                name: tag.to_string(),
+            }))
+            .next()
+            .await;
+    }
+
+    create_note(&engine, "database design notes", &["work"]).await;
+    create_note(&engine, "an idea for a database", &["idea", "work"]).await;
+    create_note(&engine, "buy groceries", &[]).await;
+    engine
+}
+
+/// Runs each engine check against every store, which must all behave the same
+macro_rules! store_tests {
+    ($($name:ident),* $(,)?) => {
+        mod memory_store {
+            use super::*;
+
+            $(
+                #[tokio::test]
+                async fn $name() {
+                    super::$name(seed(Engine::new(MemoryStore::new())).await).await;
+                }
+            )*
+        }
+
+        #[cfg(feature = "sqlite")]
+        mod sqlite_store {
+            use super::*;
+            use rember_backend::engine::database::sqlite::SqliteStore;
+
+            async fn engine() -> Engine {
+                let path = std::env::temp_dir().join(format!("rember-{}.db", uuid::Uuid::new_v4()));
+                let store = SqliteStore::connect(&format!("sqlite://{}", path.display()))
+                    .await
+                    .unwrap();
+                seed(Engine::new(store)).await
+            }
+
+            $(
+                #[tokio::test]
+                async fn $name() {
+                    super::$name(engine().await).await;
+                }
+            )*
+        }
+    };
+}
+
+store_tests!(
+    filters_notes_by_all_tags,
+    ranks_fuzzy_matches,
+    pages_results,
+    rejects_unknown_tags,
+    rejects_duplicate_tags,
+    keeps_repeated_note_tags_once,
+    enforces_note_limits,
+    exports_every_note,
+    reimport_changes_nothing,
+    reimport_drops_removed_links,
+    import_restores_into_empty_store,
+    imports_a_file_at_a_time,
+    import_reports_bad_files,
+    imports_vault,
+    vault_dry_run_changes_nothing,
+    backup_restores_losslessly,
+    restore_applies_conflict_policy,
+    restore_keeps_tags_sharing_an_id,
+    exports_dates_as_ical,
+    notifies_due_reminders_once,
+    reminders_can_be_snoozed_and_acknowledged,
+    recurring_reminders_are_due_every_occurrence,
+    reimport_keeps_reminder_states,
+    restore_keeps_reminder_states,
+    searches_recurring_dates,
+    creates_notes_with_detected_dates,
+    filters_notes_by_creation_day,
+    searches_titles,
+    gets_note_details,
+    updates_and_deletes_notes,
+);
+
+fn texts(response: response::Message) -> Vec<String> {
+    let response::Message::Notes(notes) = response else {
+        panic!("expected notes, got {response:?}");
+    };
+    notes.into_iter().map(|note| note.text).collect()
+}
+
+fn created(response: response::Message) -> response::Note {
+    let response::Message::NoteCreated(note) = response else {
+        panic!("expected the new note, got {response:?}");
+    };
+    note
+}
+
+async fn filters_notes_by_all_tags(engine: Engine) {
+    let response = engine_request(
+        &engine,
+        r#"{"type": "get_notes_filtered", "data": {"tags": ["idea", "work"]}}"#,
+    )
+    .await;
+    assert_eq!(texts(response), ["an idea for a database"]);
+
+    // untagged notes still show up when no tags are requested
+    let response = engine_request(&engine, r#"{"type": "get_notes_filtered", "data": {}}"#).await;
+    assert_eq!(texts(response).len(), 3);
+}
+
+async fn ranks_fuzzy_matches(engine: Engine) {
+    let response = engine_request(
+        &engine,
+        r#"{"type": "get_notes_filtered", "data": {"search_text": "databse desing"}}"#,
+    )
+    .await;
+    assert_eq!(texts(response), ["database design notes"]);
+
+    let response = engine_request(
+        &engine,
+        r#"{"type": "get_notes_filtered", "data": {"search_text": "database"}}"#,
+    )
+    .await;
+    // same scores as pg_trgm gives, 0.43 and 0.41
+    assert_eq!(
+        texts(response),
+        ["database design notes", "an idea for a database"]
+    );
+}
+
+async fn pages_results(engine: Engine) {
+    let response = engine_request(
+        &engine,
+        r#"{"type": "get_notes_filtered", "data": {"limit": 1, "offset": 1}}"#,
+    )
+    .await;
+    assert_eq!(texts(response), ["an idea for a database"]);
+}
+
+async fn rejects_unknown_tags(engine: Engine) {
+    let response = engine_request(
+        &engine,
+        r#"{"type": "create_note", "data": {"text": "hi", "tags": ["work", "nope"]}}"#,
+    )
+    .await;
+    assert!(matches!(response, response::Message::Unknown(e) if e.contains("nope")));
+
+    // nothing was half-created
+    let response = engine_request(&engine, r#"{"type": "get_notes", "data": {}}"#).await;
+    assert_eq!(texts(response).len(), 3);
+}
+
+async fn rejects_duplicate_tags(engine: Engine) {
+    let response = engine_request(
+        &engine,
+        r#"{"type": "create_tag", "data": {"name": "idea"}}"#,
+    )
+    .await;
+    assert!(matches!(response, response::Message::Unknown(_)));
+
+    let response::Message::Tags(tags) = engine_request(&engine, r#"{"type": "get_tags"}"#).await
+    else {
+        panic!("expected tags");
+    };
+    assert_eq!(tags.len(), 2);
+}
+
+async fn keeps_repeated_note_tags_once(engine: Engine) {
+    async fn tag_names(engine: &Engine, id: uuid::Uuid) -> Vec<String> {
+        let request = format!(r#"{{"type": "get_note_details", "data": {{"id": "{id}"}}}}"#);
+        let response = engine_request(engine, &request).await;
+        let response::Message::NoteDetails(details) = response else {
+            panic!("expected note details, got {response:?}");
+        };
+        let mut names = details
+            .tags
+            .into_iter()
+            .map(|tag| tag.name)
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    let response = engine_request(
+        &engine,
+        r#"{"type": "create_note", "data": {"text": "twice", "tags": ["work", "idea", "work"]}}"#,
+    )
+    .await;
+    let id = created(response).id;
+    assert_eq!(tag_names(&engine, id).await, ["idea", "work"]);
+
+    let request = format!(
+        r#"{{"type": "update_note", "data": {{"id": "{id}", "text": "once", "tags": ["idea", "idea"]}}}}"#
+    );
+    let response = engine_request(&engine, &request).await;
+    assert!(
+        matches!(response, response::Message::NoteUpdated(_)),
+        "{response:?}"
+    );
+    assert_eq!(tag_names(&engine, id).await, ["idea"]);
+
+    let files = vec![markdown_file(
+        FIRST_ID,
+        "tags: [work, work]\n",
+        "imported twice",
+    )];
+    let report = import(&engine, files).await;
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!(
+        tag_names(&engine, FIRST_ID.parse().unwrap()).await,
+        ["work"]
+    );
+}
+
+async fn enforces_note_limits(engine: Engine) {
+    let engine = engine.with_limits(Limits {
+        max_note_length: 5,
+        max_title_length: 5,
+        max_tags_per_note: 1,
+    });
+
+    let response = engine_request(
+        &engine,
+        r#"{"type": "create_note", "data": {"text": "too long", "tags": []}}"#,
+    )
+    .await;
+    assert!(matches!(response, response::Message::Unknown(e) if e.contains("too long")));
+
+    let response = engine_request(
+        &engine,
+        r#"{"type": "create_note", "data": {"text": "ok", "tags": ["idea", "work"]}}"#,
+    )
+    .await;
+    assert!(matches!(response, response::Message::Unknown(e) if e.contains("too many tags")));
+
+    let response = engine_request(
+        &engine,
+        r#"{"type": "create_note", "data": {"text": "ok", "title": " too long ", "tags": []}}"#,
+    )
+    .await;
+    assert!(matches!(response, response::Message::Unknown(e) if e.contains("title is too long")));
+}
+
+async fn export(engine: &Engine) -> Vec<response::MarkdownFile> {
+    let mut responses = engine_stream(engine, r#"{"type": "export_markdown"}"#).await;
+
+    let Some(response::Message::ExportFinished(finished)) = responses.pop() else {
+        panic!("expected the export to finish, got {responses:?}");
+    };
+    let files = responses
+        .into_iter()
+        .map(|response| match response {
+            response::Message::MarkdownFile(file) => file,
+            response => panic!("expected a markdown file, got {response:?}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(files.len(), finished.count);
+    files
+}
+
+async fn import(engine: &Engine, files: Vec<response::MarkdownFile>) -> response::ImportReport {
+    let response = engine
+        .handle_message(request::Message::ImportMarkdown(request::ImportMarkdown {
+            files,
+        }))
+        .next()
+        .await
+        .unwrap();
+    let response::Message::Imported(report) = response else {
+        panic!("expected an import report, got {response:?}");
+    };
+    report
+}
+
+const FIRST_ID: &str = "00000000-0000-4000-8000-000000000001";
+const SECOND_ID: &str = "00000000-0000-4000-8000-000000000002";
+
+fn markdown_file(id: &str, front_matter: &str, text: &str) -> response::MarkdownFile {
+    response::MarkdownFile {
+        name: format!("{id}.md"),
+        content: format!(
+            "---\nid: {id}\ncreated_at: 2025-01-02T03:04:05Z\n{front_matter}---\n\n{text}"
+        ),
+    }
+}
+
+/// Two linked notes, with tags and dates in the different styles the importer accepts
+fn linked_files() -> Vec<response::MarkdownFile> {
+    vec![
+        markdown_file(
+            FIRST_ID,
+            &format!(
+                "tags: [work, \"new tag\"]\ndates:\n  - label: \"deadline\"\n    date: 2025-02-01\n  - date: 2025-03-01\nlinks:\n  - \"[[{SECOND_ID}]]\"\n"
+            ),
+            "imported note\n\nwith a second paragraph\n",
+        ),
+        markdown_file(SECOND_ID, "tags:\n  - idea\n", "---\nlinked note"),
+    ]
+}
+
+async fn exports_every_note(engine: Engine) {
+    let files = export(&engine).await;
+    assert_eq!(files.len(), 3);
+
+    let file = files
+        .iter()
+        .find(|file| file.content.ends_with("\n\nan idea for a database"))
+        .expect("missing note");
+    assert!(file.content.contains("tags:\n  - \"idea\"\n  - \"work\"\n"));
+    assert!(file.name.ends_with(".md"));
+}
+
+async fn reimport_changes_nothing(engine: Engine) {
+    let report = import(&engine, linked_files()).await;
+    assert_eq!((report.created, report.updated, report.linked), (2, 0, 1));
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+
+    let exported = export(&engine).await;
+    let imported = exported
+        .iter()
+        .find(|file| file.name == format!("{FIRST_ID}.md"))
+        .unwrap();
+    assert!(
+        imported
+            .content
+            .contains("  - label: \"deadline\"\n    date: 2025-02-01\n")
+    );
+    assert!(
+        imported
+            .content
+            .contains(&format!("  - \"[[{SECOND_ID}]]\""))
+    );
+    assert!(
+        imported
+            .content
+            .ends_with("\n---\n\nimported note\n\nwith a second paragraph\n")
+    );
+
+    let report = import(&engine, exported.clone()).await;
+    assert_eq!((report.created, report.updated, report.unlinked), (0, 5, 0));
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!(export(&engine).await, exported);
+}
+
+async fn reimport_drops_removed_links(engine: Engine) {
+    import(&engine, linked_files()).await;
+
+    // the link taken out of the first file, the second never listed it
+    let mut files = linked_files();
+    files[0].content = files[0]
+        .content
+        .replace(&format!("links:\n  - \"[[{SECOND_ID}]]\"\n"), "");
+    let report = import(&engine, files).await;
+    assert_eq!((report.linked, report.unlinked), (0, 1));
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+
+    let exported = export(&engine).await;
+    assert!(
+        exported
+            .iter()
+            .all(|file| file.content.contains("links: []"))
+    );
+
+    // listing it on either side is enough to keep it
+    let mut files = linked_files();
+    files[1].content = files[1]
+        .content
+        .replace("tags:", &format!("links: [\"[[{FIRST_ID}]]\"]\ntags:"));
+    import(&engine, files.clone()).await;
+    files[0].content = files[0]
+        .content
+        .replace(&format!("links:\n  - \"[[{SECOND_ID}]]\"\n"), "");
+    let report = import(&engine, files).await;
+    assert_eq!((report.linked, report.unlinked), (1, 0));
+}
+
+async fn import_restores_into_empty_store(engine: Engine) {
+    import(&engine, linked_files()).await;
+    let exported = export(&engine).await;
+
+    let restored = Engine::new(MemoryStore::new());
+    let report = import(&restored, exported.clone()).await;
+    assert_eq!((report.created, report.linked), (5, 1));
+    assert_eq!(export(&restored).await, exported);
+
+    let response = engine_request(
+        &restored,
+        r#"{"type": "get_notes_filtered", "data": {"tags": ["new tag"]}}"#,
+    )
+    .await;
+    assert_eq!(
+        texts(response),
+        ["imported note\n\nwith a second paragraph\n"]
+    );
+}
+
+async fn respond(engine: &Engine, request: request::Message) -> response::Message {
+    engine.handle_message(request).next().await.unwrap()
+}
+
+async fn imports_a_file_at_a_time(engine: Engine) {
+    let response::Message::ImportStarted(response::ImportStarted { id }) =
+        respond(&engine, request::Message::ImportBegin).await
+    else {
+        panic!("expected the import to start");
+    };
+    for (i, file) in linked_files().into_iter().enumerate() {
+        let response = respond(
+            &engine,
+            request::Message::ImportFile(request::ImportFile { id, file }),
+        )
+        .await;
+        let response::Message::ImportFileReceived(received) = response else {
+            panic!("expected the file to be received, got {response:?}");
+        };
+        assert_eq!((received.id, received.count), (id, i + 1));
+    }
+
+    let finish = || request::Message::ImportFinish(request::ImportFinish { id });
+    let response = respond(&engine, finish()).await;
+    let response::Message::Imported(report) = response else {
+        panic!("expected an import report, got {response:?}");
+    };
+    assert_eq!((report.created, report.updated, report.linked), (2, 0, 1));
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+
+    // the import is closed once it's finished
+    let response = respond(&engine, finish()).await;
+    assert!(
+        matches!(response, response::Message::Unknown(ref e) if e.contains("no import")),
+        "{response:?}"
+    );
+    let response = respond(
+        &engine,
+        request::Message::ImportFile(request::ImportFile {
+            id,
+            file: linked_files().remove(0),
+        }),
+    )
+    .await;
+    assert!(
+        matches!(response, response::Message::Unknown(_)),
+        "{response:?}"
+    );
+}
+
+#[test]
+fn pending_imports_are_capped_in_bytes() {
+    let file = |bytes: usize| response::MarkdownFile {
+        name: String::new(),
+        content: "x".repeat(bytes),
+    };
+    let imports = PendingImports::default();
+
+    let first = imports.begin().unwrap();
+    assert_eq!(imports.add(first, file(MAX_PENDING_BYTES)), Ok(1));
+    let error = imports.add(first, file(1)).unwrap_err();
+    assert!(error.contains("too large"), "{error}");
+
+    // every import together holds no more than the total
+    let second = imports.begin().unwrap();
+    let room = MAX_PENDING_TOTAL_BYTES - MAX_PENDING_BYTES;
+    assert_eq!(imports.add(second, file(room)), Ok(1));
+    let third = imports.begin().unwrap();
+    let error = imports.add(third, file(1)).unwrap_err();
+    assert!(error.contains("too large"), "{error}");
+
+    // until one of them is finished
+    imports.finish(first).unwrap();
+    assert_eq!(imports.add(third, file(1)), Ok(1));
+}
+
+async fn import_reports_bad_files(engine: Engine) {
+    let missing = "00000000-0000-4000-8000-000000000099";
+    let files = vec![
+        response::MarkdownFile {
+            name: "plain.md".to_string(),
+            content: "no front matter here".to_string(),
+        },
+        markdown_file(FIRST_ID, "dates:\n  - date: someday\n", "bad date"),
+        markdown_file(
+            SECOND_ID,
+            &format!("links: [\"[[{missing}]]\"]\n"),
+            "dangling link",
+        ),
+        response::MarkdownFile {
+            name: "naive.md".to_string(),
+            content: "---\nid: 00000000-0000-4000-8000-000000000003\ncreated_at: 2025-01-02T03:04:05\n---\n\nno offset".to_string(),
+        },
+    ];
+
+    let report = import(&engine, files).await;
+    assert_eq!((report.created, report.linked), (1, 0));
+    assert_eq!(report.errors.len(), 4, "{:?}", report.errors);
+    assert!(report.errors[0].starts_with("plain.md"));
+    assert!(report.errors[1].contains("someday"));
+    assert!(report.errors[2].contains("invalid created_at 2025-01-02T03:04:05"));
+    assert!(report.errors[3].contains(missing));
+}
+
+/// A small Obsidian vault in a fresh temporary directory
+fn write_vault() -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("rember-vault-{}", uuid::Uuid::new_v4()));
+    let files = [
+        (
+            "Projects/Rember.md",
+            "---\ntags: [work, Rust]\naliases: [rmb]\n---\nSee [[Reading list|the list]] and [[Missing page]].\n\n```\n#not-a-tag [[nor a link]]\n```\n",
+        ),
+        (
+            "Reading list.md",
+            "books for #Idea and `#code` about [[rmb#Design]] ![[cover.png]]\n",
+        ),
+        (".obsidian/workspace.md", "ignored"),
+    ];
+    for (path, content) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, content).unwrap();
+    }
+
+    // the earlier of the file's creation and modification times becomes created_at
+    let old = std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    std::fs::File::options()
+        .write(true)
+        .open(dir.join("Reading list.md"))
+        .unwrap()
+        .set_modified(old)
+        .unwrap();
+
+    dir
+}
+
+async fn imports_vault(engine: Engine) {
+    let dir = write_vault();
+    let vault = vault::read_vault(&dir).unwrap();
+    assert_eq!(
+        vault.unresolved_links,
+        ["Projects/Rember.md: [[Missing page]]"]
+    );
+
+    let report = vault::import_vault(engine.store(), &Limits::default(), &vault, false)
+        .await
+        .unwrap();
+    assert_eq!(report.created, ["Projects/Rember.md", "Reading list.md"]);
+    // `#Idea` lands on the existing `idea` tag
+    assert_eq!(report.new_tags, ["Rust"]);
+    assert_eq!(report.links, 1);
+
+    let files = export(&engine).await;
+    let reading_list = files
+        .iter()
+        .find(|file| file.content.contains("title: \"Reading list\"\n"))
+        .unwrap();
+    assert!(
+        reading_list
+            .content
+            .contains("created_at: 2020-09-13T12:26:40Z\n")
+    );
+    assert!(reading_list.content.contains("tags:\n  - \"idea\"\n"));
+    let rember = files
+        .iter()
+        .find(|file| file.content.contains("title: \"Rember\"\n"))
+        .unwrap();
+    assert!(
+        rember
+            .content
+            .contains("tags:\n  - \"Rust\"\n  - \"work\"\n")
+    );
+    assert!(rember.content.ends_with("---\n\nSee [[Reading list|the list]] and [[Missing page]].\n\n```\n#not-a-tag [[nor a link]]\n```\n"));
+
+    // the ids come from the paths, so a second import updates the same notes
+    let report = vault::import_vault(engine.store(), &Limits::default(), &vault, false)
+        .await
+        .unwrap();
+    assert_eq!((report.created.len(), report.updated.len()), (0, 2));
+    assert_eq!(export(&engine).await, files);
+
+    // a date added to an imported note since stays through the next import
+    let dated = response::MarkdownFile {
+        name: rember.name.clone(),
+        content: rember.content.replace(
+            "dates: []\n",
+            "dates:\n  - label: \"due\"\n    date: 2026-11-01\n",
+        ),
+    };
+    let report = import(&engine, vec![dated]).await;
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    vault::import_vault(engine.store(), &Limits::default(), &vault, false)
+        .await
+        .unwrap();
+    let rember = export(&engine)
+        .await
+        .into_iter()
+        .find(|file| file.name == rember.name)
+        .unwrap();
+    assert!(rember.content.contains("2026-11-01"), "{}", rember.content);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+async fn vault_dry_run_changes_nothing(engine: Engine) {
+    let dir = write_vault();
+    let vault = vault::read_vault(&dir).unwrap();
+    let before = export(&engine).await;
+
+    let report = vault::import_vault(engine.store(), &Limits::default(), &vault, true)
+        .await
+        .unwrap();
+    assert_eq!(report.created.len(), 2);
+    assert_eq!(report.new_tags, ["Rust"]);
+    assert_eq!(report.links, 1);
+    assert_eq!(export(&engine).await, before);
+
+    // a skipped note's tags aren't created
+    let limits = Limits {
+        max_tags_per_note: 1,
+        ..Limits::default()
+    };
+    let report = vault::import_vault(engine.store(), &limits, &vault, true)
+        .await
+        .unwrap();
+    assert_eq!(report.created, ["Reading list.md"]);
+    assert!(report.new_tags.is_empty(), "{:?}", report.new_tags);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+fn backup_bytes(snapshot: &Snapshot) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    backup::write_backup(snapshot, &mut bytes).unwrap();
+    bytes
+}
+
+/// The backup's rows, without the header and its timestamp
+async fn rows(engine: &Engine) -> String {
+    let bytes = backup_bytes(&engine.store().snapshot().await.unwrap());
+    let text = String::from_utf8(bytes).unwrap();
+    text.split_once('\n').unwrap().1.to_string()
+}
+
+async fn backup_restores_losslessly(engine: Engine) {
+    import(&engine, linked_files()).await;
+    let bytes = backup_bytes(&engine.store().snapshot().await.unwrap());
+    assert!(bytes.starts_with(br#"{"kind":"header","schema_version":1,"#));
+
+    let restored = Engine::new(MemoryStore::new());
+    let snapshot = backup::read_backup(bytes.as_slice()).unwrap();
+    let report = backup::restore(restored.store(), &snapshot, ConflictPolicy::Fail)
+        .await
+        .unwrap();
+    assert_eq!(
+        (report.created, report.tags_created, report.links),
+        (5, 3, 1)
+    );
+    assert_eq!(rows(&restored).await, rows(&engine).await);
+}
+
+async fn restore_applies_conflict_policy(engine: Engine) {
+    let snapshot = engine.store().snapshot().await.unwrap();
+    import(&engine, linked_files()).await;
+
+    // change a note that's also in the backup
+    let mut files = export(&engine).await;
+    let file = files
+        .iter_mut()
+        .find(|file| file.content.ends_with("buy groceries"))
+        .unwrap();
+    file.content = file.content.replace("buy groceries", "buy flowers");
+    import(&engine, vec![file.clone()]).await;
+
+    let Err(backup::Error::Conflict(conflicts)) =
+        backup::restore(engine.store(), &snapshot, ConflictPolicy::Fail).await
+    else {
+        panic!("expected a conflict");
+    };
+    assert_eq!(conflicts.len(), 3);
+
+    let report = backup::restore(engine.store(), &snapshot, ConflictPolicy::Skip)
+        .await
+        .unwrap();
+    assert_eq!(
+        (report.skipped, report.created, report.tags_created),
+        (3, 0, 0)
+    );
+    let notes = texts(engine_request(&engine, r#"{"type": "get_notes", "data": {}}"#).await);
+    assert!(notes.contains(&"buy flowers".to_string()));
+
+    let report = backup::restore(engine.store(), &snapshot, ConflictPolicy::Overwrite)
+        .await
+        .unwrap();
+    assert_eq!(report.overwritten, 3);
+    let notes = texts(engine_request(&engine, r#"{"type": "get_notes", "data": {}}"#).await);
+    assert!(notes.contains(&"buy groceries".to_string()));
+    // notes that aren't in the backup are left alone
+    assert_eq!(notes.len(), 5);
+}
+
+async fn restore_keeps_tags_sharing_an_id(engine: Engine) {
+    let idea = engine
+        .store()
+        .snapshot()
+        .await
+        .unwrap()
+        .tags
+        .into_iter()
+        .find(|tag| tag.name == "idea")
+        .unwrap();
+
+    // a backup from another store whose tag happens to have the same id
+    let other = Engine::new(MemoryStore::new());
+    engine_request(
+        &other,
+        r#"{"type": "create_tag", "data": {"name": "idea"}}"#,
+    )
+    .await;
+    create_note(&other, "plan a trip", &["idea"]).await;
+    let mut backup = other.store().snapshot().await.unwrap();
+    let tag = backup
+        .tags
+        .iter_mut()
+        .find(|tag| tag.name == "idea")
+        .unwrap();
+    let old_id = std::mem::replace(&mut tag.id, idea.id);
+    tag.name = "plan".to_string();
+    for note_tag in &mut backup.note_tags {
+        if note_tag.tag_id == old_id {
+            note_tag.tag_id = idea.id;
+        }
+    }
+
+    let report = backup::restore(engine.store(), &backup, ConflictPolicy::Fail)
+        .await
+        .unwrap();
+    assert_eq!(report.tags_created, 1);
+
+    let response = engine_request(
+        &engine,
+        r#"{"type": "get_notes_filtered", "data": {"tags": ["idea"]}}"#,
+    )
+    .await;
+    assert_eq!(texts(response), ["an idea for a database"]);
+    let response = engine_request(
+        &engine,
+        r#"{"type": "get_notes_filtered", "data": {"tags": ["plan"]}}"#,
+    )
+    .await;
+    assert_eq!(texts(response), ["plan a trip"]);
+}
+
+#[test]
+fn backup_checks_schema_version() {
+    let newer = br#"{"kind":"header","schema_version":99,"created_at":"2025-01-01T00:00:00Z"}"#;
+    assert!(matches!(
+        backup::read_backup(newer.as_slice()),
+        Err(backup::Error::UnsupportedVersion(99))
+    ));
+
+    let headless = br#"{"kind":"tag","id":"00000000-0000-4000-8000-000000000001","name":"x"}"#;
+    assert!(matches!(
+        backup::read_backup(headless.as_slice()),
+        Err(backup::Error::MissingHeader)
+    ));
+}
+
+#[tokio::test]
+async fn scheduled_backups_keep_the_latest() {
+    let engine = seed(Engine::new(MemoryStore::new())).await;
+    let dir = std::env::temp_dir().join(format!("rember-backups-{}", uuid::Uuid::new_v4()));
+    let schedule = BackupSchedule {
+        dir: dir.clone(),
+        interval: Duration::from_secs(60),
+        keep: 2,
+    };
+
+    let mut paths = Vec::new();
+    for _ in 0..3 {
+        paths.push(schedule.backup(engine.store()).await.unwrap());
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    let mut kept = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    kept.sort();
+    assert_eq!(kept, paths[1..]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+async fn exports_dates_as_ical(engine: Engine) {
+    let birthday = markdown_file(
+        FIRST_ID,
+        "tags: [friends]\ndates:\n  - label: \"birthday\"\n    date: 1990-05-17\n",
+        "# Alex, likes tea; not coffee\n\nbring a gift",
+    );
+    import(&engine, vec![birthday]).await;
+    import(&engine, linked_files().split_off(1)).await;
+
+    let calendar = ical::export(engine.store(), None).await.unwrap();
+    assert!(calendar.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+    assert_eq!(calendar.matches("BEGIN:VEVENT").count(), 1);
+    assert!(calendar.contains("DTSTART;VALUE=DATE:19900517\r\nDTEND;VALUE=DATE:19900518\r\n"));
+    assert!(calendar.contains("RRULE:FREQ=YEARLY\r\n"));
+    assert!(calendar.contains("SUMMARY:birthday: Alex\\, likes tea\\; not coffee\r\n"));
+    assert!(
+        calendar.contains("DESCRIPTION:# Alex\\, likes tea\\; not coffee\\n\\nbring a gift\r\n")
+    );
+    assert!(calendar.contains("CATEGORIES:friends\r\n"));
+
+    let calendar = ical::export(engine.store(), Some("work")).await.unwrap();
+    assert!(!calendar.contains("BEGIN:VEVENT"));
+}
+
+#[test]
+fn ical_maps_recurring_labels() {
+    assert_eq!(ical::recurrence_for_label("Birthday"), Some("FREQ=YEARLY"));
+    assert_eq!(
+        ical::recurrence_for_label("weekly standup"),
+        Some("FREQ=WEEKLY")
+    );
+    assert_eq!(ical::recurrence_for_label("deadline"), None);
+}
+
+/// Serves `calendar` for the tag `work` and a 404 for anything else
+async fn calendar_request(request: &str) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        calendar::handle_connection(stream, "secret", |tag: Option<String>| async move {
+            Ok(format!("calendar for {tag:?}"))
+        })
+        .await;
+    });
+
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    timeout(Duration::from_secs(2), stream.read_to_string(&mut response))
+        .await
+        .unwrap()
+        .unwrap();
+    response
+}
+
+#[tokio::test]
+async fn calendar_feed_serves_tags() {
+    let response =
+        calendar_request("GET /secret/calendar/to%20do.ics HTTP/1.1\r\nHost: x\r\n\r\n").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+    assert!(response.contains("Content-Type: text/calendar; charset=utf-8\r\n"));
+    assert!(response.ends_with("\r\n\r\ncalendar for Some(\"to do\")"));
+
+    let response = calendar_request("GET /secret/calendar.ics?x=1 HTTP/1.1\r\n\r\n").await;
+    assert!(response.ends_with("calendar for None"));
+
+    let response = calendar_request("HEAD /secret/calendar.ics HTTP/1.1\r\n\r\n").await;
+    assert!(response.contains("Content-Length: 17\r\n"));
+    assert!(response.ends_with("\r\n\r\n"));
+
+    for path in [
+        "/notes",
+        "/calendar.ics",
+        "/secreT/calendar.ics",
+        "/secret/../calendar.ics",
+    ] {
+        let response = calendar_request(&format!("GET {path} HTTP/1.1\r\n\r\n")).await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"), "{path}");
+    }
+}
+
+fn at(date: &str, time: &str) -> DateTime<Utc> {
+    NaiveDateTime::parse_from_str(&format!("{date} {time}"), "%Y-%m-%d %H:%M")
+        .unwrap()
+        .and_utc()
+}
+
+fn date(date: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+}
+
+/// A note with a reminder on 2025-01-10, a deadline that same day and a reminder on 2025-02-01
+async fn import_reminders(engine: &Engine) {
+    let file = markdown_file(
+        FIRST_ID,
+        "dates:\n  - label: \"Reminder\"\n    date: 2025-01-10\n  - label: \"deadline\"\n    date: 2025-01-10\n  - label: \"reminder\"\n    date: 2025-02-01\n",
+        "renew the passport",
+    );
+    import(engine, vec![file]).await;
+}
+
+async fn notifies_due_reminders_once(engine: Engine) {
+    import_reminders(&engine).await;
+    let config = ReminderConfig::default();
+    let (pushes, mut pushed) = broadcast::channel(16);
+
+    let now = at("2025-01-10", "09:00");
+    let notified = reminders::notify_due(engine.store(), &config, now, &pushes)
+        .await
+        .unwrap();
+    assert_eq!(notified, 1);
+    let Ok(response::Message::Reminder(reminder)) = pushed.try_recv() else {
+        panic!("expected a pushed reminder");
+    };
+    assert_eq!(reminder.label.as_deref(), Some("Reminder"));
+    assert_eq!(reminder.note.text, "renew the passport");
+
+    // still due, but clients already know
+    let notified = reminders::notify_due(engine.store(), &config, now, &pushes)
+        .await
+        .unwrap();
+    assert_eq!(notified, 0);
+    assert!(pushed.try_recv().is_err());
+
+    // the lead time brings the next one forward
+    let config = ReminderConfig {
+        lead_days: 30,
+        ..config
+    };
+    let due = reminders::due(engine.store(), &config, now).await.unwrap();
+    let dates = due
+        .iter()
+        .map(|pending| pending.reminder.date)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        dates,
+        [
+            NaiveDate::from_ymd_opt(2025, 1, 10).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 2, 1).unwrap()
+        ]
+    );
+}
+
+async fn reminders_can_be_snoozed_and_acknowledged(engine: Engine) {
+    import_reminders(&engine).await;
+    let config = ReminderConfig::default();
+    let (pushes, _pushed) = broadcast::channel(16);
+
+    let now = at("2025-01-10", "09:00");
+    reminders::notify_due(engine.store(), &config, now, &pushes)
+        .await
+        .unwrap();
+    let due = reminders::due(engine.store(), &config, now).await.unwrap();
+    let id = due[0].reminder.id;
+
+    engine
+        .store()
+        .snooze_reminder(id, date("2025-01-10"), at("2025-01-10", "10:00"))
+        .await
+        .unwrap();
+    let snoozed = reminders::due(engine.store(), &config, at("2025-01-10", "09:30"))
+        .await
+        .unwrap();
+    assert!(snoozed.is_empty());
+
+    // pushed again once the snooze is over
+    let notified =
+        reminders::notify_due(engine.store(), &config, at("2025-01-10", "10:30"), &pushes)
+            .await
+            .unwrap();
+    assert_eq!(notified, 1);
+
+    engine
+        .store()
+        .acknowledge_reminder(id, date("2025-01-10"), at("2025-01-10", "10:31"))
+        .await
+        .unwrap();
+    let due = reminders::due(engine.store(), &config, at("2025-01-11", "00:00"))
+        .await
+        .unwrap();
+    assert!(due.is_empty());
+
+    let unknown = uuid::Uuid::new_v4();
+    let result = engine
+        .store()
+        .acknowledge_reminder(unknown, date("2025-01-10"), now)
+        .await;
+    assert!(matches!(result, Err(Error::ReminderNotFound(id)) if id == unknown));
+
+    // requests answer with what's still due now, which is the February reminder
+    let response = engine_request(&engine, r#"{"type": "get_reminders"}"#).await;
+    let response::Message::Reminders(due) = response else {
+        panic!("expected reminders, got {response:?}");
+    };
+    assert_eq!(due.len(), 1);
+    let json = format!(
+        r#"{{"type": "snooze_reminder", "data": {{"id": "{}", "date": "{}", "minutes": 60}}}}"#,
+        due[0].id, due[0].date
+    );
+    let response = engine_request(&engine, &json).await;
+    assert!(matches!(response, response::Message::Reminders(due) if due.is_empty()));
+}
+
+async fn recurring_reminders_are_due_every_occurrence(engine: Engine) {
+    // every Monday from 2026-01-05
+    let file = markdown_file(
+        FIRST_ID,
+        "dates:\n  - label: \"reminder\"\n    date: 2026-01-05\n    rrule: \"FREQ=WEEKLY\"\n",
+        "water the plants",
+    );
+    import(&engine, vec![file]).await;
+    let config = ReminderConfig::default();
+    let (pushes, _pushed) = broadcast::channel(16);
+    async fn due_dates(engine: &Engine, now: DateTime<Utc>) -> Vec<NaiveDate> {
+        let due = reminders::due(engine.store(), &ReminderConfig::default(), now)
+            .await
+            .unwrap();
+        due.iter().map(|pending| pending.reminder.date).collect()
+    }
+
+    let now = at("2026-01-05", "09:00");
+    assert_eq!(due_dates(&engine, now).await, [date("2026-01-05")]);
+    let notified = reminders::notify_due(engine.store(), &config, now, &pushes)
+        .await
+        .unwrap();
+    assert_eq!(notified, 1);
+    let id = reminders::due(engine.store(), &config, now).await.unwrap()[0]
+        .reminder
+        .id;
+    engine
+        .store()
+        .acknowledge_reminder(id, date("2026-01-05"), now)
+        .await
+        .unwrap();
+    assert!(
+        due_dates(&engine, at("2026-01-08", "09:00"))
+            .await
+            .is_empty()
+    );
+
+    // the next Monday is due and pushed in its own right
+    let now = at("2026-01-12", "09:00");
+    assert_eq!(due_dates(&engine, now).await, [date("2026-01-12")]);
+    let notified = reminders::notify_due(engine.store(), &config, now, &pushes)
+        .await
+        .unwrap();
+    assert_eq!(notified, 1);
+
+    // a missed occurrence stays due next to the new one, older ones are let go
+    assert_eq!(
+        due_dates(&engine, at("2026-01-19", "09:00")).await,
+        [date("2026-01-12"), date("2026-01-19")]
+    );
+    assert_eq!(
+        due_dates(&engine, at("2026-01-27", "09:00")).await,
+        [date("2026-01-26")]
+    );
+}
+
+async fn reimport_keeps_reminder_states(engine: Engine) {
+    import_reminders(&engine).await;
+    let config = ReminderConfig::default();
+    let now = at("2025-01-10", "09:00");
+    let due = reminders::due(engine.store(), &config, now).await.unwrap();
+    engine
+        .store()
+        .acknowledge_reminder(due[0].reminder.id, due[0].reminder.date, now)
+        .await
+        .unwrap();
+
+    // same January reminder, the others changed
+    let file = markdown_file(
+        FIRST_ID,
+        "dates:\n  - label: \"Reminder\"\n    date: 2025-01-10\n  - label: \"reminder\"\n    date: 2025-02-02\n",
+        "renew the passport soon",
+    );
+    import(&engine, vec![file]).await;
+
+    let due = reminders::due(engine.store(), &config, at("2025-03-01", "00:00"))
+        .await
+        .unwrap();
+    let dates = due
+        .iter()
+        .map(|pending| pending.reminder.date)
+        .collect::<Vec<_>>();
+    assert_eq!(dates, [date("2025-02-02")]);
+}
+
+async fn restore_keeps_reminder_states(engine: Engine) {
+    import_reminders(&engine).await;
+    let before = engine.store().snapshot().await.unwrap();
+    let config = ReminderConfig::default();
+    let now = at("2025-01-10", "09:00");
+    let due = reminders::due(engine.store(), &config, now).await.unwrap();
+    engine
+        .store()
+        .acknowledge_reminder(due[0].reminder.id, due[0].reminder.date, now)
+        .await
+        .unwrap();
+    async fn due_dates(engine: &Engine) -> Vec<NaiveDate> {
+        let config = ReminderConfig::default();
+        let due = reminders::due(engine.store(), &config, at("2025-03-01", "00:00"))
+            .await
+            .unwrap();
+        due.iter().map(|pending| pending.reminder.date).collect()
+    }
+
+    // overwriting with a backup from before keeps what's been acknowledged since
+    backup::restore(engine.store(), &before, ConflictPolicy::Overwrite)
+        .await
+        .unwrap();
+    assert_eq!(due_dates(&engine).await, [date("2025-02-01")]);
+
+    let bytes = backup_bytes(&engine.store().snapshot().await.unwrap());
+    assert!(String::from_utf8_lossy(&bytes).contains(r#"{"kind":"reminder_state","#));
+    let restored = Engine::new(MemoryStore::new());
+    let snapshot = backup::read_backup(bytes.as_slice()).unwrap();
+    backup::restore(restored.store(), &snapshot, ConflictPolicy::Fail)
+        .await
+        .unwrap();
+    assert_eq!(due_dates(&restored).await, [date("2025-02-01")]);
+}
+
+fn first_occurrences(rule: &str, start: &str, count: usize) -> Vec<NaiveDate> {
+    let rule = rule.parse::<Recurrence>().unwrap();
+    rule.occurrences(date(start)).take(count).collect()
+}
+
+#[test]
+fn recurrence_expands_rules() {
+    assert_eq!(
+        first_occurrences("FREQ=MONTHLY;BYDAY=1MO", "2025-01-01", 3),
+        [date("2025-01-06"), date("2025-02-03"), date("2025-03-03")]
+    );
+    assert_eq!(
+        first_occurrences(
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=TU,TH;COUNT=4",
+            "2025-01-07",
+            10
+        ),
+        [
+            date("2025-01-07"),
+            date("2025-01-09"),
+            date("2025-01-21"),
+            date("2025-01-23")
+        ]
+    );
+    assert_eq!(
+        first_occurrences(
+            "FREQ=MONTHLY;BYMONTHDAY=-1;UNTIL=20250401",
+            "2025-01-15",
+            10
+        ),
+        [date("2025-01-31"), date("2025-02-28"), date("2025-03-31")]
+    );
+    assert_eq!(
+        first_occurrences("FREQ=DAILY;BYDAY=SA,SU", "2025-01-03", 3),
+        [date("2025-01-04"), date("2025-01-05"), date("2025-01-11")]
+    );
+    assert_eq!(
+        first_occurrences("FREQ=YEARLY;BYMONTH=11;BYDAY=4TH", "2025-01-01", 2),
+        [date("2025-11-27"), date("2026-11-26")]
+    );
+
+    // leap day birthdays only happen on leap years
+    let yearly = "FREQ=YEARLY".parse::<Recurrence>().unwrap();
+    let birthdays = yearly
+        .occurrences_between(date("2024-02-29"), date("2025-01-01"), date("2032-12-31"))
+        .collect::<Vec<_>>();
+    assert_eq!(birthdays, [date("2028-02-29"), date("2032-02-29")]);
+
+    // windows long after the start skip ahead to it, to the same occurrences
+    for rule in [
+        "FREQ=DAILY;INTERVAL=3",
+        "FREQ=WEEKLY;INTERVAL=3;BYDAY=MO,FR",
+        "FREQ=MONTHLY;INTERVAL=5;BYMONTHDAY=-1",
+        "FREQ=YEARLY;INTERVAL=2;BYMONTH=1,12;BYDAY=-1SU",
+    ] {
+        let rule = rule.parse::<Recurrence>().unwrap();
+        let (start, from, to) = (date("1950-03-15"), date("2025-01-01"), date("2026-12-31"));
+        let walked = rule
+            .occurrences(start)
+            .skip_while(|date| *date < from)
+            .take_while(|date| *date <= to)
+            .collect::<Vec<_>>();
+        assert!(!walked.is_empty());
+        assert_eq!(
+            rule.occurrences_between(start, from, to)
+                .collect::<Vec<_>>(),
+            walked,
+            "{rule}"
+        );
+    }
+
+    // daily rules get past the years without a leap day too
+    let leap_days = "FREQ=DAILY;BYMONTH=2;BYMONTHDAY=29"
+        .parse::<Recurrence>()
+        .unwrap();
+    assert_eq!(
+        leap_days
+            .occurrences(date("1896-03-01"))
+            .take(2)
+            .collect::<Vec<_>>(),
+        [date("1904-02-29"), date("1908-02-29")]
+    );
+
+    // a rule that never matches ends instead of looping forever
+    let never = "FREQ=MONTHLY;BYMONTH=2;BYMONTHDAY=30"
+        .parse::<Recurrence>()
+        .unwrap();
+    assert_eq!(never.occurrences(date("2025-01-01")).next(), None);
+}
+
+#[test]
+fn recurrence_parses_the_supported_subset() {
+    let rule = "RRULE:FREQ=weekly;BYDAY=mo,-1fr;INTERVAL=1"
+        .parse::<Recurrence>()
+        .map(|rule| rule.to_string());
+    assert_eq!(
+        rule,
+        Err("BYDAY ordinals need FREQ=MONTHLY or FREQ=YEARLY".to_string())
+    );
+
+    let rule = "RRULE:freq=monthly;byday=mo,-1fr;interval=1"
+        .parse::<Recurrence>()
+        .unwrap();
+    assert_eq!(rule.to_string(), "FREQ=MONTHLY;BYDAY=MO,-1FR");
+
+    for invalid in [
+        "BYDAY=MO",
+        "FREQ=HOURLY",
+        "FREQ=DAILY;COUNT=2;UNTIL=20250101",
+        "FREQ=DAILY;BYSETPOS=1",
+        "FREQ=WEEKLY;BYMONTHDAY=1",
+        "FREQ=YEARLY;BYDAY=MO",
+        "FREQ=MONTHLY;BYMONTHDAY=32",
+        "FREQ=MONTHLY;INTERVAL=0",
+    ] {
+        assert!(
+            invalid.parse::<Recurrence>().is_err(),
+            "{invalid} should be rejected"
+        );
+    }
+}
+
+/// A yearly birthday since 1990 tagged `friends` and a one-off deadline tagged `work`
+async fn import_recurring_dates(engine: &Engine) -> response::ImportReport {
+    import(
+        engine,
+        vec![
+            markdown_file(
+                FIRST_ID,
+                "tags: [friends]\ndates:\n  - label: \"birthday\"\n    date: 1990-05-17\n    rrule: \"RRULE:FREQ=yearly\"\n",
+                "Alex",
+            ),
+            markdown_file(
+                SECOND_ID,
+                "tags: [work]\ndates:\n  - label: \"deadline\"\n    date: 2025-09-01\n",
+                "ship it",
+            ),
+            markdown_file(
+                "00000000-0000-4000-8000-000000000003",
+                "dates:\n  - date: 2025-01-01\n    rrule: \"FREQ=SOMETIMES\"\n",
+                "bad rule",
+            ),
+        ],
+    )
+    .await
+}
+
+fn occurrence_dates(response: response::Message) -> Vec<(String, NaiveDate)> {
+    let response::Message::Occurrences(occurrences) = response else {
+        panic!("expected occurrences, got {response:?}");
+    };
+    occurrences
+        .into_iter()
+        .map(|occurrence| (occurrence.note.text, occurrence.date))
+        .collect()
+}
+
+async fn searches_recurring_dates(engine: Engine) {
+    let report = import_recurring_dates(&engine).await;
+    assert_eq!(report.created, 2);
+    assert_eq!(report.errors.len(), 1);
+    assert!(report.errors[0].contains("FREQ=SOMETIMES"));
+
+    // rules are stored in their canonical form
+    let files = export(&engine).await;
+    let birthday = files
+        .iter()
+        .find(|file| file.content.contains("Alex"))
+        .unwrap();
+    assert!(birthday.content.contains("    rrule: \"FREQ=YEARLY\"\n"));
+
+    let search = |from: &str, to: &str| {
+        format!(
+            r#"{{"type": "get_notes_filtered", "data": {{"dates": {{"from": "{from}", "to": "{to}"}}}}}}"#
+        )
+    };
+    let response = engine_request(&engine, &search("2026-05-01", "2026-05-31")).await;
+    assert_eq!(texts(response), ["Alex"]);
+    let response = engine_request(&engine, &search("2025-09-01", "2025-09-01")).await;
+    assert_eq!(texts(response), ["ship it"]);
+    let response = engine_request(&engine, &search("1985-01-01", "1990-05-16")).await;
+    assert!(texts(response).is_empty());
+    let response = engine_request(&engine, &search("1000-01-01", "9999-12-31")).await;
+    assert!(matches!(response, response::Message::Unknown(e) if e.contains("too wide")));
+
+    let occurrences = |tags: &str| {
+        format!(
+            r#"{{"type": "get_occurrences", "data": {{"dates": {{"from": "2025-01-01", "to": "2026-12-31"}}, "tags": {tags}}}}}"#
+        )
+    };
+    let response = engine_request(&engine, &occurrences("null")).await;
+    assert_eq!(
+        occurrence_dates(response),
+        [
+            ("Alex".to_string(), date("2025-05-17")),
+            ("ship it".to_string(), date("2025-09-01")),
+            ("Alex".to_string(), date("2026-05-17")),
+        ]
+    );
+    let response = engine_request(&engine, &occurrences(r#"["work"]"#)).await;
+    assert_eq!(
+        occurrence_dates(response),
+        [("ship it".to_string(), date("2025-09-01"))]
+    );
+
+    let response = engine_request(
+        &engine,
+        r#"{"type": "get_occurrences", "data": {"dates": {"from": "2000-01-01", "to": "2025-01-01"}}}"#,
+    )
+    .await;
+    assert!(matches!(response, response::Message::Unknown(e) if e.contains("too wide")));
+
+    // the explicit rule goes to calendars, not a guess from the label
+    let calendar = ical::export(engine.store(), None).await.unwrap();
+    assert!(calendar.contains("RRULE:FREQ=YEARLY\r\n"));
+    assert_eq!(calendar.matches("RRULE:").count(), 1);
+}
+
+#[test]
+fn extracted_dates_follow_the_timezone() {
+    // already tuesday morning in Auckland
+    let now = at("2026-10-19", "23:30");
+
+    let dates = extracted_dates("tomorrow", None, now).unwrap();
+    assert_eq!(dates[0].date, date("2026-10-20"));
+    let dates = extracted_dates("tomorrow", Some("Pacific/Auckland"), now).unwrap();
+    assert_eq!(dates[0].date, date("2026-10-21"));
+
+    assert_eq!(
+        extracted_dates("tomorrow", Some("Mars/Olympus_Mons"), now).map(|_| ()),
+        Err("unknown timezone Mars/Olympus_Mons".to_string())
+    );
+}
+
+async fn creates_notes_with_detected_dates(engine: Engine) {
+    let create = |text: &str, extract_dates: bool| {
+        format!(
+            r#"{{"type": "create_note", "data": {{"text": "{text}", "tags": [], "extract_dates": {extract_dates}, "timezone": "Europe/Vienna"}}}}"#
+        )
+    };
+    let response = engine_request(&engine, &create("ship it @due:2026-11-01", true)).await;
+    assert_eq!(created(response).text, "ship it @due:2026-11-01");
+    let response = engine_request(&engine, &create("not on 2026-11-02", false)).await;
+    assert_eq!(created(response).text, "not on 2026-11-02");
+
+    let response = engine_request(
+        &engine,
+        r#"{"type": "get_occurrences", "data": {"dates": {"from": "2026-11-01", "to": "2026-11-30"}}}"#,
+    )
+    .await;
+    let response::Message::Occurrences(occurrences) = response else {
+        panic!("expected occurrences, got {response:?}");
+    };
+    assert_eq!(occurrences.len(), 1);
+    assert_eq!(occurrences[0].note.text, "ship it @due:2026-11-01");
+    assert_eq!(occurrences[0].label.as_deref(), Some("due"));
+    assert_eq!(occurrences[0].date, date("2026-11-01"));
+
+    let response = engine_request(
+        &engine,
+        r#"{"type": "create_note", "data": {"text": "tomorrow", "tags": [], "extract_dates": true, "timezone": "Nowhere"}}"#,
+    )
+    .await;
+    assert!(matches!(response, response::Message::Unknown(e) if e.contains("unknown timezone")));
+}
+
+async fn filters_notes_by_creation_day(engine: Engine) {
+    // an hour before midnight in UTC, already the next day in Vienna
+    let file = |id: &str, created_at: &str, text: &str| response::MarkdownFile {
+        name: format!("{id}.md"),
+        content: format!("---\nid: {id}\ncreated_at: {created_at}\ntags: []\n---\n\n{text}"),
+    };
+    let report = import(
+        &engine,
+        vec![
+            file(FIRST_ID, "2026-03-01T23:30:00Z", "late"),
+            file(SECOND_ID, "2026-03-02T08:00:00+01:00", "early"),
+        ],
+    )
+    .await;
+    assert_eq!(report.created, 2);
+
+    let search = |day: &str, timezone: &str| {
+        format!(
+            r#"{{"type": "get_notes_filtered", "data": {{"created": {{"from": "{day}", "to": "{day}"}}, "timezone": {timezone}}}}}"#
+        )
+    };
+    let response = engine_request(&engine, &search("2026-03-01", "null")).await;
+    assert_eq!(texts(response), ["late"]);
+    let response = engine_request(&engine, &search("2026-03-02", "null")).await;
+    assert_eq!(texts(response), ["early"]);
+    let response = engine_request(&engine, &search("2026-03-01", r#""Europe/Vienna""#)).await;
+    assert!(texts(response).is_empty());
+    let response = engine_request(&engine, &search("2026-03-02", r#""Europe/Vienna""#)).await;
+    assert_eq!(texts(response), ["late", "early"]);
+
+    let response = engine_request(&engine, &search("2026-03-02", r#""Vienna""#)).await;
+    assert!(matches!(response, response::Message::Unknown(e) if e.contains("unknown timezone")));
+}
+
+#[test]
+fn derives_titles() {
+    assert_eq!(
+        derive_title("\n\n## Garden plans\n\ntomatoes"),
+        "Garden plans"
+    );
+    assert_eq!(derive_title("Call Alex. About the trip"), "Call Alex.");
+    assert_eq!(
+        derive_title("costs 3.5 euros, see rember.rs"),
+        "costs 3.5 euros, see rember.rs"
+    );
+    assert_eq!(derive_title(""), "");
+
+    let long = derive_title(&"word ".repeat(40));
+    assert_eq!(long.chars().count(), 80);
+    assert!(long.ends_with("word…"));
+
+    let mut note: response::Note = deserialize(
+        r#"{"id": "00000000-0000-4000-8000-000000000001", "text": "first line\nsecond", "created_at": "2025-01-02T03:04:05Z"}"#,
+    )
+    .unwrap();
+    assert_eq!(note.display_title(), "first line");
+    note.title = Some("  ".to_string());
+    assert_eq!(note.display_title(), "first line");
+    note.title = Some("Given".to_string());
+    assert_eq!(note.display_title(), "Given");
+}
+
+async fn searches_titles(engine: Engine) {
+    let response = engine_request(
+        &engine,
+        r#"{"type": "create_note", "data": {"text": "tomatoes, beans and zucchini along the fence", "title": "Garden plans", "tags": []}}"#,
+    )
+    .await;
+    let note = created(response);
+    assert_eq!(note.title.as_deref(), Some("Garden plans"));
+    create_note(&engine, "some garden thoughts, plans for later", &[]).await;
+    create_note(&engine, "unrelated\n\nthe garden plans are elsewhere", &[]).await;
+
+    let search = r#"{"type": "get_notes_filtered", "data": {"search_text": "garden plans"}}"#;
+    let response = engine_request(&engine, search).await;
+    // the given title outranks a matching first line, which outranks a matching body
+    assert_eq!(
+        texts(response),
+        [
+            "tomatoes, beans and zucchini along the fence",
+            "some garden thoughts, plans for later",
+            "unrelated\n\nthe garden plans are elsewhere",
+        ]
+    );
+
+    // titles survive an export and import
+    let files = export(&engine).await;
+    let garden = files
+        .iter()
+        .find(|file| file.content.contains("zucchini"))
+        .unwrap();
+    assert!(garden.content.contains("title: \"Garden plans\"\n"));
+    let restored = Engine::new(MemoryStore::new());
+    import(&restored, files).await;
+    let response = engine_request(&restored, search).await;
+    assert_eq!(
+        texts(response)[0],
+        "tomatoes, beans and zucchini along the fence"
+    );
+
+    // imported titles are trimmed, and blank ones are no titles
+    let files = vec![
+        markdown_file(FIRST_ID, "title: \" Seeds \"\n", "order seeds"),
+        markdown_file(SECOND_ID, "title: \"  \"\n", "water the beans"),
+    ];
+    import(&restored, files).await;
+    let response = engine_request(&restored, r#"{"type": "get_notes", "data": {}}"#).await;
+    let response::Message::Notes(notes) = response else {
+        panic!("expected notes, got {response:?}");
+    };
+    let title = |text: &str| {
+        notes
+            .iter()
+            .find(|note| note.text == text)
+            .and_then(|note| note.title.clone())
+    };
+    assert_eq!(title("order seeds").as_deref(), Some("Seeds"));
+    assert_eq!(title("water the beans"), None);
+}
+
+async fn gets_note_details(engine: Engine) {
+    let response = engine_request(
+        &engine,
+        r#"{"type": "create_note", "data": {"text": "ship the schema @due:2026-11-01", "tags": ["work", "idea"], "extract_dates": true}}"#,
+    )
+    .await;
+    let id = created(response).id;
+
+    let response = engine_request(&engine, r#"{"type": "get_notes", "data": {}}"#).await;
+    let response::Message::Notes(notes) = response else {
+        panic!("expected notes, got {response:?}");
+    };
+    let linked = notes
+        .iter()
+        .find(|note| note.text == "database design notes")
+        .unwrap();
+    engine.store().link_notes(linked.id, id).await.unwrap();
+
+    let response = engine_request(
+        &engine,
+        &format!(r#"{{"type": "get_note_details", "data": {{"id": "{id}"}}}}"#),
+    )
+    .await;
+    let response::Message::NoteDetails(details) = response else {
+        panic!("expected note details, got {response:?}");
+    };
+    assert_eq!(details.note.text, "ship the schema @due:2026-11-01");
+    let tags = details.tags.iter().map(|tag| tag.name.as_str());
+    assert_eq!(tags.collect::<Vec<_>>(), ["idea", "work"]);
+    assert_eq!(details.note_dates.len(), 1);
+    assert_eq!(details.note_dates[0].label.as_deref(), Some("due"));
+    assert_eq!(details.note_dates[0].date, date("2026-11-01"));
+    let links = details.note_links.iter().map(|note| note.text.as_str());
+    assert_eq!(links.collect::<Vec<_>>(), ["database design notes"]);
+
+    // the link shows up from the other side too
+    let response = engine_request(
+        &engine,
+        &format!(
+            r#"{{"type": "get_note_details", "data": {{"id": "{}"}}}}"#,
+            linked.id
+        ),
+    )
+    .await;
+    let response::Message::NoteDetails(details) = response else {
+        panic!("expected note details, got {response:?}");
+    };
+    let links = details.note_links.iter().map(|note| note.text.as_str());
+    assert_eq!(
+        links.collect::<Vec<_>>(),
+        ["ship the schema @due:2026-11-01"]
+    );
+
+    let missing = uuid::Uuid::new_v4();
+    let response = engine_request(
+        &engine,
+        &format!(r#"{{"type": "get_note_details", "data": {{"id": "{missing}"}}}}"#),
+    )
+    .await;
+    assert!(matches!(response, response::Message::Unknown(e) if e.contains("note not found")));
+}
+
+async fn updates_and_deletes_notes(engine: Engine) {
+    let response = engine_request(
+        &engine,
+        r#"{"type": "create_note", "data": {"text": "draft @due:2026-11-01", "title": "Draft", "tags": ["idea"], "extract_dates": true}}"#,
+    )
+    .await;
+    let id = created(response).id;
+    let details =
+        |id: uuid::Uuid| format!(r#"{{"type": "get_note_details", "data": {{"id": "{id}"}}}}"#);
+    let update = |id: uuid::Uuid, text: &str, tags: &str| {
+        format!(
+            r#"{{"type": "update_note", "data": {{"id": "{id}", "text": "{text}", "tags": {tags}}}}}"#
+        )
+    };
+
+    let response = engine_request(&engine, &update(id, "final version", r#"["work"]"#)).await;
+    let response::Message::NoteUpdated(updated) = response else {
+        panic!("expected the updated note, got {response:?}");
+    };
+    assert_eq!(updated.text, "final version");
+
+    // the title and dates stay, the tags are replaced
+    let response = engine_request(&engine, &details(id)).await;
+    let response::Message::NoteDetails(edited) = response else {
+        panic!("expected note details, got {response:?}");
+    };
+    assert_eq!(edited.note.title.as_deref(), Some("Draft"));
+    let tags = edited.tags.iter().map(|tag| tag.name.as_str());
+    assert_eq!(tags.collect::<Vec<_>>(), ["work"]);
+    assert_eq!(edited.note_dates.len(), 1);
+
+    // the new text is what search finds
+    let search = r#"{"type": "get_notes_filtered", "data": {"search_text": "final version"}}"#;
+    assert_eq!(
+        texts(engine_request(&engine, search).await),
+        ["final version"]
+    );
+
+    // an unknown tag leaves the note as it was
+    let response = engine_request(&engine, &update(id, "lost edit", r#"["nope"]"#)).await;
+    assert!(matches!(response, response::Message::Unknown(e) if e.contains("tag not found")));
+    let response = engine_request(&engine, &details(id)).await;
+    let response::Message::NoteDetails(unchanged) = response else {
+        panic!("expected note details, got {response:?}");
+    };
+    assert_eq!(unchanged.note.text, "final version");
+    assert_eq!(unchanged.tags.len(), 1);
+
+    let response = engine_request(&engine, r#"{"type": "get_notes", "data": {}}"#).await;
+    let response::Message::Notes(notes) = response else {
+        panic!("expected notes, got {response:?}");
+    };
+    let other = notes
+        .iter()
+        .find(|note| note.text == "buy groceries")
+        .unwrap();
+    engine.store().link_notes(other.id, id).await.unwrap();
+
+    let delete = |id: uuid::Uuid| format!(r#"{{"type": "delete_note", "data": {{"id": "{id}"}}}}"#);
+    let response = engine_request(&engine, &delete(id)).await;
+    assert!(matches!(response, response::Message::NoteDeleted(deleted) if deleted.id == id));
+
+    // its dates, tags and links are gone with it
+    assert!(texts(engine_request(&engine, search).await).is_empty());
+    let response = engine_request(&engine, &details(other.id)).await;
+    let response::Message::NoteDetails(other) = response else {
+        panic!("expected note details, got {response:?}");
+    };
+    assert!(other.note_links.is_empty());
+    let snapshot = engine.store().snapshot().await.unwrap();
+    assert!(
+        snapshot
+            .note_dates
+            .iter()
+            .all(|note_date| note_date.note_id != id)
+    );
+    assert!(
+        snapshot
+            .note_tags
+            .iter()
+            .all(|note_tag| note_tag.note_id != id)
+    );
+
+    for response in [
+        engine_request(&engine, &delete(id)).await,
+        engine_request(&engine, &update(id, "too late", "[]")).await,
+    ] {
+        assert!(matches!(response, response::Message::Unknown(e) if e.contains("note not found")));
+    }
+}
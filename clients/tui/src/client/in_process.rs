@@ -0,0 +1,94 @@
+use async_trait::async_trait;
+use color_eyre::Result;
+use futures::StreamExt;
+use rember_backend::engine::{
+    core::{Engine, Limits},
+    reminders::{self, ReminderConfig},
+};
+use tokio::{
+    sync::{broadcast, mpsc},
+    task::JoinHandle,
+};
+
+use rust_shared::{request, response};
+
+use crate::client::Transport;
+
+/// Runs the backend engine inside the TUI process, for offline use without a server
+pub struct InProcessClient {
+    app_to_engine_tx: mpsc::Sender<request::Message>,
+    engine_to_app_rx: mpsc::Receiver<response::Message>,
+    engine_thread: JoinHandle<()>,
+    reminder_thread: JoinHandle<()>,
+}
+
+impl InProcessClient {
+    /// Opens the store at `db_url` the same way the backend would
+    pub async fn connect(db_url: &str) -> Result<Self> {
+        let engine = Engine::with_database_url(db_url)
+            .await?
+            .with_limits(Limits::from_env())
+            .with_reminders(ReminderConfig::from_env());
+        Ok(Self::new(engine))
+    }
+
+    pub fn new(engine: Engine) -> Self {
+        let (app_to_engine_tx, mut app_to_engine_rx) = mpsc::channel(100);
+        let (engine_to_app_tx, engine_to_app_rx) = mpsc::channel(100);
+        let (pushes, mut pushed) = broadcast::channel(64);
+        let reminder_thread = tokio::spawn(reminders::run(engine.clone(), pushes));
+
+        // one request at a time, so responses arrive in order like over a websocket
+        let engine_thread = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    msg = app_to_engine_rx.recv() => {
+                        let Some(msg) = msg else {
+                            return;
+                        };
+                        let mut responses = engine.handle_message(msg);
+
+                        while let Some(response) = responses.next().await {
+                            if engine_to_app_tx.send(response).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Ok(push) = pushed.recv() => {
+                        if engine_to_app_tx.send(push).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+
+        Self {
+            app_to_engine_tx,
+            engine_to_app_rx,
+            engine_thread,
+            reminder_thread,
+        }
+    }
+}
+
+impl Drop for InProcessClient {
+    fn drop(&mut self) {
+        self.engine_thread.abort();
+        self.reminder_thread.abort();
+    }
+}
+
+#[async_trait]
+impl Transport for InProcessClient {
+    async fn send(&self, message: request::Message) -> Result<()> {
+        self.app_to_engine_tx
+            .send(message)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    async fn recv(&mut self) -> Option<response::Message> {
+        self.engine_to_app_rx.recv().await
+    }
+}
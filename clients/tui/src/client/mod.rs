@@ -1 +1,17 @@
+#[cfg(feature = "embedded")]
+pub mod in_process;
 pub mod websocket;
+
+use async_trait::async_trait;
+use color_eyre::Result;
+
+use rust_shared::{request, response};
+
+/// Carries requests to the backend engine and its responses back to the app
+#[async_trait]
+pub trait Transport: Send {
+    async fn send(&self, message: request::Message) -> Result<()>;
+
+    /// Waits for the next response, `None` once the backend is gone
+    async fn recv(&mut self) -> Option<response::Message>;
+}
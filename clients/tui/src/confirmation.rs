@@ -0,0 +1,41 @@
+use ratatui::{
+    prelude::{Buffer, Rect, Style, Widget},
+    style::Color,
+    widgets::{Block, Clear, Paragraph},
+};
+use uuid::Uuid;
+
+/// A question that takes every key until it's answered with `y`, or dismissed with `n` or esc
+pub enum Confirmation {
+    /// Close the note editor, losing its changes
+    DiscardEdit,
+    /// Quit with a draft or changes to a note unsaved
+    Quit,
+    DeleteNote {
+        id: Uuid,
+        title: String,
+    },
+}
+
+impl Confirmation {
+    fn question(&self) -> String {
+        match self {
+            Self::DiscardEdit => "Discard your changes?".to_string(),
+            Self::Quit => "Quit, losing what isn't saved?".to_string(),
+            Self::DeleteNote { title, .. } => format!("Delete \"{title}\"?"),
+        }
+    }
+}
+
+impl Widget for &Confirmation {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered()
+            .title("y: yes, n: no")
+            .border_style(Style::default().fg(Color::Red));
+
+        Clear.render(area, buf);
+        Paragraph::new(self.question())
+            .block(block)
+            .render(area, buf);
+    }
+}
@@ -1,16 +1,20 @@
 use color_eyre::Result;
 use crossterm::event::{Event, KeyEventKind};
 use futures::{FutureExt, StreamExt};
+use std::{fs, path::Path};
+use tokio::time::{Duration, Instant};
 
 use crate::App;
 
-use rust_shared::response;
+use rust_shared::{request, response};
 
 impl App {
     pub async fn handle_events(&mut self) -> Result<()> {
         tokio::select! {
             event = self.crossterm_event_stream.next().fuse() => self.handle_crossterm_events(event).await,
-            message = self.websocket_client.recv() => self.handle_websocket_message(message).await,
+            message = self.client.recv() => self.handle_backend_message(message).await,
+            _ = wait_until(self.pending_search) => self.send_search().await,
+            _ = wait_until(self.retries.iter().map(|(at, _)| *at).min()) => self.send_retries().await,
             _ = event_timeout(100).fuse() => {}
         }
         Ok(())
@@ -29,34 +33,156 @@ impl App {
         }
     }
 
-    async fn handle_websocket_message(&mut self, message: Option<response::Message>) {
+    async fn handle_backend_message(&mut self, message: Option<response::Message>) {
         let Some(message) = message else {
             return;
         };
+        // every request gets one response, in order, besides the reminders pushed as they're due
+        let answered = match message {
+            // an export's files come before the `export_finished` that answers it
+            response::Message::Reminder(_) | response::Message::MarkdownFile(_) => None,
+            _ => self.in_flight.pop_front(),
+        };
 
         match message {
+            response::Message::NoteUpdated(note) => {
+                if self
+                    .editor
+                    .as_ref()
+                    .is_some_and(|editor| editor.saving && editor.id == note.id)
+                {
+                    self.close_editor();
+                }
+                self.note_details.set_status("saved".to_string());
+                self.refresh_search().await;
+            }
+            response::Message::NoteCreated(note) => {
+                if self.submitting {
+                    self.submitting = false;
+                    self.new_note.clear();
+                }
+                self.note_details
+                    .set_status(format!("created \"{}\"", note.display_title()));
+                self.refresh_search().await;
+            }
             response::Message::Notes(notes) => {
-                self.search_results.set_notes(notes.clone());
-                let text = notes
-                    .iter()
-                    .map(|note| note.text.clone())
-                    .collect::<Vec<_>>()
-                    .join("\n");
-                self.response_box.text = text;
+                self.search_results.set_notes(notes);
+                self.request_details().await;
+            }
+            response::Message::NoteDeleted(deleted) => {
+                if self
+                    .editor
+                    .as_ref()
+                    .is_some_and(|editor| editor.id == deleted.id)
+                {
+                    self.close_editor();
+                }
+                self.note_details.set_status("deleted".to_string());
+                self.refresh_search().await;
             }
+            response::Message::NoteDetails(details) => {
+                let selected = self.search_results.selected_id();
+                self.note_details.set_details(details, selected);
+            }
+            // all of them at first, then each one created
             response::Message::Tags(tags) => {
-                let text = tags
+                for tag in tags {
+                    if !self.tags.contains(&tag.name) {
+                        self.tags.push(tag.name);
+                    }
+                }
+                let text = self
+                    .tags
                     .iter()
-                    .map(|tag| "#".to_string() + &tag.name.clone())
+                    .map(|name| format!("#{name}"))
                     .collect::<Vec<_>>()
-                    .join("\n");
-                self.response_box.text = text;
+                    .join(" ");
+                self.note_details.set_status(text);
+            }
+            response::Message::RateLimited(rate_limited) => {
+                let retry_at = Instant::now() + Duration::from_millis(rate_limited.retry_after_ms);
+                match answered {
+                    // searched again with whatever the search box says by then
+                    Some(request::Message::GetNotesFiltered(_)) => {
+                        self.pending_search = Some(retry_at);
+                    }
+                    Some(request) => self.retries.push((retry_at, request)),
+                    None => {}
+                }
+                self.note_details.set_status(format!(
+                    "rate limited, retrying in {}ms",
+                    rate_limited.retry_after_ms
+                ));
+            }
+            response::Message::MarkdownFile(file) => self.write_export_file(file),
+            response::Message::ExportFinished(finished) => {
+                let mut status = format!(
+                    "exported {} notes to {}",
+                    finished.count,
+                    self.export_dir.display()
+                );
+                if self.export_errors > 0 {
+                    status += &format!(", {} couldn't be written", self.export_errors);
+                }
+                self.note_details.set_status(status);
             }
+            response::Message::Imported(report) => {
+                self.note_details.set_status(format!(
+                    "imported {} new and {} updated notes, {} errors",
+                    report.created,
+                    report.updated,
+                    report.errors.len()
+                ));
+            }
+            response::Message::Reminder(reminder) => self.reminders.notify(reminder),
+            response::Message::Reminders(reminders) => self.reminders.set_reminders(reminders),
+            // the TUI never asks for imports, which the backend's subcommands handle, nor for
+            // occurrences, which are for calendar views
+            response::Message::ImportStarted(_)
+            | response::Message::ImportFileReceived(_)
+            | response::Message::Occurrences(_) => {}
             response::Message::Unknown(msg) => {
-                self.response_box.text = format!("{msg:?}");
+                if let Some(request) = &answered {
+                    self.forget_pending_save(request);
+                }
+                self.note_details.set_status(format!("{msg:?}"));
             }
         }
     }
+
+    /// Stops waiting on a new or edited note after the backend turned down the request saving
+    /// it, the draft stays to fix it
+    fn forget_pending_save(&mut self, request: &request::Message) {
+        match request {
+            request::Message::CreateNote(_) => self.submitting = false,
+            request::Message::UpdateNote(_) => {
+                if let Some(editor) = &mut self.editor {
+                    editor.saving = false;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Writes a file of the running export into the export directory, under its name without
+    /// any directories
+    fn write_export_file(&mut self, file: response::MarkdownFile) {
+        let written = Path::new(&file.name).file_name().is_some_and(|name| {
+            fs::create_dir_all(&self.export_dir)
+                .and_then(|()| fs::write(self.export_dir.join(name), &file.content))
+                .is_ok()
+        });
+        if !written {
+            self.export_errors += 1;
+        }
+    }
+}
+
+async fn wait_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
 }
 
 async fn event_timeout(timeout_ms: u64) {
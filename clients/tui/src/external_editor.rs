@@ -0,0 +1,179 @@
+use color_eyre::{Result, eyre::eyre};
+use crossterm::{
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use ratatui::DefaultTerminal;
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::process::Command;
+
+use crate::search::distinct_tags;
+
+/// What's opened in `$VISUAL` or `$EDITOR`, and where the result goes back to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExternalEdit {
+    /// The new note being written, back into its box
+    Draft,
+    /// The note editor's tags and text, back into the editor
+    Editor,
+    /// The note editor opened on the selected note for this, saved right away
+    SelectedNote,
+}
+
+/// A note as the file it's edited in, with its tags in a header above the text:
+///
+/// ```markdown
+/// ---
+/// tags: #idea #work
+/// ---
+/// the note text
+/// ```
+pub fn to_file(tags: &[String], text: &str) -> String {
+    let tags = tags
+        .iter()
+        .map(|tag| format!("#{tag}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    // editors end files with a line break, which isn't part of the note
+    format!("---\ntags: {tags}\n---\n{text}\n")
+}
+
+/// The tags and text back out of the file, with no tags if the header is gone
+pub fn from_file(content: &str) -> (Vec<String>, String) {
+    let content = content.replace("\r\n", "\n");
+    let content = content.strip_suffix('\n').unwrap_or(&content);
+
+    let Some((header, text)) = content.strip_prefix("---\n").and_then(|rest| {
+        rest.split_once("\n---\n")
+            .or(rest.strip_suffix("\n---").map(|header| (header, "")))
+    }) else {
+        return (Vec::new(), content.to_string());
+    };
+
+    let tags = header
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("tags:"))
+        .flat_map(str::split_whitespace)
+        .map(|word| word.trim_start_matches('#').to_string());
+    (distinct_tags(tags), text.to_string())
+}
+
+/// Opens `content` in the user's editor with the terminal handed over to it, returning what
+/// was saved, or `None` if nothing changed
+///
+/// The terminal is taken back whatever the editor did, crashing included.
+pub async fn edit(terminal: &mut DefaultTerminal, content: &str) -> Result<Option<String>> {
+    let path = temp_path();
+    fs::File::create_new(&path)?.write_all(content.as_bytes())?;
+
+    suspend()?;
+    let status = run_editor(&path).await;
+    let resumed = resume(terminal);
+
+    let edited = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+    resumed?;
+
+    let status = status?;
+    if !status.success() {
+        return Err(eyre!(
+            "the editor exited with {status}, nothing was changed"
+        ));
+    }
+
+    let edited = edited?;
+    Ok((edited != content).then_some(edited))
+}
+
+/// `$VISUAL`, `$EDITOR` or else `vi`, which may come with arguments, e.g. `code --wait`
+async fn run_editor(path: &Path) -> Result<std::process::ExitStatus> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+
+    Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .await
+        .map_err(|e| eyre!("couldn't run {program}: {e}"))
+}
+
+fn temp_path() -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    std::env::temp_dir().join(format!("rember-{}-{nanos}.md", std::process::id()))
+}
+
+/// Gives the terminal back as it was before the TUI started, for the editor to draw on
+fn suspend() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)
+}
+
+/// Takes the terminal over again like `ratatui::init` does, redrawing everything on the next
+/// frame since the editor drew over it
+fn resume(terminal: &mut DefaultTerminal) -> io::Result<()> {
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    enable_raw_mode()?;
+    terminal.clear()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_file, to_file};
+
+    #[test]
+    fn files_round_trip() {
+        let tags = vec!["idea".to_string(), "work".to_string()];
+        for text in [
+            "",
+            "one line",
+            "# heading\n\n- [ ] task\n",
+            "---\nnot a header\n---",
+        ] {
+            let file = to_file(&tags, text);
+            assert_eq!(from_file(&file), (tags.clone(), text.to_string()));
+            // as an editor on Windows may save it
+            assert_eq!(
+                from_file(&file.replace('\n', "\r\n")),
+                (tags.clone(), text.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn tags_are_read_with_or_without_their_hash() {
+        let file = "---\ntags: #idea work  ## #work\n---\ntext\n";
+        assert_eq!(
+            from_file(file),
+            (
+                vec!["idea".to_string(), "work".to_string()],
+                "text".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn a_removed_header_leaves_no_tags() {
+        assert_eq!(
+            from_file("just text\n"),
+            (Vec::new(), "just text".to_string())
+        );
+        assert_eq!(
+            from_file("---\ntags: #a\n---"),
+            (vec!["a".to_string()], String::new())
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use ratatui::{
+    prelude::{Buffer, Rect, Style, Widget},
+    style::{Color, Modifier},
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph},
+};
+
+use crate::{
+    focus::Focus,
+    keymap::{Context, Keymap},
+};
+
+/// The keys bound with the focus where it was when it opened, per context, until any key
+/// closes it
+pub struct Help {
+    sections: Vec<(Context, Vec<(String, &'static str)>)>,
+}
+
+impl Help {
+    pub fn new(keymap: &Keymap, focus: Focus) -> Self {
+        let mut sections: Vec<(Context, Vec<_>)> = Vec::new();
+        for (context, action) in keymap.actions(focus) {
+            let keys = keymap.keys(context, action);
+            // unbound actions are left to the command palette
+            if keys == "unbound" {
+                continue;
+            }
+
+            let binding = (keys, action.description());
+            match sections.last_mut() {
+                Some((last, bindings)) if *last == context => bindings.push(binding),
+                _ => sections.push((context, vec![binding])),
+            }
+        }
+        Self { sections }
+    }
+
+    fn lines(&self) -> Vec<Line<'_>> {
+        let width = self
+            .sections
+            .iter()
+            .flat_map(|(_, bindings)| bindings)
+            .map(|(keys, _)| keys.len())
+            .max()
+            .unwrap_or_default();
+        let heading = Style::default().add_modifier(Modifier::BOLD);
+
+        let mut lines = Vec::new();
+        for (context, bindings) in &self.sections {
+            if !lines.is_empty() {
+                lines.push(Line::default());
+            }
+            lines.push(Line::styled(context.name(), heading));
+            for (keys, description) in bindings {
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        format!("  {keys:width$}  "),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::raw(*description),
+                ]));
+            }
+        }
+        lines
+    }
+}
+
+impl Widget for &Help {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered()
+            .title("Keys (any key: close)")
+            .border_style(Style::default().fg(Color::Cyan));
+
+        Clear.render(area, buf);
+        Paragraph::new(self.lines()).block(block).render(area, buf);
+    }
+}
@@ -0,0 +1,116 @@
+use std::mem;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::text_box::Cursor;
+
+// undo steps kept per text box, the oldest go first
+const MAX_STEPS: usize = 200;
+
+/// What an edit did, back to back edits of the same kind undo as one step
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditKind {
+    Typing,
+    Backspace,
+    Delete,
+    /// Paste, cut, new lines and replacing a selection, each a step of its own
+    Other,
+}
+
+impl EditKind {
+    pub fn of(key: KeyEvent) -> Self {
+        match key.code {
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) && c != '\n' => {
+                Self::Typing
+            }
+            KeyCode::Backspace => Self::Backspace,
+            KeyCode::Delete => Self::Delete,
+            _ => Self::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    text: String,
+    cursor: Cursor,
+}
+
+/// Undo and redo for a text box, restoring the cursor and selection along with the text
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    undo: Vec<State>,
+    redo: Vec<State>,
+    /// The last edit's kind and where it left the cursor, while the next edit can still join it
+    run: Option<(EditKind, usize)>,
+    /// Every edit joins one step until the run breaks, for vim's insert mode, `Some(true)` once
+    /// that step is recorded
+    group: Option<bool>,
+}
+
+impl History {
+    /// Records an edit, from the text and cursor before it to `position` after it
+    pub fn record(&mut self, text: String, cursor: Cursor, kind: EditKind, position: usize) {
+        let joins_run = kind != EditKind::Other
+            && cursor.selection_anchor.is_none()
+            && self.run == Some((kind, cursor.position));
+        let joins_group = self.group == Some(true);
+        if self.group.is_some() {
+            self.group = Some(true);
+        }
+        if !joins_run && !joins_group {
+            self.undo.push(State { text, cursor });
+            if self.undo.len() > MAX_STEPS {
+                self.undo.remove(0);
+            }
+        }
+
+        self.redo.clear();
+        self.run = Some((kind, position));
+    }
+
+    /// The cursor moved or something else happened, so the next edit is a step of its own
+    pub fn break_run(&mut self) {
+        self.run = None;
+        self.group = None;
+    }
+
+    /// Makes the next edits one step, up to the next time the run breaks
+    pub fn start_group(&mut self) {
+        self.group = Some(false);
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Returns `false` if there's nothing to undo
+    pub fn undo(&mut self, text: &mut String, cursor: &mut Cursor) -> bool {
+        let Some(state) = self.undo.pop() else {
+            return false;
+        };
+
+        self.redo.push(swap(state, text, cursor));
+        self.break_run();
+        true
+    }
+
+    /// Returns `false` if there's nothing to redo
+    pub fn redo(&mut self, text: &mut String, cursor: &mut Cursor) -> bool {
+        let Some(state) = self.redo.pop() else {
+            return false;
+        };
+
+        self.undo.push(swap(state, text, cursor));
+        self.break_run();
+        true
+    }
+}
+
+/// Puts `state` in place, returning what was there
+fn swap(state: State, text: &mut String, cursor: &mut Cursor) -> State {
+    State {
+        text: mem::replace(text, state.text),
+        cursor: mem::replace(cursor, state.cursor),
+    }
+}
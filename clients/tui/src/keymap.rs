@@ -0,0 +1,708 @@
+use color_eyre::{Result, eyre::eyre};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::focus::Focus;
+
+// every action and context with its default keys, which a keymap file replaces per action
+const DEFAULT_KEYMAP: &str = include_str!("../keymap.toml");
+
+/// Something a key can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    Quit,
+    NewNote,
+    Reminders,
+    EditNote,
+    DeleteNote,
+    OpenInEditor,
+    ToggleMarkdown,
+    SelectPrevious,
+    SelectNext,
+    ScrollUp,
+    ScrollDown,
+    Save,
+    /// Between the tags and the text in the note editor
+    ToggleField,
+    Close,
+    Acknowledge,
+    SnoozeHour,
+    SnoozeDay,
+    SelectAll,
+    Copy,
+    Cut,
+    Paste,
+    Undo,
+    Redo,
+    /// Every note as a Markdown file in the export directory
+    Export,
+    /// The key bindings with the focus where it is
+    Help,
+    /// Every action there, searchable by name
+    CommandPalette,
+}
+
+impl Action {
+    pub const ALL: [Self; 26] = [
+        Self::Quit,
+        Self::NewNote,
+        Self::Reminders,
+        Self::EditNote,
+        Self::DeleteNote,
+        Self::OpenInEditor,
+        Self::ToggleMarkdown,
+        Self::SelectPrevious,
+        Self::SelectNext,
+        Self::ScrollUp,
+        Self::ScrollDown,
+        Self::Save,
+        Self::ToggleField,
+        Self::Close,
+        Self::Acknowledge,
+        Self::SnoozeHour,
+        Self::SnoozeDay,
+        Self::SelectAll,
+        Self::Copy,
+        Self::Cut,
+        Self::Paste,
+        Self::Undo,
+        Self::Redo,
+        Self::Export,
+        Self::Help,
+        Self::CommandPalette,
+    ];
+
+    /// As written in a keymap file
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Quit => "quit",
+            Self::NewNote => "new_note",
+            Self::Reminders => "reminders",
+            Self::EditNote => "edit_note",
+            Self::DeleteNote => "delete_note",
+            Self::OpenInEditor => "open_in_editor",
+            Self::ToggleMarkdown => "toggle_markdown",
+            Self::SelectPrevious => "select_previous",
+            Self::SelectNext => "select_next",
+            Self::ScrollUp => "scroll_up",
+            Self::ScrollDown => "scroll_down",
+            Self::Save => "save",
+            Self::ToggleField => "toggle_field",
+            Self::Close => "close",
+            Self::Acknowledge => "acknowledge",
+            Self::SnoozeHour => "snooze_hour",
+            Self::SnoozeDay => "snooze_day",
+            Self::SelectAll => "select_all",
+            Self::Copy => "copy",
+            Self::Cut => "cut",
+            Self::Paste => "paste",
+            Self::Undo => "undo",
+            Self::Redo => "redo",
+            Self::Export => "export",
+            Self::Help => "help",
+            Self::CommandPalette => "command_palette",
+        }
+    }
+
+    /// What it does, for the help and the command palette
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Quit => "Quit",
+            Self::NewNote => "New note",
+            Self::Reminders => "Show due reminders",
+            Self::EditNote => "Edit the selected note",
+            Self::DeleteNote => "Delete the note",
+            Self::OpenInEditor => "Open the note in $EDITOR",
+            Self::ToggleMarkdown => "Toggle rendered Markdown",
+            Self::SelectPrevious => "Select the previous one",
+            Self::SelectNext => "Select the next one",
+            Self::ScrollUp => "Scroll the note up",
+            Self::ScrollDown => "Scroll the note down",
+            Self::Save => "Save the note",
+            Self::ToggleField => "Switch between tags and text",
+            Self::Close => "Close",
+            Self::Acknowledge => "Acknowledge the reminder",
+            Self::SnoozeHour => "Snooze the reminder for an hour",
+            Self::SnoozeDay => "Snooze the reminder for a day",
+            Self::SelectAll => "Select all the text",
+            Self::Copy => "Copy",
+            Self::Cut => "Cut",
+            Self::Paste => "Paste",
+            Self::Undo => "Undo",
+            Self::Redo => "Redo",
+            Self::Export => "Export every note as Markdown",
+            Self::Help => "Show the key bindings",
+            Self::CommandPalette => "Command palette",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Where a binding applies, a section of the keymap file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Context {
+    Global,
+    Search,
+    NewNote,
+    EditNote,
+    Reminders,
+    /// Every box text is typed in, along with its focus' own context
+    TextBox,
+}
+
+impl Context {
+    pub const ALL: [Self; 6] = [
+        Self::Global,
+        Self::Search,
+        Self::NewNote,
+        Self::EditNote,
+        Self::Reminders,
+        Self::TextBox,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Global => "global",
+            Self::Search => "search",
+            Self::NewNote => "new_note",
+            Self::EditNote => "edit_note",
+            Self::Reminders => "reminders",
+            Self::TextBox => "text_box",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|context| context.name() == name)
+    }
+
+    /// The contexts whose bindings apply with the focus on `focus`, its own first
+    pub fn active(focus: Focus) -> &'static [Self] {
+        match focus {
+            Focus::Search => &[Self::Search, Self::TextBox, Self::Global],
+            Focus::NewNote => &[Self::NewNote, Self::TextBox, Self::Global],
+            Focus::EditNote => &[Self::EditNote, Self::TextBox, Self::Global],
+            Focus::Reminders => &[Self::Reminders, Self::Global],
+        }
+    }
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A key with its modifiers, the same however the terminal reports shift
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let (code, modifiers) = match code {
+            // shift shows in the letter, or as a modifier, or both
+            KeyCode::Char(c) if c.is_uppercase() => (
+                KeyCode::Char(c.to_lowercase().next().unwrap_or(c)),
+                modifiers | KeyModifiers::SHIFT,
+            ),
+            // `?` is typed with shift on some keyboards and not on others
+            KeyCode::Char(c) if !c.is_alphabetic() => (code, modifiers - KeyModifiers::SHIFT),
+            KeyCode::BackTab => (code, modifiers - KeyModifiers::SHIFT),
+            code => (code, modifiers),
+        };
+        Self { code, modifiers }
+    }
+
+    /// A character a text box would take, without ctrl or alt
+    fn is_typed(self) -> bool {
+        matches!(self.code, KeyCode::Char(_))
+            && !self
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+
+    /// `ctrl+shift+z`, `esc`, `?`
+    fn parse(text: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text;
+        // split off modifiers but not a `+` key, as in `ctrl++`
+        while let Some((modifier, key)) = rest.split_once('+')
+            && !key.is_empty()
+        {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(eyre!("unknown modifier {modifier:?} in {text:?}")),
+            };
+            rest = key;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_lowercase().as_str() {
+                "esc" | "escape" => KeyCode::Esc,
+                "enter" | "return" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "delete" | "del" => KeyCode::Delete,
+                "backspace" => KeyCode::Backspace,
+                name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(eyre!("unknown key {text:?}")),
+                },
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl From<KeyEvent> for KeyBinding {
+    fn from(key: KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl+"),
+            (KeyModifiers::ALT, "alt+"),
+            (KeyModifiers::SHIFT, "shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::Esc => f.write_str("esc"),
+            KeyCode::Enter => f.write_str("enter"),
+            KeyCode::Tab => f.write_str("tab"),
+            KeyCode::BackTab => f.write_str("backtab"),
+            KeyCode::Up => f.write_str("up"),
+            KeyCode::Down => f.write_str("down"),
+            KeyCode::Left => f.write_str("left"),
+            KeyCode::Right => f.write_str("right"),
+            KeyCode::Home => f.write_str("home"),
+            KeyCode::End => f.write_str("end"),
+            KeyCode::PageUp => f.write_str("pgup"),
+            KeyCode::PageDown => f.write_str("pgdn"),
+            KeyCode::Delete => f.write_str("delete"),
+            KeyCode::Backspace => f.write_str("backspace"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// Which action each key runs, per context
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: BTreeMap<Context, BTreeMap<Action, Vec<KeyBinding>>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self {
+            bindings: BTreeMap::new(),
+        };
+        keymap
+            .merge(DEFAULT_KEYMAP, true)
+            .expect("the default keymap is valid");
+        keymap
+    }
+}
+
+impl Keymap {
+    /// The defaults, with the bindings from `path`, or from the config directory's
+    /// `rember/keymap.toml` if there is one
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut keymap = Self::default();
+
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(keymap),
+            },
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if !required && e.kind() == io::ErrorKind::NotFound => return Ok(keymap),
+            Err(e) => return Err(eyre!("can't read {}: {e}", path.display())),
+        };
+
+        keymap
+            .merge(&content, false)
+            .map_err(|e| eyre!("{}: {e}", path.display()))?;
+        Ok(keymap)
+    }
+
+    /// Replaces the bindings of the actions in `content`, which only the defaults may add to a
+    /// context, and checks the result for keys bound twice
+    fn merge(&mut self, content: &str, defaults: bool) -> Result<()> {
+        let table = content
+            .parse::<toml::Table>()
+            .map_err(|e| eyre!("{}", e.message()))?;
+
+        for (context_name, actions) in table {
+            let context = Context::from_name(&context_name)
+                .ok_or_else(|| eyre!("unknown section [{context_name}]"))?;
+            let toml::Value::Table(actions) = actions else {
+                return Err(eyre!("[{context}] should be a section"));
+            };
+
+            for (action_name, keys) in actions {
+                let action = Action::from_name(&action_name)
+                    .ok_or_else(|| eyre!("unknown action {action_name:?} in [{context}]"))?;
+                let keys = match keys {
+                    toml::Value::String(key) => vec![key],
+                    toml::Value::Array(keys) => keys
+                        .into_iter()
+                        .map(|key| match key {
+                            toml::Value::String(key) => Ok(key),
+                            key => Err(eyre!(
+                                "a {} for {action} in [{context}] isn't a key",
+                                key.type_str()
+                            )),
+                        })
+                        .collect::<Result<_>>()?,
+                    keys => {
+                        return Err(eyre!(
+                            "a {} for {action} in [{context}] isn't a key",
+                            keys.type_str()
+                        ));
+                    }
+                };
+                let keys = keys
+                    .iter()
+                    .map(|key| KeyBinding::parse(key))
+                    .collect::<Result<Vec<_>>>()?;
+
+                let actions = self.bindings.entry(context).or_default();
+                if !defaults && !actions.contains_key(&action) {
+                    return Err(eyre!("{action} can't be bound in [{context}]"));
+                }
+                actions.insert(action, keys);
+            }
+        }
+
+        self.check_conflicts()
+    }
+
+    /// Every key runs one action at most wherever the focus is
+    fn check_conflicts(&self) -> Result<()> {
+        for focus in [
+            Focus::Search,
+            Focus::NewNote,
+            Focus::EditNote,
+            Focus::Reminders,
+        ] {
+            let mut seen = Vec::<(KeyBinding, Context, Action)>::new();
+            for &context in Context::active(focus) {
+                for (action, key) in self.context_bindings(context) {
+                    if let Some((_, other_context, other_action)) =
+                        seen.iter().find(|(seen_key, ..)| *seen_key == key)
+                    {
+                        return Err(eyre!(
+                            "{key} is bound to {other_action} in [{other_context}] and to {action} in [{context}]"
+                        ));
+                    }
+                    seen.push((key, context, action));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn context_bindings(&self, context: Context) -> impl Iterator<Item = (Action, KeyBinding)> {
+        self.bindings
+            .get(&context)
+            .into_iter()
+            .flatten()
+            .flat_map(|(&action, keys)| keys.iter().map(move |&key| (action, key)))
+    }
+
+    /// The action `key` runs with the focus on `focus`, if any. While `typing` into a text box,
+    /// characters without ctrl or alt are typed whatever they're bound to
+    pub fn action(&self, focus: Focus, key: KeyEvent, typing: bool) -> Option<Action> {
+        let key = KeyBinding::from(key);
+        if typing && key.is_typed() {
+            return None;
+        }
+        Context::active(focus).iter().find_map(|&context| {
+            self.context_bindings(context)
+                .find(|&(_, bound)| bound == key)
+                .map(|(action, _)| action)
+        })
+    }
+
+    /// `ctrl+s`, or `ctrl+y/ctrl+shift+z` for several keys, to show along with what they do
+    pub fn keys(&self, context: Context, action: Action) -> String {
+        let keys = self
+            .bindings
+            .get(&context)
+            .and_then(|actions| actions.get(&action))
+            .map(|keys| keys.iter().map(KeyBinding::to_string).collect::<Vec<_>>())
+            .unwrap_or_default();
+        if keys.is_empty() {
+            "unbound".to_string()
+        } else {
+            keys.join("/")
+        }
+    }
+
+    /// The actions of each context that applies with the focus on `focus`, bound or not, in
+    /// the order of [`Context::active`]
+    pub fn actions(&self, focus: Focus) -> Vec<(Context, Action)> {
+        Context::active(focus)
+            .iter()
+            .flat_map(|&context| {
+                self.bindings
+                    .get(&context)
+                    .into_iter()
+                    .flat_map(move |actions| actions.keys().map(move |&action| (context, action)))
+            })
+            .collect()
+    }
+
+    /// `ctrl+s: save, esc: close` for a box's title
+    pub fn hints(&self, context: Context, hints: &[(Action, &str)]) -> String {
+        hints
+            .iter()
+            .map(|&(action, what)| format!("{}: {what}", self.keys(context, action)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// `$XDG_CONFIG_HOME/rember/keymap.toml`, or `~/.config/rember/keymap.toml`
+fn default_path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config.join("rember").join("keymap.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::{Action, Keymap};
+    use crate::focus::Focus;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn defaults_match_the_built_in_keys() {
+        let keymap = Keymap::default();
+        let ctrl = KeyModifiers::CONTROL;
+
+        for focus in [
+            Focus::Search,
+            Focus::NewNote,
+            Focus::EditNote,
+            Focus::Reminders,
+        ] {
+            assert_eq!(
+                keymap.action(focus, key(KeyCode::Char('q'), ctrl), false),
+                Some(Action::Quit)
+            );
+        }
+        assert_eq!(
+            keymap.action(Focus::Search, key(KeyCode::Char('n'), ctrl), false),
+            Some(Action::NewNote)
+        );
+        assert_eq!(
+            keymap.action(Focus::NewNote, key(KeyCode::Esc, KeyModifiers::NONE), false),
+            Some(Action::Close)
+        );
+        assert_eq!(
+            keymap.action(Focus::EditNote, key(KeyCode::Char('z'), ctrl), false),
+            Some(Action::Undo)
+        );
+        assert_eq!(
+            keymap.action(Focus::Reminders, key(KeyCode::Char('z'), ctrl), false),
+            None
+        );
+        // plain letters type in the search box, they only act in the reminders
+        assert_eq!(
+            keymap.action(
+                Focus::Search,
+                key(KeyCode::Char('a'), KeyModifiers::NONE),
+                false
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn characters_are_typed_while_typing() {
+        let keymap = Keymap::default();
+        let question_mark = key(KeyCode::Char('?'), KeyModifiers::SHIFT);
+
+        for focus in [
+            Focus::Search,
+            Focus::NewNote,
+            Focus::EditNote,
+            Focus::Reminders,
+        ] {
+            assert_eq!(
+                keymap.action(focus, question_mark, false),
+                Some(Action::Help)
+            );
+        }
+        assert_eq!(keymap.action(Focus::NewNote, question_mark, true), None);
+        // keys that don't type anything still act
+        assert_eq!(
+            keymap.action(Focus::NewNote, key(KeyCode::F(1), KeyModifiers::NONE), true),
+            Some(Action::Help)
+        );
+        assert_eq!(
+            keymap.action(Focus::NewNote, key(KeyCode::Esc, KeyModifiers::NONE), true),
+            Some(Action::Close)
+        );
+        assert_eq!(
+            keymap.action(
+                Focus::NewNote,
+                key(KeyCode::Char('s'), KeyModifiers::CONTROL),
+                true
+            ),
+            Some(Action::Save)
+        );
+    }
+
+    #[test]
+    fn shift_matches_however_the_terminal_reports_it() {
+        let keymap = Keymap::default();
+        let redo = Some(Action::Redo);
+        let ctrl_shift = KeyModifiers::CONTROL | KeyModifiers::SHIFT;
+
+        assert_eq!(
+            keymap.action(Focus::Search, key(KeyCode::Char('Z'), ctrl_shift), false),
+            redo
+        );
+        assert_eq!(
+            keymap.action(Focus::Search, key(KeyCode::Char('z'), ctrl_shift), false),
+            redo
+        );
+        assert_eq!(
+            keymap.action(
+                Focus::Search,
+                key(KeyCode::Char('Z'), KeyModifiers::CONTROL),
+                false
+            ),
+            redo
+        );
+    }
+
+    #[test]
+    fn files_replace_bindings_per_action() {
+        let mut keymap = Keymap::default();
+        keymap
+            .merge(
+                "[search]\nnew_note = [\"ctrl+b\", \"f2\"]\n[text_box]\nredo = []\n",
+                false,
+            )
+            .unwrap();
+
+        assert_eq!(
+            keymap.keys(super::Context::Search, Action::NewNote),
+            "ctrl+b/f2"
+        );
+        assert_eq!(
+            keymap.action(Focus::Search, key(KeyCode::F(2), KeyModifiers::NONE), false),
+            Some(Action::NewNote)
+        );
+        assert_eq!(
+            keymap.action(
+                Focus::Search,
+                key(KeyCode::Char('n'), KeyModifiers::CONTROL),
+                false
+            ),
+            None
+        );
+        assert_eq!(
+            keymap.keys(super::Context::TextBox, Action::Redo),
+            "unbound"
+        );
+    }
+
+    #[test]
+    fn conflicts_are_rejected() {
+        let conflicts = [
+            // twice in one context
+            "[search]\nedit_note = \"ctrl+n\"",
+            // shadowing a global key
+            "[new_note]\nsave = \"ctrl+q\"",
+            // shadowing a text box key
+            "[edit_note]\nsave = \"ctrl+z\"",
+        ];
+        for conflict in conflicts {
+            let error = Keymap::default().merge(conflict, false).unwrap_err();
+            assert!(
+                error.to_string().contains("is bound to"),
+                "{conflict}: {error}"
+            );
+        }
+
+        // contexts that never apply together may share keys
+        Keymap::default()
+            .merge("[reminders]\nacknowledge = \"ctrl+s\"", false)
+            .unwrap();
+    }
+
+    #[test]
+    fn mistakes_are_reported() {
+        for (content, error) in [
+            ("[serach]\nquit = \"q\"", "unknown section [serach]"),
+            (
+                "[global]\nquti = \"q\"",
+                "unknown action \"quti\" in [global]",
+            ),
+            (
+                "[global]\nsave = \"ctrl+s\"",
+                "save can't be bound in [global]",
+            ),
+            ("[global]\nquit = \"hyper+q\"", "unknown modifier \"hyper\""),
+            ("[global]\nquit = \"f13\"", "unknown key \"f13\""),
+            ("[global]\nquit = 1", "isn't a key"),
+        ] {
+            let result = Keymap::default().merge(content, false).unwrap_err();
+            assert!(result.to_string().contains(error), "{content}: {result}");
+        }
+    }
+}
@@ -1,58 +1,499 @@
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{EventStream, KeyCode, KeyEvent};
+use ratatui::DefaultTerminal;
+use tokio::time::{Duration, Instant};
 
 use rust_shared::request;
 
 use crate::App;
+use crate::confirmation::Confirmation;
+use crate::external_editor::{self, ExternalEdit};
 use crate::focus::Focus;
+use crate::help::Help;
+use crate::keymap::{Action, Context};
+use crate::note_editor::NoteEditor;
+use crate::palette::CommandPalette;
+use crate::search::{distinct_tags, parse_search_text};
+use crate::text_box::InteractiveTextBox;
+
+// keystrokes closer together than this are coalesced into a single search request
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(150);
+
+// how far page up and down scroll the note details
+const DETAILS_SCROLL_LINES: u16 = 10;
 
 impl App {
     pub async fn on_key_event(&mut self, key: KeyEvent) {
-        if self.maybe_exit(key) || self.focus.maybe_update(key) {
+        if let Some(confirmation) = self.confirmation.take() {
+            self.on_confirmation_key_event(confirmation, key).await;
+            return;
+        }
+        if self.help.take().is_some() {
+            return;
+        }
+        if let Some(palette) = self.palette.take() {
+            self.on_palette_key_event(palette, key).await;
             return;
         }
 
-        if key.code == KeyCode::Up || key.code == KeyCode::Down {
+        // with vim, esc leaves insert mode before it closes anything
+        if key.code == KeyCode::Esc
+            && self
+                .focused_text_box()
+                .is_some_and(|text_box| text_box.takes_esc())
+        {
+            self.type_key(key);
+            return;
+        }
+
+        // vim's normal mode picks search results with j and k, like up and down do
+        let vim_move = self.search.text_box.is_vim_normal()
+            && matches!(key.code, KeyCode::Char('j' | 'k'))
+            && key.modifiers.is_empty();
+        if self.focus == Focus::Search && vim_move {
             self.search_results
-                .move_selection(key.code == KeyCode::Down);
+                .move_selection(key.code == KeyCode::Char('j'));
+            self.request_details().await;
             return;
         }
 
+        match self.keymap.action(self.focus, key, self.typing()) {
+            Some(action) => self.run_action(action).await,
+            None => self.type_key(key),
+        }
+    }
+
+    /// Runs an action, bound to a key in the focus' context or not
+    pub async fn run_action(&mut self, action: Action) {
+        let search_text = self.search.text_box.text_box.text.clone();
+
+        match (action, self.focus) {
+            (Action::Quit, _) => self.ask_to_quit(),
+            (Action::Help, focus) => self.help = Some(Help::new(&self.keymap, focus)),
+            (Action::CommandPalette, focus) => {
+                self.palette = Some(CommandPalette::new(&self.keymap, focus));
+            }
+            (Action::NewNote, _) => self.focus = Focus::NewNote,
+            (Action::Reminders, _) => self.focus = Focus::Reminders,
+            (Action::EditNote, _) => {
+                self.open_editor();
+            }
+            (Action::DeleteNote, _) => self.confirm_delete(),
+            (Action::OpenInEditor, Focus::NewNote) => {
+                self.external_edit = Some(ExternalEdit::Draft);
+            }
+            (Action::OpenInEditor, Focus::EditNote) => {
+                self.external_edit = Some(ExternalEdit::Editor);
+            }
+            (Action::OpenInEditor, _) => {
+                // through the note editor, which keeps the changes if saving fails
+                if self.open_editor() {
+                    self.external_edit = Some(ExternalEdit::SelectedNote);
+                }
+            }
+            (Action::ToggleMarkdown, _) => {
+                // switches the note details between rendered and raw Markdown
+                self.note_details.markdown = !self.note_details.markdown;
+            }
+            (Action::SelectPrevious | Action::SelectNext, Focus::Reminders) => {
+                self.reminders.move_selection(action == Action::SelectNext);
+            }
+            (Action::SelectPrevious | Action::SelectNext, _) => {
+                self.search_results
+                    .move_selection(action == Action::SelectNext);
+                self.request_details().await;
+            }
+            (Action::ScrollUp | Action::ScrollDown, _) => {
+                self.note_details
+                    .scroll(action == Action::ScrollDown, DETAILS_SCROLL_LINES);
+            }
+            (Action::Save, Focus::NewNote) => self.submit_new_note().await,
+            (Action::Save, _) => self.save_editor().await,
+            (Action::ToggleField, _) => {
+                if let Some(editor) = &mut self.editor {
+                    editor.toggle_field();
+                }
+            }
+            (Action::Close, Focus::EditNote) => match &self.editor {
+                Some(editor) if editor.is_modified() => {
+                    self.confirmation = Some(Confirmation::DiscardEdit);
+                }
+                _ => self.close_editor(),
+            },
+            (Action::Close, _) => self.focus = Focus::Search,
+            (Action::Export, _) => {
+                // answered with a `markdown_file` per note, then `export_finished`
+                self.export_errors = 0;
+                self.send(request::Message::ExportMarkdown).await;
+            }
+            (Action::Acknowledge | Action::SnoozeHour | Action::SnoozeDay, _) => {
+                self.answer_reminder(action).await;
+            }
+            (
+                Action::SelectAll
+                | Action::Copy
+                | Action::Cut
+                | Action::Paste
+                | Action::Undo
+                | Action::Redo,
+                focus,
+            ) => match focus {
+                Focus::Search => self.search.run_action(action),
+                Focus::NewNote => self.new_note.run_action(action),
+                Focus::EditNote => {
+                    if let Some(editor) = &mut self.editor {
+                        editor.run_action(action);
+                    }
+                }
+                Focus::Reminders => {}
+            },
+        }
+
+        self.search_if_changed(&search_text);
+    }
+
+    /// Hands a key that isn't bound to an action over to the focused text box
+    fn type_key(&mut self, key: KeyEvent) {
+        let search_text = self.search.text_box.text_box.text.clone();
+
         match self.focus {
             Focus::Search => self.search.handle_key_event(key),
             Focus::NewNote => self.new_note.handle_key_event(key),
+            Focus::EditNote => {
+                if let Some(editor) = &mut self.editor {
+                    editor.handle_key_event(key);
+                }
+            }
+            Focus::Reminders => {}
         }
 
-        // if any displayable key is pressed, request the notes
-        // this includes all chars, backspace, delete, etc.
-        let should_request = self.focus == Focus::Search
-            && (key.code.is_backspace()
-                || key.code.is_delete()
-                || matches!(key.code, KeyCode::Char(_)));
-
-        if should_request {
-            self.websocket_client
-                .send(request::Message::GetNotesFiltered(
-                    request::GetNotesFiltered {
-                        search_text: self.search.parsed_search_text.clone(),
-                        tags: Some(self.search.parsed_tags.clone()),
-                        limit: Some(10),
-                        offset: Some(0),
-                    },
-                ))
-                .await
-                .expect("msg");
-        }
-    }
-
-    fn maybe_exit(&mut self, key: KeyEvent) -> bool {
-        if self.focus == Focus::Search
-            && key.modifiers.contains(KeyModifiers::CONTROL)
-            && key.code == KeyCode::Char('q')
-        {
+        self.search_if_changed(&search_text);
+    }
+
+    /// Requests the notes whenever the search text changed, typed or pasted, cut or undone
+    fn search_if_changed(&mut self, search_text: &str) {
+        if self.search.text_box.text_box.text != search_text {
+            self.pending_search = Some(Instant::now() + SEARCH_DEBOUNCE);
+        }
+    }
+
+    /// Sends a request, remembering it until it's answered
+    pub async fn send(&mut self, request: request::Message) {
+        self.in_flight.push_back(request.clone());
+        self.client.send(request).await.expect("msg");
+    }
+
+    /// Sends the requests the rate limit turned away again, once it allows them
+    pub async fn send_retries(&mut self) {
+        let now = Instant::now();
+        let (due, later) = std::mem::take(&mut self.retries)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(at, _)| *at <= now);
+        self.retries = later;
+        for (_, request) in due {
+            self.send(request).await;
+        }
+    }
+
+    /// Quits, asking first if that would lose a draft or changes to a note
+    fn ask_to_quit(&mut self) {
+        let unsaved = !self.new_note.text_box.text.trim().is_empty()
+            || self.editor.as_ref().is_some_and(NoteEditor::is_modified);
+        if unsaved {
+            self.confirmation = Some(Confirmation::Quit);
+        } else {
             self.quit();
-            return true;
         }
+    }
+
+    async fn on_confirmation_key_event(&mut self, confirmation: Confirmation, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('y') => match confirmation {
+                Confirmation::DiscardEdit => self.close_editor(),
+                Confirmation::Quit => self.quit(),
+                // answered with `note_deleted`
+                Confirmation::DeleteNote { id, .. } => {
+                    self.send(request::Message::DeleteNote(request::DeleteNote { id }))
+                        .await;
+                }
+            },
+            KeyCode::Char('n') | KeyCode::Esc => {}
+            // still waiting for an answer
+            _ => self.confirmation = Some(confirmation),
+        }
+    }
+
+    async fn on_palette_key_event(&mut self, mut palette: CommandPalette, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {}
+            KeyCode::Enter => {
+                if let Some(action) = palette.selected() {
+                    self.run_action(action).await;
+                }
+            }
+            KeyCode::Up | KeyCode::Down => {
+                palette.move_selection(key.code == KeyCode::Down);
+                self.palette = Some(palette);
+            }
+            _ => {
+                palette.handle_key_event(key);
+                self.palette = Some(palette);
+            }
+        }
+    }
+
+    /// Sends the note in the editor, answered with `note_updated`, see `handle_backend_message`
+    async fn save_editor(&mut self) {
+        let Some(editor) = &mut self.editor else {
+            return;
+        };
+        editor.saving = true;
+        let request = request::UpdateNote {
+            id: editor.id,
+            text: editor.text.text_box.text.clone(),
+            tags: editor.tag_names(),
+        };
+
+        self.create_missing_tags(&request.tags).await;
+        self.send(request::Message::UpdateNote(request)).await;
+    }
+
+    /// Hands the terminal over to `$VISUAL` or `$EDITOR` for the draft or the edited note, and
+    /// puts what comes back in its place
+    pub async fn edit_externally(&mut self, edit: ExternalEdit, terminal: &mut DefaultTerminal) {
+        let (tags, text) = match (edit, &self.editor) {
+            (ExternalEdit::Draft, _) => {
+                let (tags, text) = parse_search_text(&self.new_note.text_box.text);
+                (tags, text.unwrap_or_default())
+            }
+            (ExternalEdit::Editor | ExternalEdit::SelectedNote, Some(editor)) => {
+                (editor.tag_names(), editor.text.text_box.text.clone())
+            }
+            (_, None) => return,
+        };
+        let content = external_editor::to_file(&tags, &text);
+
+        // the editor reads the keys until it's done, a new stream takes over after
+        self.crossterm_event_stream = EventStream::default();
+        let edited = external_editor::edit(terminal, &content).await;
+
+        let (tags, text) = match edited {
+            Ok(Some(edited)) => external_editor::from_file(&edited),
+            Ok(None) | Err(_) => {
+                self.note_details.set_status(match edited {
+                    Err(e) => e.to_string(),
+                    _ => "nothing changed".to_string(),
+                });
+                if edit == ExternalEdit::SelectedNote {
+                    self.close_editor();
+                }
+                return;
+            }
+        };
+
+        match edit {
+            ExternalEdit::Draft => {
+                let tags = tags
+                    .iter()
+                    .map(|tag| format!("#{tag} "))
+                    .collect::<String>();
+                self.new_note.set_text(tags + &text);
+            }
+            ExternalEdit::Editor | ExternalEdit::SelectedNote => {
+                if let Some(editor) = &mut self.editor {
+                    editor.set_content(&tags, text);
+                }
+                if edit == ExternalEdit::SelectedNote {
+                    self.save_editor().await;
+                }
+            }
+        }
+    }
+
+    /// Sends the new note, its leading `#tags` split off like in the search box
+    async fn submit_new_note(&mut self) {
+        let Some((tags, text)) = new_note(&self.new_note.text_box.text) else {
+            self.note_details
+                .set_status("a note needs some text".to_string());
+            return;
+        };
+
+        self.create_missing_tags(&tags).await;
+
+        // answered with `note_created`, see `handle_backend_message`
+        self.submitting = true;
+        self.send(request::Message::CreateNote(request::CreateNote {
+            text,
+            title: None,
+            tags,
+            extract_dates: true,
+            timezone: self.timezone.clone(),
+        }))
+        .await;
+    }
+
+    /// Creates the tags the backend doesn't know yet, ahead of a note that uses them
+    ///
+    /// The backend handles a connection's requests in order, so they exist by the time the
+    /// note is saved.
+    async fn create_missing_tags(&mut self, names: &[String]) {
+        for name in names {
+            if self.tags.contains(name) {
+                continue;
+            }
+            self.tags.push(name.clone());
+            self.send(request::Message::CreateTag(request::CreateTag {
+                name: name.clone(),
+            }))
+            .await;
+        }
+    }
+
+    /// Opens the selected search result in the editor, once its details are in
+    fn open_editor(&mut self) -> bool {
+        let Some(details) = &self.note_details.details else {
+            return false;
+        };
+        if self.search_results.selected_id() != Some(details.note.id) {
+            return false;
+        }
+
+        let hints = self.keymap.hints(
+            Context::EditNote,
+            &[
+                (Action::Save, "save"),
+                (Action::ToggleField, "tags/text"),
+                (Action::OpenInEditor, "$EDITOR"),
+                (Action::DeleteNote, "delete"),
+                (Action::Close, "close"),
+            ],
+        );
+        self.editor = Some(NoteEditor::new(details, &hints).vim(self.vim));
+        self.focus = Focus::EditNote;
+        true
+    }
+
+    pub fn close_editor(&mut self) {
+        self.editor = None;
+        self.focus = Focus::Search;
+    }
+
+    /// Asks before deleting the note being edited, or else the selected search result
+    fn confirm_delete(&mut self) {
+        let id = match &self.editor {
+            Some(editor) => Some(editor.id),
+            None => self.search_results.selected_id(),
+        };
+        let Some(id) = id else {
+            return;
+        };
+
+        let title = match &self.note_details.details {
+            Some(details) if details.note.id == id => details.note.display_title(),
+            _ => self
+                .search_results
+                .selected_title()
+                .unwrap_or_else(|| id.to_string()),
+        };
+        self.confirmation = Some(Confirmation::DeleteNote { id, title });
+    }
+
+    /// Acknowledges or snoozes the selected reminder
+    async fn answer_reminder(&mut self, action: Action) {
+        let Some(reminder) = self.reminders.selected() else {
+            return;
+        };
+        let (id, date) = (reminder.id, reminder.date);
+        let snooze = |minutes| {
+            request::Message::SnoozeReminder(request::SnoozeReminder { id, date, minutes })
+        };
+        let request = match action {
+            Action::SnoozeHour => snooze(60),
+            Action::SnoozeDay => snooze(24 * 60),
+            _ => request::Message::AcknowledgeReminder(request::AcknowledgeReminder { id, date }),
+        };
+
+        // answered with the reminders still due
+        self.send(request).await;
+    }
+
+    pub async fn send_search(&mut self) {
+        self.pending_search = None;
+        self.send(request::Message::GetNotesFiltered(
+            request::GetNotesFiltered {
+                search_text: self.search.parsed_search_text.clone(),
+                tags: Some(self.search.parsed_tags.clone()),
+                dates: None,
+                created: None,
+                timezone: self.timezone.clone(),
+                limit: Some(10),
+                offset: Some(0),
+            },
+        ))
+        .await;
+    }
+
+    /// Searches again and reloads the details, after a note changed
+    pub async fn refresh_search(&mut self) {
+        self.note_details.clear();
+        self.send_search().await;
+    }
+
+    /// Asks for the selected note's details, unless they're already shown
+    pub async fn request_details(&mut self) {
+        let Some(id) = self.search_results.selected_id() else {
+            self.note_details.clear();
+            return;
+        };
+        if self.note_details.id() == Some(id) {
+            return;
+        }
+
+        self.send(request::Message::GetNoteDetails(request::GetNoteDetails {
+            id,
+        }))
+        .await;
+    }
+
+    /// Whether the focused text box takes characters, which it doesn't in vim's normal mode. An
+    /// empty search box leaves them to the keys bound to them too, `?` for the help
+    fn typing(&self) -> bool {
+        if self.focus == Focus::Search && self.search.text_box.text_box.text.is_empty() {
+            return false;
+        }
+        self.focused_text_box()
+            .is_some_and(|text_box| !text_box.is_vim_normal())
+    }
+
+    fn focused_text_box(&self) -> Option<&InteractiveTextBox> {
+        match self.focus {
+            Focus::Search => Some(&self.search.text_box),
+            Focus::NewNote => Some(&self.new_note),
+            Focus::EditNote => self.editor.as_ref().map(NoteEditor::focused),
+            Focus::Reminders => None,
+        }
+    }
+}
+
+/// The tags and text of the new note written as `text`, `None` without any text
+fn new_note(text: &str) -> Option<(Vec<String>, String)> {
+    let (tags, text) = parse_search_text(text);
+    let text = text.filter(|text| !text.trim().is_empty())?;
+    Some((distinct_tags(tags), text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::new_note;
 
-        false
+    #[test]
+    fn new_notes_send_each_tag_once() {
+        assert_eq!(
+            new_note("#work #idea #work fix the build"),
+            Some((
+                vec!["work".to_string(), "idea".to_string()],
+                "fix the build".to_string()
+            ))
+        );
+        assert_eq!(new_note("#work #work  "), None);
     }
 }
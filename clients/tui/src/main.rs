@@ -1,23 +1,45 @@
-use color_eyre::Result;
+use color_eyre::{Result, eyre::eyre};
 use crossterm::event::EventStream;
-use ratatui::prelude::{Buffer, Color, Rect, Widget};
-use std::sync::LazyLock;
+use ratatui::{
+    prelude::{Buffer, Color, Rect, Style, Widget},
+    widgets::Paragraph,
+};
+use std::{collections::VecDeque, path::PathBuf, sync::LazyLock};
+use tokio::time::Instant;
 
-use rust_shared::request;
+use rust_shared::{dates, request};
 
 mod client;
 mod clipboard;
+mod confirmation;
 mod events;
+mod external_editor;
 mod focus;
+mod help;
+mod history;
+mod keymap;
 mod keys;
+mod markdown;
+mod note_details;
+mod note_editor;
+mod palette;
+mod reminders;
 mod search;
 mod text_box;
+mod vim;
 
-use client::websocket::WebSocketClient;
+use client::{Transport, websocket::WebSocketClient};
+use confirmation::Confirmation;
+use external_editor::ExternalEdit;
 use focus::Focus;
+use help::Help;
+use keymap::{Action, Context, Keymap};
+use note_details::NoteDetailsBox;
+use note_editor::NoteEditor;
+use palette::CommandPalette;
+use reminders::RemindersBox;
 use search::{SearchBox, SearchResultsBox};
 use text_box::InteractiveTextBox;
-use text_box::TextBox;
 
 // set develop flag
 pub static DEV: LazyLock<bool> =
@@ -26,10 +48,83 @@ pub static DEV: LazyLock<bool> =
 #[tokio::main]
 async fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
-    App::new().run().await
+    let options = Options::from_args()?;
+    // a broken keymap is reported before anything else happens
+    let keymap = Keymap::load(options.keymap.as_deref())?;
+
+    let client = match connect(&options).await {
+        Ok(client) => client,
+        Err(e) if options.embedded_db_url.is_none() => {
+            eprintln!("websocket connection failed - is the backend running? ({e})");
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+
+    App::new(client, options.vim, keymap, options.export_dir)
+        .run()
+        .await
+}
+
+/// Command line options
+struct Options {
+    /// `--server <url>`, the backend to connect to
+    server_url: String,
+    /// `--embedded <database url>`, runs the engine in this process instead of connecting
+    embedded_db_url: Option<String>,
+    /// `--vim`, modal editing in the text boxes
+    vim: bool,
+    /// `--keymap <path>`, key bindings to use instead of the user's keymap file
+    keymap: Option<PathBuf>,
+    /// `--export-dir <path>`, where the export action writes the notes
+    export_dir: PathBuf,
+}
+
+impl Options {
+    fn from_args() -> Result<Self> {
+        let mut options = Self {
+            server_url: "ws://localhost:3210".to_string(),
+            embedded_db_url: None,
+            vim: false,
+            keymap: None,
+            export_dir: PathBuf::from("rember-export"),
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| eyre!("{arg} needs a value"));
+            match arg.as_str() {
+                "--server" => options.server_url = value()?,
+                "--embedded" => options.embedded_db_url = Some(value()?),
+                "--vim" => options.vim = true,
+                "--keymap" => options.keymap = Some(PathBuf::from(value()?)),
+                "--export-dir" => options.export_dir = PathBuf::from(value()?),
+                _ => return Err(eyre!("unknown argument: {arg}")),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+async fn connect(options: &Options) -> Result<Box<dyn Transport>> {
+    let Some(db_url) = &options.embedded_db_url else {
+        return Ok(Box::new(
+            WebSocketClient::connect(&options.server_url).await?,
+        ));
+    };
+
+    #[cfg(feature = "embedded")]
+    return Ok(Box::new(
+        client::in_process::InProcessClient::connect(db_url).await?,
+    ));
+
+    #[cfg(not(feature = "embedded"))]
+    Err(eyre!(
+        "can't open {db_url}, rember-tui was built without the `embedded` feature"
+    ))
 }
 
-#[derive(Default)]
 pub struct App {
     running: bool,
     crossterm_event_stream: EventStream,
@@ -38,52 +133,130 @@ pub struct App {
 
     search: SearchBox,
     search_results: SearchResultsBox,
-    response_box: TextBox,
+    pending_search: Option<Instant>,
+    /// Requests not answered yet, oldest first, the order the backend answers them in
+    in_flight: VecDeque<request::Message>,
+    /// Requests the rate limit turned away, to send again once it allows them
+    retries: Vec<(Instant, request::Message)>,
+    note_details: NoteDetailsBox,
     new_note: InteractiveTextBox,
+    /// The new note was sent to the backend, and stays in its box until it's confirmed
+    submitting: bool,
+    /// Open while focus is on [`Focus::EditNote`]
+    editor: Option<NoteEditor>,
+    /// Asked over everything else, see [`Confirmation`]
+    confirmation: Option<Confirmation>,
+    /// The keys bound where the focus is, over everything but a confirmation
+    help: Option<Help>,
+    /// Every action where the focus is, to run one by name
+    palette: Option<CommandPalette>,
+    /// Asked for by a key, and run by the main loop, which has the terminal to hand over
+    external_edit: Option<ExternalEdit>,
+    reminders: RemindersBox,
+    /// Names of the tags the backend knows about
+    tags: Vec<String>,
+    /// IANA name of the local timezone, which the backend reads dates in
+    timezone: Option<String>,
+    /// Vim-style modal editing, for the editor's boxes as they're opened
+    vim: bool,
+    /// Which key runs which [`Action`], per [`Context`]
+    keymap: Keymap,
+    /// Where [`Action::Export`] writes a Markdown file per note
+    export_dir: PathBuf,
+    /// Files of the running export that couldn't be written
+    export_errors: usize,
 
-    websocket_client: WebSocketClient,
+    client: Box<dyn Transport>,
 }
 
 impl App {
-    fn new() -> Self {
+    fn new(client: Box<dyn Transport>, vim: bool, keymap: Keymap, export_dir: PathBuf) -> Self {
+        let new_note_hints = keymap.hints(
+            Context::NewNote,
+            &[
+                (Action::Save, "save"),
+                (Action::OpenInEditor, "$EDITOR"),
+                (Action::Close, "close"),
+            ],
+        );
+        let toggle_markdown = keymap.keys(Context::Search, Action::ToggleMarkdown);
+        let scroll = format!(
+            "{}/{}: scroll",
+            keymap.keys(Context::Search, Action::ScrollUp),
+            keymap.keys(Context::Search, Action::ScrollDown)
+        );
         Self {
+            running: false,
+            crossterm_event_stream: EventStream::default(),
+            focus: Focus::default(),
+            search: SearchBox::default()
+                .title(format!(
+                    "Search ({})",
+                    keymap.hints(
+                        Context::Global,
+                        &[(Action::Help, "help"), (Action::CommandPalette, "commands")]
+                    )
+                ))
+                .vim(vim),
+            search_results: SearchResultsBox::default(),
+            pending_search: None,
+            in_flight: VecDeque::new(),
+            retries: Vec::new(),
+            note_details: NoteDetailsBox::default().hints(
+                format!("{toggle_markdown}: raw Markdown, {scroll}"),
+                format!("{toggle_markdown}: rendered Markdown, {scroll}"),
+            ),
             new_note: InteractiveTextBox::default()
-                .title("New Note".to_string())
-                .border_color(Color::Yellow),
-            ..Default::default()
+                .title(format!("New Note (leading #tags, {new_note_hints})"))
+                .border_color(Color::Yellow)
+                .multiline(true)
+                .vim(vim),
+            submitting: false,
+            editor: None,
+            confirmation: None,
+            help: None,
+            palette: None,
+            external_edit: None,
+            reminders: RemindersBox::default().hints(
+                keymap.hints(
+                    Context::Reminders,
+                    &[
+                        (Action::Acknowledge, "acknowledge"),
+                        (Action::SnoozeHour, "snooze an hour"),
+                        (Action::SnoozeDay, "snooze a day"),
+                    ],
+                ),
+                keymap.keys(Context::Search, Action::Reminders),
+            ),
+            tags: Vec::new(),
+            timezone: iana_time_zone::get_timezone().ok(),
+            vim,
+            keymap,
+            export_dir,
+            export_errors: 0,
+            client,
         }
     }
 }
 
 impl App {
     pub async fn run(mut self) -> Result<()> {
-        let Ok((outgoing_thread, incoming_thread)) = self
-            .websocket_client
-            .connect_and_run("ws://localhost:3210")
-            .await
-        else {
-            eprintln!("websocket connection failed - is the backend running?");
-            return Ok(());
-        };
-
-        // request the whole list of tags first thing
-        self.websocket_client
-            .send(request::Message::GetTags)
-            .await
-            .expect("msg");
+        // request the whole list of tags and whatever reminders are due first thing
+        self.send(request::Message::GetTags).await;
+        self.send(request::Message::GetReminders).await;
 
         // main loop
         let mut terminal = ratatui::init();
         self.running = true;
         while self.running {
             self.handle_events().await?;
+            if let Some(edit) = self.external_edit.take() {
+                self.edit_externally(edit, &mut terminal).await;
+            }
             terminal.draw(|frame| frame.render_widget(&self, frame.area()))?;
         }
         ratatui::restore();
 
-        // clean up
-        outgoing_thread.abort();
-        incoming_thread.abort();
         Ok(())
     }
 
@@ -92,6 +265,29 @@ impl App {
     }
 }
 
+/// `dates: due 2026-11-01, 2026-10-23 15:00` for the date phrases in a note being written
+fn date_preview(text: &str) -> Option<String> {
+    let detected = dates::extract_dates(text, chrono::Local::now().naive_local());
+    if detected.is_empty() {
+        return None;
+    }
+
+    let detected = detected
+        .iter()
+        .map(|detected| {
+            let date = match detected.time {
+                Some(time) => format!("{} {}", detected.date, time.format("%H:%M")),
+                None => detected.date.to_string(),
+            };
+            match &detected.label {
+                Some(label) => format!("{label} {date}"),
+                None => date,
+            }
+        })
+        .collect::<Vec<_>>();
+    Some(format!("dates: {}", detected.join(", ")))
+}
+
 impl Widget for &App {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let search_area = Rect::new(0, 0, area.width, 3).clamp(area);
@@ -100,14 +296,72 @@ impl Widget for &App {
         let search_results_area = Rect::new(1, 3, area.width - 2, 3).clamp(area);
         self.search_results.render(search_results_area, buf);
 
-        let response_area = Rect::new(0, 6, area.width, area.height - 6).clamp(area);
-        self.response_box.render(response_area, buf);
+        let note_details_area = Rect::new(0, 6, area.width, area.height - 6).clamp(area);
+        self.note_details.render(note_details_area, buf);
 
         if self.focus == Focus::NewNote {
             // vertical middle third of the screen
             let new_note_area =
                 Rect::new(0, area.height / 3, area.width, area.height / 3).clamp(area);
             self.new_note.render(new_note_area, buf);
+
+            // the dates the backend will store along with the note, right below it
+            if let Some(preview) = date_preview(&self.new_note.text_box.text) {
+                let preview_area = Rect::new(0, new_note_area.bottom(), area.width, 1).clamp(area);
+                Paragraph::new(preview)
+                    .style(Style::default().bg(Color::Yellow).fg(Color::Black))
+                    .render(preview_area, buf);
+            }
+        }
+
+        if self.focus == Focus::EditNote
+            && let Some(editor) = &self.editor
+        {
+            let editor_area =
+                Rect::new(0, area.height / 4, area.width, area.height / 2).clamp(area);
+            editor.render(editor_area, buf);
+        }
+
+        if self.focus == Focus::Reminders {
+            let reminders_area =
+                Rect::new(0, area.height / 3, area.width, area.height / 3).clamp(area);
+            self.reminders.render(reminders_area, buf);
+        }
+
+        if let Some(help) = &self.help {
+            let help_area = Rect::new(
+                area.width / 6,
+                area.height / 8,
+                area.width * 2 / 3,
+                area.height * 3 / 4,
+            )
+            .clamp(area);
+            help.render(help_area, buf);
+        }
+
+        if let Some(palette) = &self.palette {
+            let palette_area =
+                Rect::new(area.width / 4, 3, area.width / 2, area.height / 2).clamp(area);
+            palette.render(palette_area, buf);
+        }
+
+        if let Some(confirmation) = &self.confirmation {
+            let confirmation_area = Rect::new(
+                area.width / 4,
+                area.height.saturating_sub(3) / 2,
+                area.width / 2,
+                3,
+            )
+            .clamp(area);
+            confirmation.render(confirmation_area, buf);
+        }
+
+        if let Some(banner) = self.reminders.banner() {
+            let banner_area =
+                Rect::new(0, area.height.saturating_sub(1), area.width, 1).clamp(area);
+            Paragraph::new(banner)
+                .style(Style::default().bg(Color::Magenta).fg(Color::Black))
+                .render(banner_area, buf);
         }
     }
 }
@@ -0,0 +1,324 @@
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+/// Lines of `text` rendered as Markdown, with line breaks kept as written
+pub fn render(text: &str) -> Vec<Line<'static>> {
+    let options =
+        Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS | Options::ENABLE_TABLES;
+
+    let mut renderer = Renderer::default();
+    for event in Parser::new_ext(text, options) {
+        renderer.event(event);
+    }
+    renderer.finish()
+}
+
+#[derive(Default)]
+struct Renderer {
+    lines: Vec<Line<'static>>,
+    /// The line being built
+    spans: Vec<Span<'static>>,
+    /// Inline styles in effect, innermost last
+    styles: Vec<Style>,
+    /// Open lists, with the next item's number for ordered ones
+    lists: Vec<Option<u64>>,
+    quote_depth: usize,
+    in_code_block: bool,
+    /// Destinations of open links, shown after their text
+    links: Vec<String>,
+}
+
+fn heading_style(level: HeadingLevel) -> Style {
+    let style = Style::default().add_modifier(Modifier::BOLD);
+    match level {
+        HeadingLevel::H1 => style.fg(Color::Magenta).add_modifier(Modifier::UNDERLINED),
+        HeadingLevel::H2 => style.fg(Color::Magenta),
+        _ => style,
+    }
+}
+
+fn code_style() -> Style {
+    Style::default().fg(Color::Yellow)
+}
+
+fn dim_style() -> Style {
+    Style::default().fg(Color::DarkGray)
+}
+
+/// Whether a line has nothing on it but quote bars
+fn is_blank(line: &Line) -> bool {
+    line.spans
+        .iter()
+        .all(|span| span.content.trim_start_matches("│ ").is_empty())
+}
+
+impl Renderer {
+    fn style(&self) -> Style {
+        self.styles
+            .iter()
+            .fold(Style::default(), |style, inner| style.patch(*inner))
+    }
+
+    /// Quote bars for a new line, and list indentation unless it's an item's first line
+    fn start_line(&mut self, indent_lists: bool) {
+        for _ in 0..self.quote_depth {
+            self.spans.push(Span::styled("│ ", dim_style()));
+        }
+        let depth = if indent_lists {
+            self.lists.len()
+        } else {
+            self.lists.len().saturating_sub(1)
+        };
+        if depth > 0 {
+            self.spans.push(Span::raw("  ".repeat(depth)));
+        }
+    }
+
+    fn push(&mut self, text: &str, style: Style) {
+        if self.spans.is_empty() {
+            self.start_line(true);
+        }
+        self.spans.push(Span::styled(text.to_string(), style));
+    }
+
+    fn end_line(&mut self) {
+        self.lines.push(Line::from(std::mem::take(&mut self.spans)));
+    }
+
+    /// Ends the current line, if there is one
+    fn flush(&mut self) {
+        if !self.spans.is_empty() {
+            self.end_line();
+        }
+    }
+
+    /// Ends a block, separated from the next one by an empty line outside of lists, which keeps
+    /// the quote bars of the quote it's in
+    fn end_block(&mut self) {
+        self.flush();
+        if self.lists.is_empty() && !self.lines.last().is_none_or(is_blank) {
+            self.start_line(true);
+            self.end_line();
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) if self.in_code_block => {
+                for line in text.split_inclusive('\n') {
+                    self.push(line.trim_end_matches('\n'), code_style());
+                    if line.ends_with('\n') {
+                        self.end_line();
+                    }
+                }
+            }
+            Event::Text(text) => self.push(&text, self.style()),
+            Event::Code(code) => self.push(&code, code_style()),
+            Event::InlineHtml(html) | Event::Html(html) => self.push(&html, dim_style()),
+            Event::SoftBreak | Event::HardBreak => self.end_line(),
+            Event::Rule => {
+                self.flush();
+                self.push(&"─".repeat(40), dim_style());
+                self.end_block();
+            }
+            Event::TaskListMarker(checked) => {
+                // the box takes the bullet's place, after the item's indentation
+                if self.spans.last().is_some_and(|span| span.content == "• ") {
+                    self.spans.pop();
+                }
+                let (marker, style) = if checked {
+                    ("☑ ", Style::default().fg(Color::Green))
+                } else {
+                    ("☐ ", Style::default())
+                };
+                self.spans.push(Span::styled(marker, style));
+            }
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.flush();
+                self.styles.push(heading_style(level));
+            }
+            Tag::BlockQuote(_) => {
+                self.flush();
+                self.quote_depth += 1;
+            }
+            Tag::CodeBlock(_) => {
+                self.flush();
+                self.in_code_block = true;
+            }
+            Tag::List(start) => {
+                self.flush();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush();
+                self.start_line(false);
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.spans
+                    .push(Span::styled(marker, Style::default().fg(Color::Cyan)));
+            }
+            Tag::Emphasis => self
+                .styles
+                .push(Style::default().add_modifier(Modifier::ITALIC)),
+            Tag::Strong => self
+                .styles
+                .push(Style::default().add_modifier(Modifier::BOLD)),
+            Tag::Strikethrough => self
+                .styles
+                .push(Style::default().add_modifier(Modifier::CROSSED_OUT)),
+            Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. } => {
+                self.styles.push(
+                    Style::default()
+                        .fg(Color::Blue)
+                        .add_modifier(Modifier::UNDERLINED),
+                );
+                self.links.push(dest_url.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Heading(_) => {
+                self.styles.pop();
+                self.end_block();
+            }
+            TagEnd::Paragraph | TagEnd::HtmlBlock => self.end_block(),
+            TagEnd::BlockQuote(_) => {
+                self.flush();
+                self.quote_depth -= 1;
+                // the gap after the quote is outside of it
+                if self.lines.last().is_some_and(is_blank) {
+                    self.lines.pop();
+                }
+                self.end_block();
+            }
+            TagEnd::CodeBlock => {
+                self.in_code_block = false;
+                self.end_block();
+            }
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+                self.end_block();
+            }
+            TagEnd::Item | TagEnd::TableHead | TagEnd::TableRow => self.flush(),
+            TagEnd::Table => self.end_block(),
+            TagEnd::TableCell => self.push(" │ ", dim_style()),
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => {
+                self.styles.pop();
+            }
+            TagEnd::Link | TagEnd::Image => {
+                self.styles.pop();
+                if let Some(destination) = self.links.pop()
+                    && !destination.is_empty()
+                {
+                    self.push(&format!(" ({destination})"), dim_style());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(mut self) -> Vec<Line<'static>> {
+        self.flush();
+        while self.lines.last().is_some_and(is_blank) {
+            self.lines.pop();
+        }
+        self.lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::{Color, Modifier};
+
+    use super::render;
+
+    /// The rendered lines without their styles
+    fn plain(text: &str) -> Vec<String> {
+        render(text)
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nested_lists_are_indented() {
+        assert_eq!(
+            plain("- fruit\n  - apples\n  - pears\n- bread"),
+            ["• fruit", "  • apples", "  • pears", "• bread"]
+        );
+    }
+
+    #[test]
+    fn ordered_lists_count_from_their_start() {
+        assert_eq!(
+            plain("3. three\n4. four\n   1. inner\n\nafter"),
+            ["3. three", "4. four", "  1. inner", "", "after"]
+        );
+    }
+
+    #[test]
+    fn task_boxes_replace_bullets() {
+        assert_eq!(plain("- [ ] milk\n- [x] eggs"), ["☐ milk", "☑ eggs"]);
+    }
+
+    #[test]
+    fn code_blocks_keep_their_lines() {
+        let text = "```rust\nfn main() {\n    run();\n}\n```\nafter";
+        assert_eq!(plain(text), ["fn main() {", "    run();", "}", "", "after"]);
+        assert_eq!(render(text)[1].spans[0].style.fg, Some(Color::Yellow));
+    }
+
+    #[test]
+    fn block_quotes_get_a_bar_per_level() {
+        assert_eq!(
+            plain("> said\n>\n> > quoted\n>\n> back\n\nreply"),
+            ["│ said", "│ ", "│ │ quoted", "│ ", "│ back", "", "reply"]
+        );
+    }
+
+    #[test]
+    fn links_show_where_they_go() {
+        let lines = render("see [the docs](https://example.com) or <https://rember.rs>");
+        let spans = &lines[0].spans;
+        let text = spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect::<String>();
+        assert_eq!(
+            text,
+            "see the docs (https://example.com) or https://rember.rs (https://rember.rs)"
+        );
+
+        let link = spans
+            .iter()
+            .find(|span| span.content == "the docs")
+            .unwrap();
+        assert_eq!(link.style.fg, Some(Color::Blue));
+        assert!(link.style.add_modifier.contains(Modifier::UNDERLINED));
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    prelude::{Buffer, Rect, Style, Widget},
+    style::{Color, Modifier},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Wrap},
+};
+use uuid::Uuid;
+
+use rust_shared::response::NoteWithDetails;
+
+use crate::markdown;
+
+/// How long a status stays at the bottom of the box
+const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The selected search result in full, along with its tags, dates and links
+pub struct NoteDetailsBox {
+    pub details: Option<NoteWithDetails>,
+    /// Lines scrolled past, independent of the selection in the search results
+    pub scroll: u16,
+    /// Render the text as Markdown rather than as written
+    pub markdown: bool,
+    /// Last thing the backend said that isn't a note, e.g. an error, and when
+    status: Option<(String, Instant)>,
+    /// Keys for the title, while the text shows as Markdown and while it shows as written
+    hints: (String, String),
+    /// Width and height inside the border when last rendered, which wrapping depends on
+    inner: Cell<(u16, u16)>,
+}
+
+impl Default for NoteDetailsBox {
+    fn default() -> Self {
+        Self {
+            details: None,
+            scroll: 0,
+            markdown: true,
+            status: None,
+            hints: Default::default(),
+            inner: Cell::default(),
+        }
+    }
+}
+
+impl NoteDetailsBox {
+    pub fn hints(mut self, markdown: String, raw: String) -> Self {
+        self.hints = (markdown, raw);
+        self
+    }
+
+    /// Shows the details if they're for `selected`, which they may no longer be when the
+    /// selection moved on while they were requested
+    pub fn set_details(&mut self, details: NoteWithDetails, selected: Option<Uuid>) {
+        if selected != Some(details.note.id) {
+            return;
+        }
+        if self.id() != Some(details.note.id) {
+            self.scroll = 0;
+        }
+        self.details = Some(details);
+    }
+
+    pub fn clear(&mut self) {
+        self.details = None;
+        self.scroll = 0;
+    }
+
+    /// Shows `status` until it times out or the next one replaces it
+    pub fn set_status(&mut self, status: impl Into<String>) {
+        self.status = Some((status.into(), Instant::now()));
+    }
+
+    pub fn id(&self) -> Option<Uuid> {
+        self.details.as_ref().map(|details| details.note.id)
+    }
+
+    pub fn scroll(&mut self, direction_down: bool, lines: u16) {
+        self.scroll = if direction_down {
+            self.scroll.saturating_add(lines).min(self.max_scroll())
+        } else {
+            self.scroll.saturating_sub(lines)
+        };
+    }
+
+    /// Scrolling further would only leave space below the last line
+    fn max_scroll(&self) -> u16 {
+        let (width, height) = self.inner.get();
+        // nothing's been rendered yet, so there's no telling how the lines wrap
+        if width == 0 {
+            return 0;
+        }
+        let lines = self.paragraph().line_count(width);
+        u16::try_from(lines.saturating_sub(height.into())).unwrap_or(u16::MAX)
+    }
+
+    /// The lines, wrapped but keeping their indentation, which list items and code blocks rely on
+    fn paragraph(&self) -> Paragraph<'static> {
+        Paragraph::new(self.lines()).wrap(Wrap { trim: false })
+    }
+
+    fn lines(&self) -> Vec<Line<'static>> {
+        let Some(details) = &self.details else {
+            return Vec::new();
+        };
+        let dim = Style::default().fg(Color::DarkGray);
+
+        let mut lines = vec![Line::styled(
+            details.note.display_title(),
+            Style::default().add_modifier(Modifier::BOLD),
+        )];
+
+        // `2026-10-19 14:02  #idea #work`
+        let created_at = details.note.created_at.with_timezone(&chrono::Local);
+        let mut header = vec![Span::styled(
+            created_at.format("%Y-%m-%d %H:%M").to_string(),
+            dim,
+        )];
+        for tag in &details.tags {
+            header.push(Span::raw(" "));
+            header.push(Span::styled(
+                format!("#{}", tag.name),
+                Style::default().fg(Color::Blue),
+            ));
+        }
+        lines.push(Line::from(header));
+
+        for note_date in &details.note_dates {
+            let mut spans = vec![Span::styled(
+                note_date.date.to_string(),
+                Style::default().fg(Color::Yellow),
+            )];
+            if let Some(label) = &note_date.label {
+                spans.push(Span::raw(format!(" {label}")));
+            }
+            if let Some(rrule) = &note_date.rrule {
+                spans.push(Span::styled(format!(" (repeats {rrule})"), dim));
+            }
+            lines.push(Line::from(spans));
+        }
+
+        lines.push(Line::default());
+        if self.markdown {
+            lines.extend(markdown::render(&details.note.text));
+        } else {
+            lines.extend(
+                details
+                    .note
+                    .text
+                    .lines()
+                    .map(|line| Line::raw(line.to_string())),
+            );
+        }
+
+        if !details.note_links.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::styled("linked notes", dim));
+            for linked in &details.note_links {
+                lines.push(Line::from(vec![
+                    Span::styled("→ ", dim),
+                    Span::raw(linked.display_title()),
+                ]));
+            }
+        }
+
+        lines
+    }
+}
+
+impl Widget for &NoteDetailsBox {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let hints = if self.markdown {
+            &self.hints.0
+        } else {
+            &self.hints.1
+        };
+        let title = format!("Note ({hints})");
+        let mut block = Block::bordered().title(title);
+        if let Some((status, at)) = &self.status
+            && at.elapsed() < STATUS_TIMEOUT
+        {
+            block = block.title_bottom(status.clone());
+        }
+
+        let inner = block.inner(area);
+        self.inner.set((inner.width, inner.height));
+        // the text may have gotten shorter since it was scrolled, e.g. by showing it as written
+        let scroll = self.scroll.min(self.max_scroll());
+
+        self.paragraph()
+            .scroll((scroll, 0))
+            .block(block)
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use chrono::Utc;
+    use ratatui::prelude::{Buffer, Rect, Widget};
+    use rust_shared::response::{Note, NoteWithDetails};
+    use uuid::Uuid;
+
+    use super::{NoteDetailsBox, STATUS_TIMEOUT};
+
+    #[test]
+    fn scrolling_stops_at_the_last_line() {
+        let note = Note {
+            id: Uuid::nil(),
+            text: (1..=20)
+                .map(|i| format!("line {i}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            title: Some("twenty lines".to_string()),
+            created_at: Utc::now(),
+        };
+        let mut details = NoteDetailsBox::default();
+        details.set_details(
+            NoteWithDetails {
+                note: note.clone(),
+                tags: Vec::new(),
+                note_dates: Vec::new(),
+                note_links: Vec::new(),
+            },
+            Some(note.id),
+        );
+
+        // title, created date, a blank line and the text, 8 lines at a time
+        let area = Rect::new(0, 0, 30, 10);
+        details.render(area, &mut Buffer::empty(area));
+        details.scroll(true, 100);
+        assert_eq!(details.scroll, 23 - 8);
+
+        // in a narrower box, lines wrap and there is more to scroll through
+        let area = Rect::new(0, 0, 6, 10);
+        details.render(area, &mut Buffer::empty(area));
+        details.scroll(true, 100);
+        assert!(details.scroll > 23 - 8);
+
+        details.scroll(false, 100);
+        assert_eq!(details.scroll, 0);
+    }
+
+    #[test]
+    fn status_times_out() {
+        let mut details = NoteDetailsBox::default();
+        let area = Rect::new(0, 0, 30, 4);
+        let bottom = |details: &NoteDetailsBox| {
+            let mut buf = Buffer::empty(area);
+            details.render(area, &mut buf);
+            (0..area.width)
+                .map(|x| buf[(x, area.height - 1)].symbol())
+                .collect::<String>()
+        };
+
+        details.set_status("saved");
+        assert!(bottom(&details).contains("saved"));
+
+        details.status = Some(("saved".to_string(), Instant::now() - STATUS_TIMEOUT));
+        assert!(!bottom(&details).contains("saved"));
+    }
+}
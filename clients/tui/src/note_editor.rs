@@ -0,0 +1,151 @@
+use crossterm::event::KeyEvent;
+use ratatui::{
+    prelude::{Buffer, Rect, Widget},
+    style::Color,
+    widgets::Clear,
+};
+use uuid::Uuid;
+
+use rust_shared::response::NoteWithDetails;
+
+use crate::keymap::Action;
+use crate::search::distinct_tags;
+use crate::text_box::InteractiveTextBox;
+
+/// An existing note being edited, its tags as `#tag` words in a box above the text
+pub struct NoteEditor {
+    pub id: Uuid,
+    pub tags: InteractiveTextBox,
+    pub text: InteractiveTextBox,
+    pub editing_tags: bool,
+    /// Waiting for the backend to confirm the update
+    pub saving: bool,
+    original_tags: String,
+    original_text: String,
+}
+
+impl NoteEditor {
+    /// `hints` on the keys to use, for the title
+    pub fn new(details: &NoteWithDetails, hints: &str) -> Self {
+        let tags = tag_words(details.tags.iter().map(|tag| tag.name.as_str()));
+        let text = details.note.text.clone();
+
+        let mut editor = Self {
+            id: details.note.id,
+            tags: InteractiveTextBox::default().title("Tags".to_string()),
+            text: InteractiveTextBox::default()
+                .title(format!(
+                    "Edit \"{}\" ({hints})",
+                    details.note.display_title()
+                ))
+                .multiline(true),
+            editing_tags: false,
+            saving: false,
+            original_tags: tags.clone(),
+            original_text: text.clone(),
+        };
+        editor.tags.text_box.text = tags;
+        editor.tags.cursor.position = editor.tags.text_box.text.len();
+        editor.text.text_box.text = text;
+        editor.text.cursor.position = editor.text.text_box.text.len();
+        editor.highlight_focused_box();
+        editor
+    }
+
+    pub fn vim(mut self, enabled: bool) -> Self {
+        self.tags = self.tags.vim(enabled);
+        self.text = self.text.vim(enabled);
+        self
+    }
+
+    /// The box being typed in
+    pub fn focused(&self) -> &InteractiveTextBox {
+        if self.editing_tags {
+            &self.tags
+        } else {
+            &self.text
+        }
+    }
+
+    pub fn handle_key_event(&mut self, key: KeyEvent) {
+        if self.editing_tags {
+            self.tags.handle_key_event(key);
+        } else {
+            self.text.handle_key_event(key);
+        }
+    }
+
+    pub fn run_action(&mut self, action: Action) {
+        if self.editing_tags {
+            self.tags.run_action(action);
+        } else {
+            self.text.run_action(action);
+        }
+    }
+
+    /// Switches between the tags and the text
+    pub fn toggle_field(&mut self) {
+        self.editing_tags = !self.editing_tags;
+        self.highlight_focused_box();
+    }
+
+    fn highlight_focused_box(&mut self) {
+        let (focused, other) = if self.editing_tags {
+            (&mut self.tags, &mut self.text)
+        } else {
+            (&mut self.text, &mut self.tags)
+        };
+        focused.text_box.border_color = Color::Cyan;
+        other.text_box.border_color = Color::default();
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.tags.text_box.text != self.original_tags
+            || self.text.text_box.text != self.original_text
+    }
+
+    /// Puts in tags and text edited elsewhere, which can be undone like typing
+    pub fn set_content(&mut self, tags: &[String], text: String) {
+        self.tags
+            .set_text(tag_words(tags.iter().map(String::as_str)));
+        self.text.set_text(text);
+    }
+
+    /// The tag names, with or without their `#`, each once
+    pub fn tag_names(&self) -> Vec<String> {
+        distinct_tags(
+            self.tags
+                .text_box
+                .text
+                .split_whitespace()
+                .map(|word| word.trim_start_matches('#').to_string()),
+        )
+    }
+}
+
+/// `#idea #work`
+fn tag_words<'a>(names: impl Iterator<Item = &'a str>) -> String {
+    names
+        .map(|name| format!("#{name}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Widget for &NoteEditor {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        // whatever is below would show through where the boxes have no text
+        Clear.render(area, buf);
+
+        let tags_area = Rect::new(area.x, area.y, area.width, 3).clamp(area);
+        self.tags.render(tags_area, buf);
+
+        let text_area = Rect::new(
+            area.x,
+            tags_area.bottom(),
+            area.width,
+            area.height.saturating_sub(tags_area.height),
+        )
+        .clamp(area);
+        self.text.render(text_area, buf);
+    }
+}
@@ -0,0 +1,237 @@
+use crossterm::event::KeyEvent;
+use ratatui::{
+    prelude::{Buffer, Rect, Style, Widget},
+    style::{Color, Modifier},
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph},
+};
+
+use crate::{
+    focus::Focus,
+    keymap::{Action, Keymap},
+    text_box::InteractiveTextBox,
+};
+
+/// An action to run from the palette, with the keys that run it otherwise
+struct Command {
+    action: Action,
+    keys: String,
+}
+
+/// Every action with the focus where it was when it opened, narrowed down by typing part of
+/// its name
+pub struct CommandPalette {
+    pub input: InteractiveTextBox,
+    commands: Vec<Command>,
+    /// Index into [`Self::matches`]
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub fn new(keymap: &Keymap, focus: Focus) -> Self {
+        let mut commands: Vec<Command> = Vec::new();
+        for (context, action) in keymap.actions(focus) {
+            // an action bound in several contexts is listed with the keys of the first
+            if action == Action::CommandPalette
+                || commands.iter().any(|command| command.action == action)
+            {
+                continue;
+            }
+            commands.push(Command {
+                action,
+                keys: keymap.keys(context, action),
+            });
+        }
+
+        Self {
+            input: InteractiveTextBox::default()
+                .title("Run (enter: run, esc: close)".to_string())
+                .border_color(Color::Cyan),
+            commands,
+            selected: 0,
+        }
+    }
+
+    /// The commands matching what's typed, best first
+    fn matches(&self) -> Vec<&Command> {
+        let query = &self.input.text_box.text;
+        let mut matches = self
+            .commands
+            .iter()
+            .filter_map(|command| {
+                let name = command.action.name().replace('_', " ");
+                let score = [
+                    fuzzy_score(query, command.action.description()),
+                    fuzzy_score(query, &name),
+                ]
+                .into_iter()
+                .flatten()
+                .min()?;
+                Some((score, command))
+            })
+            .collect::<Vec<_>>();
+        // stable, so equally good matches keep the keymap's order
+        matches.sort_by_key(|&(score, _)| score);
+        matches.into_iter().map(|(_, command)| command).collect()
+    }
+
+    pub fn selected(&self) -> Option<Action> {
+        self.matches()
+            .get(self.selected)
+            .map(|command| command.action)
+    }
+
+    pub fn move_selection(&mut self, direction_down: bool) {
+        let last = self.matches().len().saturating_sub(1);
+        self.selected = if direction_down {
+            (self.selected + 1).min(last)
+        } else {
+            self.selected.saturating_sub(1)
+        };
+    }
+
+    pub fn handle_key_event(&mut self, key: KeyEvent) {
+        let query = self.input.text_box.text.clone();
+        self.input.handle_key_event(key);
+        if self.input.text_box.text != query {
+            self.selected = 0;
+        }
+    }
+}
+
+/// How well `query` matches `text`, lower being better, if its characters all appear in
+/// `text` in order, ignoring case and the spaces in `query`
+///
+/// The score is how far into `text` the match starts plus how spread out it is, so `dn`
+/// matches `Delete the note` but `Delete` and `delete note` come first.
+fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let text = text.to_lowercase().chars().collect::<Vec<_>>();
+    let mut positions = Vec::new();
+    let mut next = 0;
+    for char in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = next + text[next..].iter().position(|&c| c == char)?;
+        positions.push(found);
+        next = found + 1;
+    }
+
+    let (Some(&first), Some(&last)) = (positions.first(), positions.last()) else {
+        return Some(0);
+    };
+    Some(first + (last - first + 1 - positions.len()))
+}
+
+impl Widget for &CommandPalette {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+
+        let input_area = Rect::new(area.x, area.y, area.width, 3).clamp(area);
+        self.input.render(input_area, buf);
+
+        let list_area = Rect::new(
+            area.x,
+            input_area.bottom(),
+            area.width,
+            area.height.saturating_sub(input_area.height),
+        )
+        .clamp(area);
+        let block = Block::bordered().border_style(Style::default().fg(Color::Cyan));
+        let visible = block.inner(list_area).height as usize;
+
+        let matches = self.matches();
+        // keeps the selection in view
+        let first = self.selected.saturating_sub(visible.saturating_sub(1));
+        let width = block.inner(list_area).width as usize;
+        let lines = matches
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(visible)
+            .map(|(i, command)| {
+                let description = command.action.description();
+                let padding = width.saturating_sub(description.len() + command.keys.len() + 2);
+                let line = Line::from(vec![
+                    Span::raw(format!(" {description}{}", " ".repeat(padding))),
+                    Span::styled(
+                        format!("{} ", command.keys),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]);
+                if i == self.selected {
+                    line.style(Style::default().add_modifier(Modifier::REVERSED))
+                } else {
+                    line
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let lines = if lines.is_empty() {
+            vec![Line::styled(
+                " no matching command",
+                Style::default().fg(Color::DarkGray),
+            )]
+        } else {
+            lines
+        };
+        Paragraph::new(lines).block(block).render(list_area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CommandPalette, fuzzy_score};
+    use crate::{
+        focus::Focus,
+        keymap::{Action, Keymap},
+    };
+
+    #[test]
+    fn fuzzy_matches_keep_their_order() {
+        assert_eq!(fuzzy_score("", "Quit"), Some(0));
+        assert_eq!(fuzzy_score("QUIT", "Quit"), Some(0));
+        assert_eq!(fuzzy_score("qt", "Quit"), Some(2));
+        assert_eq!(fuzzy_score("tq", "Quit"), None);
+        assert_eq!(fuzzy_score("x", "Quit"), None);
+        assert!(fuzzy_score("del note", "Delete the note") < fuzzy_score("dn", "Delete the note"));
+    }
+
+    #[test]
+    fn typing_narrows_down_the_commands() {
+        let keymap = Keymap::default();
+        let mut palette = CommandPalette::new(&keymap, Focus::Search);
+        let actions = palette
+            .matches()
+            .iter()
+            .map(|command| command.action)
+            .collect::<Vec<_>>();
+        assert!(actions.contains(&Action::NewNote));
+        assert!(actions.contains(&Action::Undo));
+        assert!(!actions.contains(&Action::CommandPalette));
+        // only with the focus on the reminders
+        assert!(!actions.contains(&Action::Acknowledge));
+
+        palette.input.set_text("new note".to_string());
+        assert_eq!(palette.selected(), Some(Action::NewNote));
+
+        palette.input.set_text("markdwn".to_string());
+        assert_eq!(palette.selected(), Some(Action::ToggleMarkdown));
+
+        palette.input.set_text("nothing like it".to_string());
+        assert_eq!(palette.selected(), None);
+    }
+
+    #[test]
+    fn commands_show_their_keys() {
+        let keymap = Keymap::default();
+        let palette = CommandPalette::new(&keymap, Focus::EditNote);
+        let keys = |action| {
+            palette
+                .commands
+                .iter()
+                .find(|command| command.action == action)
+                .map(|command| command.keys.as_str())
+        };
+        assert_eq!(keys(Action::Save), Some("ctrl+s"));
+        assert_eq!(keys(Action::Redo), Some("ctrl+y/ctrl+shift+z"));
+        assert_eq!(keys(Action::Quit), Some("ctrl+q"));
+    }
+}
@@ -0,0 +1,118 @@
+use ratatui::{
+    prelude::{Buffer, Rect, Style, Widget},
+    style::Color,
+    widgets::{Block, Paragraph},
+};
+use tokio::time::{Duration, Instant};
+
+use rust_shared::response::Reminder;
+
+// how long the banner for a pushed reminder stays up
+const BANNER_DURATION: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+pub struct RemindersBox {
+    pub reminders: Vec<Reminder>,
+    pub selected_index: usize,
+    banner: Option<(String, Instant)>,
+    /// Keys for the title
+    hints: String,
+    /// Key that opens the reminders, for the banner
+    open_key: String,
+}
+
+impl RemindersBox {
+    pub fn hints(mut self, hints: String, open_key: String) -> Self {
+        self.hints = hints;
+        self.open_key = open_key;
+        self
+    }
+
+    pub fn set_reminders(&mut self, reminders: Vec<Reminder>) {
+        self.reminders = reminders;
+        self.selected_index = self
+            .selected_index
+            .min(self.reminders.len().saturating_sub(1));
+    }
+
+    /// A reminder the backend pushed because it just came due
+    pub fn notify(&mut self, reminder: Reminder) {
+        self.banner = Some((
+            format!(
+                "reminder: {} ({} to see all)",
+                summary(&reminder),
+                self.open_key
+            ),
+            Instant::now() + BANNER_DURATION,
+        ));
+
+        // each occurrence of a recurring reminder is one of its own
+        match self
+            .reminders
+            .iter_mut()
+            .find(|due| due.id == reminder.id && due.date == reminder.date)
+        {
+            Some(due) => *due = reminder,
+            None => self.reminders.push(reminder),
+        }
+    }
+
+    pub fn selected(&self) -> Option<&Reminder> {
+        self.reminders.get(self.selected_index)
+    }
+
+    pub fn move_selection(&mut self, direction_down: bool) {
+        let last = self.reminders.len().saturating_sub(1);
+        self.selected_index = if direction_down {
+            (self.selected_index + 1).min(last)
+        } else {
+            self.selected_index.saturating_sub(1)
+        };
+    }
+
+    pub fn banner(&self) -> Option<&str> {
+        self.banner
+            .as_ref()
+            .filter(|(_, until)| Instant::now() < *until)
+            .map(|(text, _)| text.as_str())
+    }
+}
+
+/// `2025-08-08 reminder: title of the note`
+fn summary(reminder: &Reminder) -> String {
+    let title = reminder.note.display_title();
+    match &reminder.label {
+        Some(label) => format!("{} {label}: {title}", reminder.date),
+        None => format!("{} {title}", reminder.date),
+    }
+}
+
+impl Widget for &RemindersBox {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::bordered()
+            .title(format!("Reminders ({})", self.hints))
+            .border_style(Style::default().fg(Color::Magenta));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        if self.reminders.is_empty() {
+            Paragraph::new("nothing due").render(inner, buf);
+            return;
+        }
+
+        self.reminders
+            .iter()
+            .enumerate()
+            .take(inner.height as usize)
+            .for_each(|(i, reminder)| {
+                let style = if i == self.selected_index {
+                    Style::default().bg(Color::LightGreen)
+                } else {
+                    Style::default()
+                };
+
+                let line = Paragraph::new(summary(reminder)).style(style);
+                line.render(Rect::new(inner.x, inner.y + i as u16, inner.width, 1), buf)
+            });
+    }
+}
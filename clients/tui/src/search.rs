@@ -6,9 +6,12 @@ use ratatui::{
     widgets::{Block, Paragraph},
 };
 use std::cmp::{max, min};
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
 
 use rust_shared::response::Note as SharedNote;
 
+use crate::keymap::Action;
 use crate::text_box::InteractiveTextBox;
 
 pub struct Note(SharedNote);
@@ -18,8 +21,12 @@ impl Note {
         Self(note)
     }
 
-    pub fn text(&self) -> String {
-        self.0.text.clone()
+    pub fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    pub fn title(&self) -> String {
+        self.0.display_title()
     }
 }
 
@@ -46,9 +53,27 @@ impl Default for SearchBox {
 }
 
 impl SearchBox {
+    pub fn title(mut self, title: String) -> Self {
+        self.text_box = self.text_box.title(title);
+        self
+    }
+
+    pub fn vim(mut self, enabled: bool) -> Self {
+        self.text_box = self.text_box.vim(enabled);
+        self
+    }
+
     pub fn handle_key_event(&mut self, key: KeyEvent) {
         self.text_box.handle_key_event(key);
+        self.parse();
+    }
+
+    pub fn run_action(&mut self, action: Action) {
+        self.text_box.run_action(action);
+        self.parse();
+    }
 
+    fn parse(&mut self) {
         let (tags, search_text) = parse_search_text(&self.text_box.text_box.text);
         self.parsed_tags = tags;
         self.parsed_search_text = search_text;
@@ -57,19 +82,33 @@ impl SearchBox {
 
 /// Parse search text into tags and search text
 /// Tags are prefixed with #, any whitespace separated, and precede the search text
-/// The first word that doesn't start with # marks the end of the tags
+/// The first word that doesn't start with # marks the end of the tags, as does a Markdown
+/// heading's "#" or "##"
 /// Example: "#tag1   #tag2 search text" -> tags = ["tag1", "tag2"], search_text = "search text"
-fn parse_search_text(text: &str) -> (Vec<String>, Option<String>) {
+/// Also splits a new note's text from its tags
+pub fn parse_search_text(text: &str) -> (Vec<String>, Option<String>) {
     let mut tags = Vec::new();
     let mut search_start = None;
 
     let mut tag_start = None;
     for (i, char) in text.char_indices() {
         if char == '#' {
+            if tag_start == Some(i) {
+                // "##", a heading
+                tag_start = None;
+                search_start = Some(i - 1);
+                break;
+            }
             // tag found
             tag_start = Some(i + 1);
         } else if char.is_whitespace() {
             if let Some(tag_start_val) = tag_start {
+                if tag_start_val == i {
+                    // a lone "#", a heading
+                    tag_start = None;
+                    search_start = Some(i - 1);
+                    break;
+                }
                 // end of this tag
                 tags.push(text[tag_start_val..i].to_string());
                 tag_start = None;
@@ -90,6 +129,18 @@ fn parse_search_text(text: &str) -> (Vec<String>, Option<String>) {
     (tags, search_start.map(|val| text[val..].to_string()))
 }
 
+/// Tag names without blanks, such as a lone "#" typed while writing them, and without repeats,
+/// in the order first given
+pub fn distinct_tags(tags: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut distinct: Vec<String> = Vec::new();
+    for tag in tags {
+        if !tag.is_empty() && !distinct.contains(&tag) {
+            distinct.push(tag);
+        }
+    }
+    distinct
+}
+
 impl Widget for &SearchBox {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let default_style = Style::default();
@@ -109,11 +160,12 @@ impl Widget for &SearchBox {
         let mut spans = Vec::new();
         let mut num_words_seen = 0;
         let mut last_char_was_space = true;
-        for (i, c) in self.text_box.text_box.text.chars().enumerate() {
-            if !c.is_whitespace() && last_char_was_space {
+        for (i, grapheme) in self.text_box.text_box.text.grapheme_indices(true) {
+            let is_whitespace = grapheme.chars().all(char::is_whitespace);
+            if !is_whitespace && last_char_was_space {
                 num_words_seen += 1;
             }
-            last_char_was_space = c.is_whitespace();
+            last_char_was_space = is_whitespace;
 
             let fg_color = if num_words_seen > self.parsed_tags.len() {
                 text_color
@@ -130,7 +182,7 @@ impl Widget for &SearchBox {
                     cursor_color
                 };
                 spans.push(Span::styled(
-                    c.to_string(),
+                    grapheme.to_string(),
                     default_style.bg(color).fg(fg_color),
                 ));
             } else if let Some((selection_left, selection_right)) = selection_range
@@ -138,11 +190,14 @@ impl Widget for &SearchBox {
                 && i < selection_right
             {
                 spans.push(Span::styled(
-                    c.to_string(),
+                    grapheme.to_string(),
                     default_style.bg(selection_color).fg(fg_color),
                 ));
             } else {
-                spans.push(Span::styled(c.to_string(), default_style.fg(fg_color)));
+                spans.push(Span::styled(
+                    grapheme.to_string(),
+                    default_style.fg(fg_color),
+                ));
             }
         }
 
@@ -151,9 +206,13 @@ impl Widget for &SearchBox {
             spans.push(Span::styled(" ", default_style.bg(cursor_color)));
         }
 
-        let text_box = Paragraph::new(Line::from(spans))
-            .block(Block::bordered().title(self.text_box.text_box.title.clone()));
-        text_box.render(area, buf);
+        let mut block = Block::bordered().title(self.text_box.text_box.title.clone());
+        if let Some(vim) = &self.text_box.vim {
+            block = block.title_bottom(vim.mode_line());
+        }
+        Paragraph::new(Line::from(spans))
+            .block(block)
+            .render(area, buf);
     }
 }
 
@@ -169,6 +228,16 @@ impl SearchResultsBox {
         self.selected_index = 0;
     }
 
+    pub fn selected_id(&self) -> Option<Uuid> {
+        self.search_results.get(self.selected_index).map(Note::id)
+    }
+
+    pub fn selected_title(&self) -> Option<String> {
+        self.search_results
+            .get(self.selected_index)
+            .map(Note::title)
+    }
+
     #[allow(clippy::collapsible_else_if)]
     pub fn move_selection(&mut self, direction_down: bool) {
         if self.search_results.is_empty() {
@@ -205,7 +274,7 @@ impl Widget for &SearchResultsBox {
                     Style::default()
                 };
 
-                let line = Paragraph::new(note.text()).style(style);
+                let line = Paragraph::new(note.title()).style(style);
                 line.render(Rect::new(area.x, area.y + i as u16, area.width, 1), buf)
             });
     }
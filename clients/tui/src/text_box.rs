@@ -5,14 +5,29 @@ use ratatui::{
     text::{Line, Span},
     widgets::{Block, Paragraph, Wrap},
 };
-use std::cmp::{max, min};
+use std::{
+    cell::Cell,
+    cmp::{max, min},
+    ops::Range,
+};
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
+use unicode_width::UnicodeWidthStr;
 
-use crate::clipboard;
+use crate::{
+    clipboard,
+    history::{EditKind, History},
+    keymap::Action,
+    vim::{self, Command, InsertAt, Mode, Operator, Register, Vim},
+};
 
+/// Positions are byte offsets into the text, always at a grapheme cluster's boundary
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Cursor {
     pub position: usize,
     pub selection_anchor: Option<usize>,
+    /// Column that moving up and down keeps to, through lines too short for it, in terminal
+    /// cells
+    pub preferred_column: Option<usize>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -26,6 +41,15 @@ pub struct TextBox {
 pub struct InteractiveTextBox {
     pub text_box: TextBox,
     pub cursor: Cursor,
+    /// Enter starts a new line, and up and down move between lines
+    pub multiline: bool,
+    /// Width the text wrapped at and height of the last render, for moving by lines and pages
+    viewport: Cell<(usize, usize)>,
+    /// First line shown, kept across renders so the text only scrolls to follow the cursor
+    scroll: Cell<usize>,
+    history: History,
+    /// Modal editing, if turned on
+    pub vim: Option<Vim>,
 }
 
 impl TextBox {
@@ -56,38 +80,400 @@ impl InteractiveTextBox {
         self
     }
 
+    pub fn multiline(mut self, multiline: bool) -> Self {
+        self.multiline = multiline;
+        self
+    }
+
+    pub fn vim(mut self, enabled: bool) -> Self {
+        self.vim = enabled.then(Vim::default);
+        self
+    }
+
+    pub fn clear(&mut self) {
+        self.text_box.text.clear();
+        self.cursor = Cursor::default();
+        self.history.clear();
+        if let Some(vim) = &mut self.vim {
+            *vim = Vim::default();
+        }
+    }
+
+    /// Replaces the whole text as one step to undo, the cursor at its end
+    pub fn set_text(&mut self, text: String) {
+        let end = text.len();
+        let all = 0..self.text_box.text.len();
+        self.edit(all, &text, end);
+        self.keep_vim_cursor();
+    }
+
+    /// Whether esc is for the box, to leave insert or visual mode or drop a half typed command,
+    /// rather than for closing it
+    pub fn takes_esc(&self) -> bool {
+        self.vim
+            .as_ref()
+            .is_some_and(|vim| vim.mode != Mode::Normal || !vim.pending.is_empty())
+    }
+
+    /// In vim's normal mode with no command started, where letters aren't typed
+    pub fn is_vim_normal(&self) -> bool {
+        self.vim
+            .as_ref()
+            .is_some_and(|vim| vim.mode == Mode::Normal && vim.pending.is_empty())
+    }
+
     pub fn handle_key_event(&mut self, key: KeyEvent) {
-        match key.code {
-            KeyCode::Char(char) => {
-                if self.handle_shortcut(char, key.modifiers) {
-                    return;
+        if self.handle_vim_key(key) {
+            return;
+        }
+        self.record_edit(EditKind::of(key), |text_box| text_box.apply_key_event(key));
+    }
+
+    /// Runs one of the keymap's `text_box` actions
+    pub fn run_action(&mut self, action: Action) {
+        match action {
+            Action::Undo => {
+                self.history.undo(&mut self.text_box.text, &mut self.cursor);
+            }
+            Action::Redo => {
+                self.history.redo(&mut self.text_box.text, &mut self.cursor);
+            }
+            action => self.record_edit(EditKind::Other, |text_box| text_box.apply_shortcut(action)),
+        }
+        self.keep_vim_cursor();
+    }
+
+    /// Runs `edit`, to be undone if it changed the text
+    fn record_edit(&mut self, kind: EditKind, edit: impl FnOnce(&mut Self)) {
+        let text_before = self.text_box.text.clone();
+        let cursor_before = self.cursor;
+        edit(self);
+
+        // an edit can join the cursor's neighbors into one grapheme, e.g. a combining mark after
+        // a deleted newline, so the cursor moves past it
+        let text = &self.text_box.text;
+        self.cursor.position = grapheme_boundary(text, self.cursor.position);
+        self.cursor.selection_anchor = self
+            .cursor
+            .selection_anchor
+            .map(|anchor| grapheme_boundary(text, anchor));
+
+        if *text == text_before {
+            self.history.break_run();
+        } else {
+            let position = self.cursor.position;
+            self.history
+                .record(text_before, cursor_before, kind, position);
+        }
+        self.keep_vim_cursor();
+    }
+
+    /// Runs the key as a vim command, unless vim is off or in insert mode, where keys type as
+    /// usual
+    fn handle_vim_key(&mut self, key: KeyEvent) -> bool {
+        let Some(vim) = &mut self.vim else {
+            return false;
+        };
+
+        if vim.mode == Mode::Insert {
+            if key.code != KeyCode::Esc {
+                return false;
+            }
+            // like vim, back onto the last grapheme typed
+            vim.mode = Mode::Normal;
+            let text = &self.text_box.text;
+            if self.cursor.position > vim::line_start(text, self.cursor.position) {
+                self.cursor.position = previous_grapheme(text, self.cursor.position);
+            }
+            self.cursor.selection_anchor = None;
+            self.history.break_run();
+            return true;
+        }
+
+        // control keys edit as they do outside vim, ctrl+arrows still moving by word
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            return false;
+        }
+
+        let key = match key.code {
+            KeyCode::Char(char) => char,
+            KeyCode::Left | KeyCode::Backspace => 'h',
+            KeyCode::Right => 'l',
+            KeyCode::Up => 'k',
+            KeyCode::Down => 'j',
+            KeyCode::Home => '0',
+            KeyCode::End => '$',
+            KeyCode::Esc => {
+                // drops a half typed command, or leaves visual mode
+                if vim.pending.is_empty() {
+                    vim.mode = Mode::Normal;
+                }
+                vim.pending.clear();
+                self.keep_vim_cursor();
+                return true;
+            }
+            _ => return true,
+        };
+        if let Some(command) = vim.push_key(key) {
+            self.run_vim_command(command);
+        }
+        self.keep_vim_cursor();
+        true
+    }
+
+    fn run_vim_command(&mut self, command: Command) {
+        let text = &self.text_box.text;
+        let position = self.cursor.position;
+        let register = command.register;
+
+        match command.action {
+            vim::Action::Move(motion) => {
+                self.cursor.position = motion.target(text, position, command.count);
+            }
+            vim::Action::Operate(operator, motion) => {
+                let range = motion.range(text, position, command.count, operator);
+                self.operate(operator, range, motion.is_linewise(), register);
+            }
+            vim::Action::OperateLines(operator) => {
+                let last = vim::count_lines(text, position, command.times());
+                let range = vim::lines_range(text, position, last);
+                self.operate(operator, range, true, register);
+            }
+            vim::Action::OperateSelection(operator) => {
+                let anchor = self.vim.as_ref().map_or(position, |vim| vim.visual_anchor);
+                let end = next_grapheme(text, max(anchor, position));
+                self.set_vim_mode(Mode::Normal);
+                self.operate(operator, min(anchor, position)..end, false, register);
+            }
+            vim::Action::DeleteChar => {
+                let line_end = vim::line_end(text, position);
+                let end = (0..command.times())
+                    .fold(position, |end, _| next_grapheme(text, end).min(line_end));
+                self.operate(Operator::Delete, position..end, false, register);
+            }
+            vim::Action::Put { before } => self.put(vim::read_register(register), before, command),
+            vim::Action::Insert(at) => self.start_insert(at),
+            vim::Action::Visual => {
+                let mode = match self.vim.as_ref().map(|vim| vim.mode) {
+                    Some(Mode::Visual) => Mode::Normal,
+                    _ => Mode::Visual,
+                };
+                self.set_vim_mode(mode);
+                if let Some(vim) = &mut self.vim {
+                    vim.visual_anchor = position;
+                }
+            }
+            vim::Action::Undo => {
+                for _ in 0..command.times() {
+                    if !self.history.undo(&mut self.text_box.text, &mut self.cursor) {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Deletes, changes or yanks `range`, which goes into the register either way
+    fn operate(
+        &mut self,
+        operator: Operator,
+        range: Range<usize>,
+        linewise: bool,
+        register: Option<char>,
+    ) {
+        if range.is_empty() && !linewise {
+            return;
+        }
+
+        let taken = &self.text_box.text[range.clone()];
+        let text = if linewise {
+            format!("{taken}\n")
+        } else {
+            taken.to_string()
+        };
+        vim::write_register(register, Register { text, linewise });
+
+        // deleted lines take their line break along, the one before the last line if need be
+        let mut range = range;
+        if linewise && operator == Operator::Delete {
+            if range.end < self.text_box.text.len() {
+                range.end += 1;
+            } else {
+                range.start = range.start.saturating_sub(1);
+            }
+        }
+
+        match operator {
+            Operator::Yank if linewise => {}
+            Operator::Yank => self.cursor.position = range.start,
+            Operator::Delete => {
+                let start = range.start;
+                self.edit(range, "", start);
+                if linewise {
+                    let text = &self.text_box.text;
+                    self.cursor.position = vim::first_non_blank(text, min(start, text.len()));
                 }
+            }
+            Operator::Change => {
+                let start = range.start;
+                self.history.start_group();
+                self.edit(range, "", start);
+                self.set_vim_mode(Mode::Insert);
+            }
+        }
+    }
+
+    /// `p` and `P`, lines below or above the cursor's line and text after or before the cursor
+    fn put(&mut self, register: Register, before: bool, command: Command) {
+        if register.text.is_empty() {
+            return;
+        }
+        let text = &self.text_box.text;
+        let position = self.cursor.position;
+        let repeated = register.text.repeat(command.times());
+
+        if register.linewise {
+            let line_end = vim::line_end(text, position);
+            let (at, inserted) = match (before, line_end < text.len()) {
+                (true, _) => (vim::line_start(text, position), repeated),
+                (false, true) => (line_end + 1, repeated),
+                // below the last line, which has no line break to put it after
+                (false, false) => (text.len(), format!("\n{}", repeated.trim_end_matches('\n'))),
+            };
+            let start = if inserted.starts_with('\n') {
+                at + 1
+            } else {
+                at
+            };
+            self.edit(at..at, &inserted, start);
+            return;
+        }
+
+        let at = if before || position == vim::line_end(text, position) {
+            position
+        } else {
+            next_grapheme(text, position)
+        };
+        // onto the last grapheme put
+        let end = at + repeated.len();
+        self.edit(at..at, &repeated, end);
+        let last = previous_grapheme(&self.text_box.text, end);
+        self.cursor.position = last;
+    }
+
+    /// `i`, `a`, `I`, `A`, `o` and `O`, where only boxes with several lines open new ones
+    fn start_insert(&mut self, at: InsertAt) {
+        let text = &self.text_box.text;
+        let position = self.cursor.position;
+        let line_end = vim::line_end(text, position);
 
+        // `o` and what's typed after it undo as one
+        self.history.start_group();
+        match at {
+            InsertAt::Cursor => {}
+            InsertAt::AfterCursor => {
+                self.cursor.position = next_grapheme(text, position).min(line_end);
+            }
+            InsertAt::LineStart => self.cursor.position = vim::first_non_blank(text, position),
+            InsertAt::LineBelow if self.multiline => {
+                self.edit(line_end..line_end, "\n", line_end + 1)
+            }
+            InsertAt::LineAbove if self.multiline => {
+                let line_start = vim::line_start(text, position);
+                self.edit(line_start..line_start, "\n", line_start);
+            }
+            InsertAt::LineEnd | InsertAt::LineBelow | InsertAt::LineAbove => {
+                self.cursor.position = line_end;
+            }
+        }
+        self.set_vim_mode(Mode::Insert);
+    }
+
+    fn set_vim_mode(&mut self, mode: Mode) {
+        if let Some(vim) = &mut self.vim {
+            vim.mode = mode;
+        }
+    }
+
+    /// Replaces `range` as one step to undo, leaving the cursor at `position`
+    fn edit(&mut self, range: Range<usize>, replacement: &str, position: usize) {
+        let text_before = self.text_box.text.clone();
+        let cursor_before = self.cursor;
+
+        self.text_box.text.replace_range(range, replacement);
+        self.cursor.position = position;
+        self.cursor.selection_anchor = None;
+
+        if self.text_box.text != text_before {
+            self.history
+                .record(text_before, cursor_before, EditKind::Other, position);
+        }
+    }
+
+    /// Keeps normal mode's cursor on a grapheme, and shows visual mode's selection
+    fn keep_vim_cursor(&mut self) {
+        let Some(vim) = &self.vim else {
+            return;
+        };
+        let text = &self.text_box.text;
+        let position = min(self.cursor.position, text.len());
+
+        match vim.mode {
+            Mode::Insert => {}
+            Mode::Normal => {
+                self.cursor.position = vim::normal_position(text, position);
+                self.cursor.selection_anchor = None;
+            }
+            Mode::Visual => {
+                let anchor = min(vim.visual_anchor, text.len());
+                // the selection shows up to the cursor, so past the anchor when it's behind
+                self.cursor.selection_anchor = Some(if position < anchor {
+                    next_grapheme(text, anchor)
+                } else {
+                    anchor
+                });
+            }
+        }
+    }
+
+    fn apply_key_event(&mut self, key: KeyEvent) {
+        let vertical = matches!(
+            key.code,
+            KeyCode::Up | KeyCode::Down | KeyCode::PageUp | KeyCode::PageDown
+        );
+        if !vertical {
+            self.cursor.preferred_column = None;
+        }
+
+        match key.code {
+            // a shortcut the keymap doesn't know, which types nothing, unless it's alt gr
+            KeyCode::Char(_)
+                if key.modifiers.contains(KeyModifiers::CONTROL)
+                    && !key.modifiers.contains(KeyModifiers::ALT) => {}
+            KeyCode::Char(char) => {
                 // add char to search text at cursor position
                 self.delete_selection();
                 self.text_box.text.insert(self.cursor.position, char);
-                self.cursor.position += 1;
+                self.cursor.position += char.len_utf8();
             }
             KeyCode::Backspace => {
-                // delete selection if there is one, else delete char to the left
+                // delete selection if there is one, else delete the grapheme to the left
                 if self.delete_selection() {
                     return;
                 }
 
-                if self.cursor.position > 0 {
-                    self.text_box.text.remove(self.cursor.position - 1);
-                    self.cursor.position -= 1;
-                }
+                let start = previous_grapheme(&self.text_box.text, self.cursor.position);
+                self.text_box.text.drain(start..self.cursor.position);
+                self.cursor.position = start;
             }
             KeyCode::Delete => {
-                // delete selection if there is one, else delete char to the right
+                // delete selection if there is one, else delete the grapheme to the right
                 if self.delete_selection() {
                     return;
                 }
 
-                if self.cursor.position < self.text_box.text.len() {
-                    self.text_box.text.remove(self.cursor.position);
-                }
+                let end = next_grapheme(&self.text_box.text, self.cursor.position);
+                self.text_box.text.drain(self.cursor.position..end);
             }
             KeyCode::Left => {
                 self.handle_horizontal_move(false, key.modifiers);
@@ -95,35 +481,107 @@ impl InteractiveTextBox {
             KeyCode::Right => {
                 self.handle_horizontal_move(true, key.modifiers);
             }
+            KeyCode::Enter if self.multiline => {
+                self.delete_selection();
+                self.text_box.text.insert(self.cursor.position, '\n');
+                self.cursor.position += 1;
+            }
+            KeyCode::Up | KeyCode::Down if self.multiline => {
+                self.handle_vertical_move(key.code == KeyCode::Down, 1, key.modifiers);
+            }
+            KeyCode::PageUp | KeyCode::PageDown if self.multiline => {
+                let page = self.viewport.get().1.max(1);
+                self.handle_vertical_move(key.code == KeyCode::PageDown, page, key.modifiers);
+            }
+            KeyCode::Home => self.handle_line_bound_move(false, key.modifiers),
+            KeyCode::End => self.handle_line_bound_move(true, key.modifiers),
             _ => {}
         }
     }
 
-    fn handle_shortcut(&mut self, char: char, modifiers: KeyModifiers) -> bool {
-        if !modifiers.contains(KeyModifiers::CONTROL) {
-            return false;
+    /// Moves `lines` lines up or down as the text is shown, wrapped lines included, keeping to
+    /// the column the cursor started out on
+    fn handle_vertical_move(
+        &mut self,
+        direction_down: bool,
+        lines: usize,
+        modifiers: KeyModifiers,
+    ) {
+        let text = &self.text_box.text;
+        let wrapped = wrap_lines(text, self.viewport.get().0);
+        let current = line_index(&wrapped, self.cursor.position);
+
+        let column = self
+            .cursor
+            .preferred_column
+            .unwrap_or_else(|| text[wrapped[current].start..self.cursor.position].width());
+        let target = if direction_down {
+            min(current + lines, wrapped.len() - 1)
+        } else {
+            current.saturating_sub(lines)
+        };
+
+        // past the first or last line, like most editors, go to the very start or end
+        let position = match (target == current, direction_down) {
+            (true, true) => text.len(),
+            (true, false) => 0,
+            (false, _) => position_in_line(text, &wrapped, target, column),
+        };
+        self.move_cursor(position, modifiers.contains(KeyModifiers::SHIFT));
+        self.cursor.preferred_column = Some(column);
+    }
+
+    /// Home and end, to the start or end of the line as written rather than as wrapped
+    fn handle_line_bound_move(&mut self, direction_right: bool, modifiers: KeyModifiers) {
+        let text = &self.text_box.text;
+        let position = self.cursor.position;
+        let position = if direction_right {
+            text[position..]
+                .find('\n')
+                .map_or(text.len(), |offset| position + offset)
+        } else {
+            text[..position].rfind('\n').map_or(0, |offset| offset + 1)
+        };
+        self.move_cursor(position, modifiers.contains(KeyModifiers::SHIFT));
+    }
+
+    /// Moves the cursor, extending the selection if `selecting` and dropping it otherwise
+    fn move_cursor(&mut self, position: usize, selecting: bool) {
+        if !selecting {
+            self.cursor.selection_anchor = None;
+        } else if self.cursor.selection_anchor.is_none() {
+            self.cursor.selection_anchor = Some(self.cursor.position);
+        }
+
+        self.cursor.position = position;
+
+        // back at the anchor, nothing is selected
+        if self.cursor.selection_anchor == Some(position) {
+            self.cursor.selection_anchor = None;
         }
+    }
 
-        match char {
-            'a' => {
+    fn apply_shortcut(&mut self, action: Action) {
+        match action {
+            Action::SelectAll => {
                 // select all
                 self.cursor.selection_anchor = Some(0);
                 self.cursor.position = self.text_box.text.len();
             }
-            'c' => {
+            Action::Copy => {
                 // copy selection to clipboard
                 if let Some(selection) = self.get_selection() {
                     clipboard::set_text(selection);
                 }
             }
-            'x' => {
+            Action::Cut => {
                 // cut selection to clipboard
                 if let Some(selection) = self.get_selection() {
                     clipboard::set_text(selection);
                     self.delete_selection();
                 }
             }
-            'v' => {
+            Action::Paste => {
                 // paste from clipboard
                 self.delete_selection();
                 let clipboard_text = clipboard::get_text();
@@ -132,10 +590,8 @@ impl InteractiveTextBox {
                     .insert_str(self.cursor.position, &clipboard_text);
                 self.cursor.position += clipboard_text.len();
             }
-            _ => return false,
+            _ => {}
         }
-
-        true
     }
 
     fn handle_horizontal_move(&mut self, direction_right: bool, modifiers: KeyModifiers) {
@@ -166,13 +622,14 @@ impl InteractiveTextBox {
             return;
         }
 
-        // move by word or char
+        // move by word or grapheme
+        let text = &self.text_box.text;
         self.cursor.position = if modifiers.contains(KeyModifiers::CONTROL) {
-            get_next_word_bound(&self.text_box.text, self.cursor.position, direction_right)
+            get_next_word_bound(text, self.cursor.position, direction_right)
         } else if direction_right {
-            self.cursor.position + 1
+            next_grapheme(text, self.cursor.position)
         } else {
-            self.cursor.position - 1
+            previous_grapheme(text, self.cursor.position)
         };
 
         // if we returned to the anchor while selecting, stop selecting
@@ -210,30 +667,70 @@ impl InteractiveTextBox {
     }
 }
 
-fn get_next_word_bound(text: &str, mut cursor: usize, direction_right: bool) -> usize {
-    let text = text.as_bytes();
+/// Boundary of the grapheme before `position`, or 0 at the start
+pub fn previous_grapheme(text: &str, position: usize) -> usize {
+    GraphemeCursor::new(position, text.len(), true)
+        .prev_boundary(text, 0)
+        .ok()
+        .flatten()
+        .unwrap_or(0)
+}
+
+/// Boundary of the grapheme after `position`, or the text's length at the end
+pub fn next_grapheme(text: &str, position: usize) -> usize {
+    GraphemeCursor::new(position, text.len(), true)
+        .next_boundary(text, 0)
+        .ok()
+        .flatten()
+        .unwrap_or(text.len())
+}
+
+/// `position` if it's a grapheme boundary, else the end of the grapheme it's in
+fn grapheme_boundary(text: &str, position: usize) -> usize {
+    let position = min(position, text.len());
+    let mut cursor = GraphemeCursor::new(position, text.len(), true);
+    if cursor.is_boundary(text, 0).unwrap_or(true) {
+        position
+    } else {
+        next_grapheme(text, position)
+    }
+}
+
+fn get_next_word_bound(text: &str, cursor: usize, direction_right: bool) -> usize {
+    // work on graphemes, so the cursor never lands inside one
+    let graphemes = text
+        .grapheme_indices(true)
+        .map(|(offset, grapheme)| (offset, grapheme.chars().next().is_some_and(is_word_char)))
+        .collect::<Vec<_>>();
+    let is_word_char = |index: usize| graphemes[index].1;
+    let offset = |index: usize| {
+        graphemes
+            .get(index)
+            .map_or(text.len(), |&(offset, _)| offset)
+    };
+
+    let mut cursor = graphemes.partition_point(|&(offset, _)| offset < cursor);
     let direction_left = !direction_right;
 
     // limit check
     if direction_left && cursor == 0 {
         return 0;
     }
-    if direction_right && cursor >= text.len() {
+    if direction_right && cursor >= graphemes.len() {
         return text.len();
     }
 
     // classify current position
     // if moving right, and we're at a word bound, we're already in the next block
     // if moving left, and we're at a word bound, we're still in the current block
-    let cur_char_type_is_word = (direction_right && is_word_char(text[cursor] as char))
-        || (direction_left && is_word_char(text[cursor - 1] as char));
+    let cur_char_type_is_word =
+        (direction_right && is_word_char(cursor)) || (direction_left && is_word_char(cursor - 1));
 
     // move cursor to next word bound
-    let is_word_char = |c: u8| is_word_char(c as char);
-    while (direction_left && cursor > 0 && cur_char_type_is_word == is_word_char(text[cursor - 1]))
+    while (direction_left && cursor > 0 && cur_char_type_is_word == is_word_char(cursor - 1))
         || (direction_right
-            && cursor < text.len()
-            && cur_char_type_is_word == is_word_char(text[cursor]))
+            && cursor < graphemes.len()
+            && cur_char_type_is_word == is_word_char(cursor))
     {
         if direction_left {
             cursor -= 1;
@@ -245,15 +742,15 @@ fn get_next_word_bound(text: &str, mut cursor: usize, direction_right: bool) ->
     // cursor should end up at the word side of the bound
     // if we started on the word side, only need to move the cursor once
     if cur_char_type_is_word {
-        return cursor;
+        return offset(cursor);
     }
 
     // move cursor to next word bound again
     let cur_char_type_is_word = !cur_char_type_is_word;
-    while (direction_left && cursor > 0 && cur_char_type_is_word == is_word_char(text[cursor - 1]))
+    while (direction_left && cursor > 0 && cur_char_type_is_word == is_word_char(cursor - 1))
         || (direction_right
-            && cursor < text.len()
-            && cur_char_type_is_word == is_word_char(text[cursor]))
+            && cursor < graphemes.len()
+            && cur_char_type_is_word == is_word_char(cursor))
     {
         if direction_left {
             cursor -= 1;
@@ -262,13 +759,106 @@ fn get_next_word_bound(text: &str, mut cursor: usize, direction_right: bool) ->
         }
     }
 
-    cursor
+    offset(cursor)
 }
 
 fn is_word_char(c: char) -> bool {
     c.is_alphanumeric() || c == '#'
 }
 
+/// Byte ranges of the lines `text` shows as, without their `\n`, where lines wider than
+/// `width` terminal cells wrap after the last whitespace that fits, or mid-word if there is none
+///
+/// A `width` of 0 doesn't wrap at all.
+pub fn wrap_lines(text: &str, width: usize) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+
+    let mut start = 0;
+    for line in text.split('\n') {
+        let end = start + line.len();
+
+        let mut line_start = start;
+        loop {
+            let rest = &text[line_start..end];
+            let Some(cut) = overflow(rest, width) else {
+                lines.push(line_start..end);
+                break;
+            };
+
+            let cut = rest[..cut]
+                .grapheme_indices(true)
+                .rev()
+                .find(|(_, grapheme)| grapheme.chars().all(char::is_whitespace))
+                .map_or(cut, |(i, grapheme)| i + grapheme.len());
+            lines.push(line_start..line_start + cut);
+            line_start += cut;
+        }
+
+        start = end + 1;
+    }
+
+    lines
+}
+
+/// Where `line` stops fitting into `width` cells, if it doesn't, after at least one grapheme so
+/// that one wider than `width` still gets a line of its own
+fn overflow(line: &str, width: usize) -> Option<usize> {
+    if width == 0 {
+        return None;
+    }
+
+    let mut used = 0;
+    for (offset, grapheme) in line.grapheme_indices(true) {
+        used += grapheme.width();
+        if used > width {
+            return match offset {
+                0 if grapheme.len() < line.len() => Some(grapheme.len()),
+                0 => None,
+                _ => Some(offset),
+            };
+        }
+    }
+    None
+}
+
+/// Index of the line the cursor shows on, which is the next line when it's right where a line
+/// wraps
+fn line_index(lines: &[Range<usize>], position: usize) -> usize {
+    lines
+        .iter()
+        .rposition(|line| line.start <= position)
+        .unwrap_or_default()
+}
+
+/// Whether the line continues on the next one, rather than ending with a `\n` or the text
+fn is_wrapped(lines: &[Range<usize>], index: usize) -> bool {
+    lines
+        .get(index + 1)
+        .is_some_and(|next| next.start == lines[index].end)
+}
+
+/// Position of the grapheme covering `column` in a line, or the line's end if it's narrower
+fn position_in_line(text: &str, lines: &[Range<usize>], index: usize, column: usize) -> usize {
+    let line = &lines[index];
+
+    let mut used = 0;
+    let mut last = line.start;
+    for (offset, grapheme) in text[line.clone()].grapheme_indices(true) {
+        used += grapheme.width();
+        if used > column {
+            return line.start + offset;
+        }
+        last = line.start + offset;
+    }
+
+    // the end of a wrapped line is where the next one starts, so stop short of it
+    if is_wrapped(lines, index) {
+        last
+    } else {
+        line.end
+    }
+}
+
 impl Widget for &TextBox {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let text_box = Paragraph::new(self.text.clone())
@@ -288,44 +878,281 @@ impl Widget for &InteractiveTextBox {
         let selection_style = Style::default().bg(Color::LightGreen);
         let selection_cursor_style = Style::default().bg(Color::Yellow);
 
-        let selection_range = self.cursor.selection_anchor.map(|anchor| {
-            (
-                min(anchor, self.cursor.position),
-                max(anchor, self.cursor.position),
-            )
-        });
-
-        let mut spans = Vec::new();
-        for (i, c) in self.text_box.text.chars().enumerate() {
-            if i == self.cursor.position {
-                let style = if let Some((selection_left, _)) = selection_range
-                    && i == selection_left
-                {
-                    selection_cursor_style
-                } else {
-                    cursor_style
-                };
-                spans.push(Span::styled(c.to_string(), style));
-            } else if let Some((selection_left, selection_right)) = selection_range
-                && i >= selection_left
-                && i < selection_right
-            {
-                spans.push(Span::styled(c.to_string(), selection_style));
-            } else {
-                spans.push(Span::raw(c.to_string()));
+        let mut block = Block::bordered()
+            .title(self.text_box.title.clone())
+            .border_style(self.text_box.border_color);
+        if let Some(vim) = &self.vim {
+            block = block.title_bottom(vim.mode_line());
+        }
+        let inner = block.inner(area);
+
+        // one column to spare for the cursor at the end of a full line
+        let width = usize::from(inner.width.saturating_sub(1));
+        let height = usize::from(inner.height);
+        self.viewport.set((width, height));
+
+        let text = &self.text_box.text;
+        let position = self.cursor.position;
+        let selection_range = self
+            .cursor
+            .selection_anchor
+            .map(|anchor| (min(anchor, position), max(anchor, position)));
+
+        let wrapped = wrap_lines(text, width);
+        let cursor_line = line_index(&wrapped, position);
+
+        // scroll just enough to keep the cursor in view
+        let mut scroll = self.scroll.get();
+        if cursor_line < scroll {
+            scroll = cursor_line;
+        } else if height > 0 && cursor_line >= scroll + height {
+            scroll = cursor_line + 1 - height;
+        }
+        self.scroll.set(scroll);
+
+        let lines = wrapped
+            .iter()
+            .enumerate()
+            .skip(scroll)
+            .take(height)
+            .map(|(index, line)| {
+                let mut spans = Vec::new();
+                for (offset, grapheme) in text[line.clone()].grapheme_indices(true) {
+                    let i = line.start + offset;
+                    if i == position {
+                        let style = if let Some((selection_left, _)) = selection_range
+                            && i == selection_left
+                        {
+                            selection_cursor_style
+                        } else {
+                            cursor_style
+                        };
+                        spans.push(Span::styled(grapheme.to_string(), style));
+                    } else if let Some((selection_left, selection_right)) = selection_range
+                        && i >= selection_left
+                        && i < selection_right
+                    {
+                        spans.push(Span::styled(grapheme.to_string(), selection_style));
+                    } else {
+                        spans.push(Span::raw(grapheme.to_string()));
+                    }
+                }
+
+                // render cursor if it's at the end of the line, on the newline or the text's end
+                if position == line.end && !is_wrapped(&wrapped, index) {
+                    spans.push(Span::styled(" ", cursor_style));
+                }
+
+                Line::from(spans)
+            })
+            .collect::<Vec<_>>();
+
+        Paragraph::new(lines).block(block).render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use proptest::prelude::*;
+    use ratatui::prelude::{Buffer, Rect, Widget};
+    use unicode_segmentation::UnicodeSegmentation;
+    use unicode_width::UnicodeWidthStr;
+
+    use super::{InteractiveTextBox, wrap_lines};
+    use crate::keymap::Action;
+
+    /// Characters that trip up byte or char arithmetic: multi-byte, wide, combining, joining and
+    /// regional indicators, which pair up into flags
+    fn tricky_char() -> impl Strategy<Value = char> {
+        prop_oneof![
+            Just('a'),
+            Just('#'),
+            Just(' '),
+            Just('\n'),
+            Just('é'),
+            Just('\u{301}'),
+            Just('漢'),
+            Just('👍'),
+            Just('\u{200d}'),
+            Just('🇦'),
+        ]
+    }
+
+    fn tricky_text() -> impl Strategy<Value = String> {
+        prop::collection::vec(tricky_char(), 0..40).prop_map(String::from_iter)
+    }
+
+    /// Any editing key, the keymap's actions aside
+    fn key() -> impl Strategy<Value = KeyEvent> {
+        let code = prop_oneof![
+            4 => tricky_char().prop_map(KeyCode::Char),
+            1 => Just(KeyCode::Backspace),
+            1 => Just(KeyCode::Delete),
+            1 => Just(KeyCode::Left),
+            1 => Just(KeyCode::Right),
+            1 => Just(KeyCode::Up),
+            1 => Just(KeyCode::Down),
+            1 => Just(KeyCode::Home),
+            1 => Just(KeyCode::End),
+            1 => Just(KeyCode::PageUp),
+            1 => Just(KeyCode::PageDown),
+            1 => Just(KeyCode::Enter),
+        ];
+        let modifiers = prop_oneof![
+            Just(KeyModifiers::NONE),
+            Just(KeyModifiers::SHIFT),
+            Just(KeyModifiers::CONTROL),
+            Just(KeyModifiers::CONTROL | KeyModifiers::SHIFT),
+        ];
+        (code, modifiers).prop_map(|(code, modifiers)| KeyEvent::new(code, modifiers))
+    }
+
+    fn is_grapheme_boundary(text: &str, position: usize) -> bool {
+        position == text.len() || text.grapheme_indices(true).any(|(i, _)| i == position)
+    }
+
+    impl InteractiveTextBox {
+        /// Undoes or redoes, returning whether the text or cursor changed
+        fn history_step(&mut self, action: Action) -> bool {
+            let before = (
+                self.text_box.text.clone(),
+                self.cursor.position,
+                self.cursor.selection_anchor,
+            );
+            self.run_action(action);
+            before
+                != (
+                    self.text_box.text.clone(),
+                    self.cursor.position,
+                    self.cursor.selection_anchor,
+                )
+        }
+    }
+
+    fn type_text(text_box: &mut InteractiveTextBox, text: &str) {
+        for c in text.chars() {
+            text_box.handle_key_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn edits_keep_the_cursor_on_graphemes(
+            keys in prop::collection::vec(key(), 0..80),
+            width in 3u16..16,
+            height in 3u16..8,
+        ) {
+            let mut text_box = InteractiveTextBox::default().multiline(true);
+            let area = Rect::new(0, 0, width, height);
+            let mut buffer = Buffer::empty(area);
+
+            for key in keys {
+                text_box.handle_key_event(key);
+                // rendering sets the width lines wrap at for the next moves
+                (&text_box).render(area, &mut buffer);
+
+                let text = &text_box.text_box.text;
+                let cursor = text_box.cursor;
+                prop_assert!(is_grapheme_boundary(text, cursor.position), "{text:?} {cursor:?}");
+                if let Some(anchor) = cursor.selection_anchor {
+                    prop_assert!(is_grapheme_boundary(text, anchor), "{text:?} {cursor:?}");
+                }
             }
         }
 
-        // render cursor if it's at the end of the text
-        if self.cursor.position == self.text_box.text.len() {
-            spans.push(Span::styled(" ", cursor_style));
+        #[test]
+        fn typing_then_deleting_round_trips(text in tricky_text()) {
+            let mut text_box = InteractiveTextBox::default().multiline(true);
+            type_text(&mut text_box, &text);
+            prop_assert_eq!(&text_box.text_box.text, &text);
+            prop_assert_eq!(text_box.cursor.position, text.len());
+
+            // one backspace per grapheme
+            for _ in text.graphemes(true) {
+                text_box.handle_key_event(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+            }
+            prop_assert_eq!(text_box.text_box.text.as_str(), "");
+            prop_assert_eq!(text_box.cursor.position, 0);
         }
 
-        let text_box = Paragraph::new(Line::from(spans)).block(
-            Block::bordered()
-                .title(self.text_box.title.clone())
-                .border_style(self.text_box.border_color),
-        );
-        text_box.render(area, buf);
+        #[test]
+        fn arrows_step_over_whole_graphemes(text in tricky_text()) {
+            let mut text_box = InteractiveTextBox::default();
+            type_text(&mut text_box, &text);
+
+            let boundaries = text
+                .grapheme_indices(true)
+                .map(|(i, _)| i)
+                .chain([text.len()])
+                .collect::<Vec<_>>();
+            for &boundary in boundaries.iter().rev().skip(1) {
+                text_box.handle_key_event(KeyEvent::new(KeyCode::Left, KeyModifiers::NONE));
+                prop_assert_eq!(text_box.cursor.position, boundary);
+            }
+            for &boundary in boundaries.iter().skip(1) {
+                text_box.handle_key_event(KeyEvent::new(KeyCode::Right, KeyModifiers::NONE));
+                prop_assert_eq!(text_box.cursor.position, boundary);
+            }
+        }
+
+        #[test]
+        fn undo_and_redo_walk_the_whole_history(keys in prop::collection::vec(key(), 0..80)) {
+            let mut text_box = InteractiveTextBox::default().multiline(true);
+            for key in keys {
+                text_box.handle_key_event(key);
+            }
+            let text = text_box.text_box.text.clone();
+            let cursor = text_box.cursor;
+
+            while text_box.history_step(Action::Undo) {}
+            prop_assert_eq!(text_box.text_box.text.as_str(), "");
+
+            while text_box.history_step(Action::Redo) {}
+            prop_assert_eq!(&text_box.text_box.text, &text);
+            prop_assert_eq!(text_box.cursor.position, cursor.position);
+            prop_assert_eq!(text_box.cursor.selection_anchor, cursor.selection_anchor);
+        }
+
+        #[test]
+        fn typing_undoes_in_one_step(text in "[a-z ]{1,20}") {
+            let mut text_box = InteractiveTextBox::default();
+            type_text(&mut text_box, "x");
+            text_box.handle_key_event(KeyEvent::new(KeyCode::Left, KeyModifiers::NONE));
+            type_text(&mut text_box, &text);
+
+            // the move ended the first run
+            text_box.run_action(Action::Undo);
+            prop_assert_eq!(text_box.text_box.text.as_str(), "x");
+            prop_assert_eq!(text_box.cursor.position, 0);
+            text_box.run_action(Action::Undo);
+            prop_assert_eq!(text_box.text_box.text.as_str(), "");
+        }
+
+        #[test]
+        fn wrapped_lines_cover_the_text(text in tricky_text(), width in 1usize..12) {
+            let lines = wrap_lines(&text, width);
+
+            prop_assert_eq!(lines.first().map(|line| line.start), Some(0));
+            prop_assert_eq!(lines.last().map(|line| line.end), Some(text.len()));
+            for pair in lines.windows(2) {
+                // either wrapped, or split at a newline
+                let (line, next) = (&pair[0], &pair[1]);
+                prop_assert!(
+                    next.start == line.end
+                        || (next.start == line.end + 1 && &text[line.end..next.start] == "\n"),
+                    "{text:?} {lines:?}"
+                );
+            }
+            for line in &lines {
+                let line_text = &text[line.clone()];
+                prop_assert!(!line_text.contains('\n'));
+                // only a single grapheme wider than the box may overflow it
+                prop_assert!(
+                    line_text.width() <= width || line_text.graphemes(true).count() == 1,
+                    "{text:?} {lines:?}"
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,738 @@
+use std::{
+    collections::HashMap,
+    ops::Range,
+    sync::{LazyLock, Mutex},
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    clipboard,
+    text_box::{next_grapheme, previous_grapheme},
+};
+
+// counts beyond this are taken as this, a motion repeated more often has long stopped moving
+const MAX_COUNT: usize = 10_000;
+
+// named registers, shared by every text box like the clipboard is
+static REGISTERS: LazyLock<Mutex<HashMap<char, Register>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Mode {
+    Normal,
+    /// Keys type as they do without vim, which boxes start out in
+    #[default]
+    Insert,
+    /// Motions extend a selection that includes the grapheme under the cursor
+    Visual,
+}
+
+/// Vim-style modal editing for a text box, turned on with `--vim`
+#[derive(Debug, Clone, Default)]
+pub struct Vim {
+    pub mode: Mode,
+    /// Keys of a command still being typed, e.g. `2d` waiting for its motion
+    pub pending: String,
+    /// Where visual mode started, the other end of the selection from the cursor
+    pub visual_anchor: usize,
+}
+
+impl Vim {
+    /// Takes a key in normal or visual mode, returning the command once it's complete
+    pub fn push_key(&mut self, key: char) -> Option<Command> {
+        self.pending.push(key);
+        match parse(&self.pending, self.mode == Mode::Visual) {
+            Parsed::Incomplete => None,
+            Parsed::Invalid => {
+                self.pending.clear();
+                None
+            }
+            Parsed::Done(command) => {
+                self.pending.clear();
+                Some(command)
+            }
+        }
+    }
+
+    /// `-- NORMAL -- 2d` for the bottom of the box
+    pub fn mode_line(&self) -> String {
+        let mode = match self.mode {
+            Mode::Normal => "NORMAL",
+            Mode::Insert => "INSERT",
+            Mode::Visual => "VISUAL",
+        };
+        if self.pending.is_empty() {
+            format!("-- {mode} --")
+        } else {
+            format!("-- {mode} -- {}", self.pending)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Command {
+    /// `"a`, where the command yanks to or puts from, the clipboard if none
+    pub register: Option<char>,
+    pub count: Option<usize>,
+    pub action: Action,
+}
+
+impl Command {
+    pub fn times(&self) -> usize {
+        self.count.unwrap_or(1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Move(Motion),
+    Operate(Operator, Motion),
+    /// `dd`, `cc` and `yy`
+    OperateLines(Operator),
+    /// An operator in visual mode, on what's selected
+    OperateSelection(Operator),
+    /// `x`
+    DeleteChar,
+    /// `p`, or `P` before the cursor
+    Put {
+        before: bool,
+    },
+    Insert(InsertAt),
+    /// `v`, into visual mode and back out
+    Visual,
+    Undo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+/// Where `i`, `a`, `I`, `A`, `o` and `O` start inserting
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InsertAt {
+    Cursor,
+    AfterCursor,
+    LineStart,
+    LineEnd,
+    LineBelow,
+    LineAbove,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    /// `w`
+    WordStart,
+    /// `e`
+    WordEnd,
+    /// `b`
+    WordBack,
+    /// `0`
+    LineStart,
+    /// `$`
+    LineEnd,
+    /// `gg`, or the line counted
+    FirstLine,
+    /// `G`, or the line counted
+    LastLine,
+}
+
+enum Parsed {
+    Incomplete,
+    Invalid,
+    Done(Command),
+}
+
+/// `[count]["x][count]operator[count]motion`, `["x][count]operator operator` or `["x][count]command`
+fn parse(keys: &str, visual: bool) -> Parsed {
+    let mut keys = keys.chars().peekable();
+
+    // a count can come before the register too, `2"ayy`
+    let mut count = parse_count(&mut keys);
+    let mut register = None;
+    if keys.next_if_eq(&'"').is_some() {
+        match keys.next() {
+            None => return Parsed::Incomplete,
+            Some(name @ ('a'..='z' | '"' | '+' | '*')) => register = Some(name),
+            Some(_) => return Parsed::Invalid,
+        }
+        count = multiply(count, parse_count(&mut keys));
+    }
+    let Some(key) = keys.next() else {
+        return Parsed::Incomplete;
+    };
+    let done = |count, action| {
+        Parsed::Done(Command {
+            register,
+            count,
+            action,
+        })
+    };
+
+    let operator = match key {
+        'd' | 'x' if visual => Some(Operator::Delete),
+        'c' | 's' if visual => Some(Operator::Change),
+        'd' => Some(Operator::Delete),
+        'c' => Some(Operator::Change),
+        'y' => Some(Operator::Yank),
+        _ => None,
+    };
+    if let Some(operator) = operator {
+        if visual {
+            return done(count, Action::OperateSelection(operator));
+        }
+
+        count = multiply(count, parse_count(&mut keys));
+        return match keys.next() {
+            None => Parsed::Incomplete,
+            Some(again) if again == key => done(count, Action::OperateLines(operator)),
+            Some(key) => match parse_motion(key, &mut keys) {
+                Some(Ok(motion)) => done(count, Action::Operate(operator, motion)),
+                Some(Err(())) => Parsed::Invalid,
+                None => Parsed::Incomplete,
+            },
+        };
+    }
+
+    let action = match key {
+        'v' => Action::Visual,
+        // visual mode only moves, or operates on what it selected
+        key if visual => match parse_motion(key, &mut keys) {
+            Some(Ok(motion)) => Action::Move(motion),
+            Some(Err(())) => return Parsed::Invalid,
+            None => return Parsed::Incomplete,
+        },
+        'D' => Action::Operate(Operator::Delete, Motion::LineEnd),
+        'C' => Action::Operate(Operator::Change, Motion::LineEnd),
+        'x' => Action::DeleteChar,
+        'p' => Action::Put { before: false },
+        'P' => Action::Put { before: true },
+        'i' => Action::Insert(InsertAt::Cursor),
+        'a' => Action::Insert(InsertAt::AfterCursor),
+        'I' => Action::Insert(InsertAt::LineStart),
+        'A' => Action::Insert(InsertAt::LineEnd),
+        'o' => Action::Insert(InsertAt::LineBelow),
+        'O' => Action::Insert(InsertAt::LineAbove),
+        'u' => Action::Undo,
+        key => match parse_motion(key, &mut keys) {
+            Some(Ok(motion)) => Action::Move(motion),
+            Some(Err(())) => return Parsed::Invalid,
+            None => return Parsed::Incomplete,
+        },
+    };
+    done(count, action)
+}
+
+/// A count doesn't start with 0, which is a motion on its own
+fn parse_count(keys: &mut std::iter::Peekable<std::str::Chars>) -> Option<usize> {
+    let mut count = None::<usize>;
+    while let Some(digit) = keys.next_if(|key| match count {
+        None => matches!(key, '1'..='9'),
+        Some(_) => key.is_ascii_digit(),
+    }) {
+        let digit = digit.to_digit(10).unwrap_or_default() as usize;
+        count = Some((count.unwrap_or(0) * 10 + digit).min(MAX_COUNT));
+    }
+    count
+}
+
+/// `2d3w` deletes 6 words
+fn multiply(count: Option<usize>, second: Option<usize>) -> Option<usize> {
+    match (count, second) {
+        (Some(count), Some(second)) => Some((count * second).min(MAX_COUNT)),
+        (count, second) => count.or(second),
+    }
+}
+
+/// `None` until `gg` has its second `g`
+fn parse_motion(
+    key: char,
+    keys: &mut std::iter::Peekable<std::str::Chars>,
+) -> Option<Result<Motion, ()>> {
+    let motion = match key {
+        'h' => Motion::Left,
+        'l' => Motion::Right,
+        'k' => Motion::Up,
+        'j' => Motion::Down,
+        'w' => Motion::WordStart,
+        'e' => Motion::WordEnd,
+        'b' => Motion::WordBack,
+        '0' => Motion::LineStart,
+        '$' => Motion::LineEnd,
+        'G' => Motion::LastLine,
+        'g' => match keys.next() {
+            None => return None,
+            Some('g') => Motion::FirstLine,
+            Some(_) => return Some(Err(())),
+        },
+        _ => return Some(Err(())),
+    };
+    Some(Ok(motion))
+}
+
+impl Motion {
+    /// Operators on these take whole lines
+    pub fn is_linewise(self) -> bool {
+        matches!(
+            self,
+            Self::Up | Self::Down | Self::FirstLine | Self::LastLine
+        )
+    }
+
+    /// Where the motion takes the cursor, `count` times over, or to the line counted for `gg`
+    /// and `G`
+    pub fn target(self, text: &str, position: usize, count: Option<usize>) -> usize {
+        let times = count.unwrap_or(1);
+        let line = line_start(text, position);
+        match self {
+            Self::Left => (0..times).fold(position, |position, _| {
+                previous_grapheme(text, position).max(line)
+            }),
+            Self::Right => {
+                let end = line_end(text, position);
+                (0..times).fold(position, |position, _| {
+                    next_grapheme(text, position).min(end)
+                })
+            }
+            Self::Up | Self::Down => {
+                let starts = line_starts(text);
+                let current = line_number(&starts, position);
+                let target = if self == Self::Down {
+                    (current + times).min(starts.len() - 1)
+                } else {
+                    current.saturating_sub(times)
+                };
+                let column = text[line..position].graphemes(true).count();
+                let start = starts[target];
+                let end = line_end(text, start);
+                text[start..end]
+                    .grapheme_indices(true)
+                    .nth(column)
+                    .map_or(end, |(offset, _)| start + offset)
+            }
+            Self::WordStart => (0..times).fold(position, |position, _| word_start(text, position)),
+            Self::WordEnd => (0..times).fold(position, |position, _| word_end(text, position)),
+            Self::WordBack => (0..times).fold(position, |position, _| word_back(text, position)),
+            Self::LineStart => line,
+            Self::LineEnd => line_end(text, position),
+            Self::FirstLine | Self::LastLine => {
+                let starts = line_starts(text);
+                let last = starts.len() - 1;
+                let target = match (count, self) {
+                    (Some(count), _) => (count - 1).min(last),
+                    (None, Self::FirstLine) => 0,
+                    (None, _) => last,
+                };
+                first_non_blank(text, starts[target])
+            }
+        }
+    }
+
+    /// What an operator with this motion acts on
+    pub fn range(
+        self,
+        text: &str,
+        position: usize,
+        count: Option<usize>,
+        operator: Operator,
+    ) -> Range<usize> {
+        // `cw` changes the word, not the blanks after it, like `ce` does
+        let change_word = self == Self::WordStart
+            && operator == Operator::Change
+            && position < text.len()
+            && !is_blank(text, position);
+        let (motion, target) = if change_word {
+            let end = (1..count.unwrap_or(1)).fold(current_word_end(text, position), |end, _| {
+                word_end(text, end)
+            });
+            (Self::WordEnd, end)
+        } else {
+            (self, self.target(text, position, count))
+        };
+
+        if motion.is_linewise() {
+            let lines = min_max(position, target);
+            return lines_range(text, lines.start, lines.end);
+        }
+
+        let mut range = min_max(position, target);
+        if motion == Self::WordEnd {
+            range.end = next_grapheme(text, range.end);
+        }
+        // the last word on a line doesn't take the line break along
+        if motion == Self::WordStart
+            && let Some(newline) = text[range.clone()].find('\n')
+            && newline > 0
+        {
+            range.end = range.start + newline;
+        }
+        range
+    }
+}
+
+/// The lines from the one `first` is on to the one `last` is on, without the line break after
+/// the last
+pub fn lines_range(text: &str, first: usize, last: usize) -> Range<usize> {
+    line_start(text, first)..line_end(text, last)
+}
+
+/// `count` lines down from the one `position` is on, or as many as there are
+pub fn count_lines(text: &str, position: usize, count: usize) -> usize {
+    let starts = line_starts(text);
+    let last = (line_number(&starts, position) + count.max(1) - 1).min(starts.len() - 1);
+    starts[last]
+}
+
+/// Where normal mode may have its cursor, on a grapheme rather than past a line's end
+pub fn normal_position(text: &str, position: usize) -> usize {
+    let start = line_start(text, position);
+    let end = line_end(text, position);
+    if position >= end && end > start {
+        previous_grapheme(text, end)
+    } else {
+        position
+    }
+}
+
+pub fn line_start(text: &str, position: usize) -> usize {
+    text[..position]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1)
+}
+
+pub fn line_end(text: &str, position: usize) -> usize {
+    text[position..]
+        .find('\n')
+        .map_or(text.len(), |newline| position + newline)
+}
+
+pub fn first_non_blank(text: &str, position: usize) -> usize {
+    let start = line_start(text, position);
+    let end = line_end(text, position);
+    text[start..end]
+        .find(|c: char| !c.is_whitespace())
+        .map_or(end, |offset| start + offset)
+}
+
+fn line_starts(text: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(newline, _)| newline + 1))
+        .collect()
+}
+
+fn line_number(starts: &[usize], position: usize) -> usize {
+    starts.partition_point(|&start| start <= position) - 1
+}
+
+fn min_max(a: usize, b: usize) -> Range<usize> {
+    a.min(b)..a.max(b)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Class {
+    Blank,
+    Word,
+    Punctuation,
+}
+
+fn class(grapheme: &str) -> Class {
+    match grapheme.chars().next() {
+        Some(c) if c.is_whitespace() => Class::Blank,
+        Some(c) if c.is_alphanumeric() || c == '_' => Class::Word,
+        _ => Class::Punctuation,
+    }
+}
+
+fn is_blank(text: &str, position: usize) -> bool {
+    text[position..]
+        .graphemes(true)
+        .next()
+        .is_some_and(|grapheme| class(grapheme) == Class::Blank)
+}
+
+/// The graphemes' offsets and classes, for the word motions to walk over
+fn classes(text: &str) -> Vec<(usize, Class)> {
+    text.grapheme_indices(true)
+        .map(|(offset, grapheme)| (offset, class(grapheme)))
+        .collect()
+}
+
+fn index_of(graphemes: &[(usize, Class)], position: usize) -> usize {
+    graphemes.partition_point(|&(offset, _)| offset < position)
+}
+
+/// `w`, past the word and the blanks after it
+fn word_start(text: &str, position: usize) -> usize {
+    let graphemes = classes(text);
+    let mut i = index_of(&graphemes, position);
+    if let Some(&(_, start)) = graphemes.get(i)
+        && start != Class::Blank
+    {
+        while graphemes.get(i).is_some_and(|&(_, class)| class == start) {
+            i += 1;
+        }
+    }
+    while graphemes
+        .get(i)
+        .is_some_and(|&(_, class)| class == Class::Blank)
+    {
+        i += 1;
+    }
+    graphemes.get(i).map_or(text.len(), |&(offset, _)| offset)
+}
+
+/// `e`, to the last grapheme of this word, or the next one if already there
+fn word_end(text: &str, position: usize) -> usize {
+    let graphemes = classes(text);
+    let mut i = index_of(&graphemes, position) + 1;
+    while graphemes
+        .get(i)
+        .is_some_and(|&(_, class)| class == Class::Blank)
+    {
+        i += 1;
+    }
+    let Some(&(_, word)) = graphemes.get(i) else {
+        return graphemes
+            .last()
+            .map_or(0, |&(offset, _)| offset)
+            .max(position);
+    };
+    while graphemes
+        .get(i + 1)
+        .is_some_and(|&(_, class)| class == word)
+    {
+        i += 1;
+    }
+    graphemes[i].0
+}
+
+/// The last grapheme of the word `position` is in
+fn current_word_end(text: &str, position: usize) -> usize {
+    let graphemes = classes(text);
+    let mut i = index_of(&graphemes, position);
+    while graphemes
+        .get(i + 1)
+        .is_some_and(|&(_, class)| class == graphemes[i].1)
+    {
+        i += 1;
+    }
+    graphemes.get(i).map_or(position, |&(offset, _)| offset)
+}
+
+/// `b`, to the first grapheme of this word, or the one before if already there
+fn word_back(text: &str, position: usize) -> usize {
+    let graphemes = classes(text);
+    let mut i = index_of(&graphemes, position);
+    if i == 0 {
+        return 0;
+    }
+    i -= 1;
+    while i > 0 && graphemes[i].1 == Class::Blank {
+        i -= 1;
+    }
+    let word = graphemes[i].1;
+    while i > 0 && graphemes[i - 1].1 == word {
+        i -= 1;
+    }
+    graphemes[i].0
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Register {
+    pub text: String,
+    /// Whole lines, put below or above the cursor's line rather than into it
+    pub linewise: bool,
+}
+
+/// `a` to `z` are kept here, anything else is the system clipboard
+pub fn read_register(name: Option<char>) -> Register {
+    let registers = REGISTERS.lock().expect("failed to lock registers");
+    match name {
+        Some(name @ 'a'..='z') => registers.get(&name).cloned().unwrap_or_default(),
+        _ => {
+            let text = clipboard::get_text();
+            // lines as long as the clipboard still holds what was yanked as lines
+            let linewise = registers
+                .get(&'"')
+                .is_some_and(|register| register.linewise && register.text == text);
+            Register { text, linewise }
+        }
+    }
+}
+
+pub fn write_register(name: Option<char>, register: Register) {
+    let mut registers = REGISTERS.lock().expect("failed to lock registers");
+    match name {
+        Some(name @ 'a'..='z') => {
+            registers.insert(name, register);
+        }
+        _ => {
+            clipboard::set_text(register.text.clone());
+            registers.insert('"', register);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::Mode;
+    use crate::text_box::InteractiveTextBox;
+
+    /// A box in normal mode on `text`, the cursor at its start
+    fn normal(text: &str) -> InteractiveTextBox {
+        let mut text_box = InteractiveTextBox::default().multiline(true).vim(true);
+        text_box.text_box.text = text.to_string();
+        press(&mut text_box, "\x1b0gg");
+        text_box
+    }
+
+    /// Presses each key, `\x1b` being esc; the tests only use named registers, leaving the
+    /// system clipboard alone
+    fn press(text_box: &mut InteractiveTextBox, keys: &str) {
+        for key in keys.chars() {
+            let code = match key {
+                '\x1b' => KeyCode::Esc,
+                key => KeyCode::Char(key),
+            };
+            text_box.handle_key_event(KeyEvent::new(code, KeyModifiers::NONE));
+        }
+    }
+
+    fn text(text_box: &InteractiveTextBox) -> &str {
+        &text_box.text_box.text
+    }
+
+    #[test]
+    fn word_motions_stop_at_word_and_punctuation_bounds() {
+        let mut text_box = normal("one two_three, four\nfive");
+        let mut positions = Vec::new();
+        for key in ["w", "w", "w", "w", "e", "b", "b", "2e", "0", "$"] {
+            press(&mut text_box, key);
+            positions.push(text_box.cursor.position);
+        }
+        assert_eq!(positions, [4, 13, 15, 20, 23, 20, 15, 23, 20, 23]);
+    }
+
+    #[test]
+    fn lines_are_counted_for_gg_and_g() {
+        let mut text_box = normal("a\n  b\nc\nd");
+        press(&mut text_box, "G");
+        assert_eq!(text_box.cursor.position, 8);
+        press(&mut text_box, "2gg");
+        assert_eq!(text_box.cursor.position, 4);
+        press(&mut text_box, "gg");
+        assert_eq!(text_box.cursor.position, 0);
+        press(&mut text_box, "3G");
+        assert_eq!(text_box.cursor.position, 6);
+    }
+
+    #[test]
+    fn operators_take_counts_before_and_after() {
+        let mut text_box = normal("one two three four five six seven");
+        press(&mut text_box, "\"ad2w");
+        assert_eq!(text(&text_box), "three four five six seven");
+        press(&mut text_box, "2\"ad2w");
+        assert_eq!(text(&text_box), "seven");
+
+        let mut text_box = normal("one two three");
+        press(&mut text_box, "w\"a3x");
+        assert_eq!(text(&text_box), "one  three");
+        press(&mut text_box, "\"aD");
+        assert_eq!(text(&text_box), "one ");
+    }
+
+    #[test]
+    fn change_word_keeps_the_space_after_it() {
+        let mut text_box = normal("foo bar baz");
+        press(&mut text_box, "\"acwqux\x1b");
+        assert_eq!(text(&text_box), "qux bar baz");
+        assert_eq!(
+            text_box.vim.as_ref().map(|vim| vim.mode),
+            Some(Mode::Normal)
+        );
+        // back onto the last grapheme typed
+        assert_eq!(text_box.cursor.position, 2);
+
+        press(&mut text_box, "w\"a2cwend\x1b");
+        assert_eq!(text(&text_box), "qux end");
+    }
+
+    #[test]
+    fn deleted_lines_put_back_as_lines() {
+        let mut text_box = normal("a\nb\nc");
+        press(&mut text_box, "\"add\"ap");
+        assert_eq!(text(&text_box), "b\na\nc");
+        assert_eq!(text_box.cursor.position, 2);
+
+        press(&mut text_box, "G\"ayy\"aP");
+        assert_eq!(text(&text_box), "b\na\nc\nc");
+        press(&mut text_box, "gg\"a2dd");
+        assert_eq!(text(&text_box), "c\nc");
+        press(&mut text_box, "G\"ap");
+        assert_eq!(text(&text_box), "c\nc\nb\na");
+
+        // the last line takes the line break before it
+        press(&mut text_box, "G\"bdd");
+        assert_eq!(text(&text_box), "c\nc\nb");
+        assert_eq!(text_box.cursor.position, 4);
+    }
+
+    #[test]
+    fn characters_put_after_the_cursor() {
+        let mut text_box = normal("hello world");
+        press(&mut text_box, "\"aye$\"ap");
+        assert_eq!(text(&text_box), "hello worldhello");
+        assert_eq!(text_box.cursor.position, 15);
+        press(&mut text_box, "0\"a2P");
+        assert_eq!(text(&text_box), "hellohellohello worldhello");
+    }
+
+    #[test]
+    fn visual_mode_selects_the_grapheme_under_the_cursor_too() {
+        let mut text_box = normal("one two three");
+        press(&mut text_box, "wve");
+        assert_eq!(text_box.cursor.selection_anchor, Some(4));
+        press(&mut text_box, "\"ay");
+        assert_eq!(
+            text_box.vim.as_ref().map(|vim| vim.mode),
+            Some(Mode::Normal)
+        );
+        press(&mut text_box, "$\"ap");
+        assert_eq!(text(&text_box), "one two threetwo");
+
+        // backwards, the anchor's grapheme stays selected
+        let mut text_box = normal("one two three");
+        press(&mut text_box, "wvb\"ad");
+        assert_eq!(text(&text_box), "wo three");
+    }
+
+    #[test]
+    fn inserting_opens_lines_and_undoes() {
+        let mut text_box = normal("a\nb");
+        press(&mut text_box, "ox\x1bOy\x1b");
+        assert_eq!(text(&text_box), "a\ny\nx\nb");
+        press(&mut text_box, "u");
+        assert_eq!(text(&text_box), "a\nx\nb");
+        press(&mut text_box, "Aend\x1bIstart \x1b");
+        assert_eq!(text(&text_box), "a\nstart xend\nb");
+    }
+
+    #[test]
+    fn esc_drops_a_half_typed_command() {
+        let mut text_box = normal("one two");
+        press(&mut text_box, "\"a2d");
+        assert!(text_box.takes_esc());
+        press(&mut text_box, "\x1bw");
+        assert_eq!(text(&text_box), "one two");
+        assert_eq!(text_box.cursor.position, 4);
+        assert!(!text_box.takes_esc());
+    }
+}
@@ -0,0 +1,393 @@
+//! Dates written in plain words inside note text
+//!
+//! Recognized phrases, case-insensitively:
+//! - `today`, `tonight`, `tomorrow`, `yesterday`
+//! - ISO dates, `2026-11-01`
+//! - weekdays: `friday` and `next friday` are the coming one (never today), `this friday` may be
+//!   today
+//! - `next week`, `next month`, `next year`
+//! - `in 2 weeks`, `in a month`, `in three days`
+//!
+//! Any of them can be followed by a time, `3pm`, `3:30 pm`, `at 15:00`, `noon` or `midnight`.
+//! Prefixed with `@label:`, e.g. `@due:2026-11-01` or `@call:tomorrow`, the date gets that label.
+
+use std::ops::Range;
+
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+/// A date found in a text
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedDate {
+    /// From an `@label:` prefix, plain phrases have none
+    pub label: Option<String>,
+    pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+    /// Byte range of the whole phrase in the text, label included
+    pub span: Range<usize>,
+}
+
+/// A word of the text, without surrounding punctuation
+#[derive(Debug, Clone)]
+struct Word<'a> {
+    text: &'a str,
+    span: Range<usize>,
+}
+
+const WEEKDAYS: [(&str, &str, Weekday); 7] = [
+    ("monday", "mon", Weekday::Mon),
+    ("tuesday", "tue", Weekday::Tue),
+    ("wednesday", "wed", Weekday::Wed),
+    ("thursday", "thu", Weekday::Thu),
+    ("friday", "fri", Weekday::Fri),
+    ("saturday", "sat", Weekday::Sat),
+    ("sunday", "sun", Weekday::Sun),
+];
+
+const NUMBERS: [&str; 13] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+    "eleven", "twelve",
+];
+
+fn words(text: &str) -> Vec<Word<'_>> {
+    let is_punctuation = |c: char| matches!(c, ',' | '.' | ';' | '!' | '?' | '(' | ')' | '"');
+
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(word_start)) => {
+                start = None;
+                let word = &text[word_start..i];
+                let trimmed_start = word.trim_start_matches(is_punctuation);
+                let trimmed = trimmed_start.trim_end_matches(is_punctuation);
+                if !trimmed.is_empty() {
+                    let offset = word_start + (word.len() - trimmed_start.len());
+                    words.push(Word {
+                        text: trimmed,
+                        span: offset..offset + trimmed.len(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+fn weekday(word: &str, abbreviated: bool) -> Option<Weekday> {
+    WEEKDAYS
+        .iter()
+        .find(|(name, short, _)| word == *name || (abbreviated && word == *short))
+        .map(|(_, _, weekday)| *weekday)
+}
+
+/// Days from `from` to the next `weekday`, 0 to 6
+fn days_until(from: NaiveDate, weekday: Weekday) -> u64 {
+    let days = weekday.num_days_from_monday() as i64 - from.weekday().num_days_from_monday() as i64;
+    days.rem_euclid(7) as u64
+}
+
+fn number(word: &str) -> Option<u32> {
+    match word {
+        "a" | "an" => Some(1),
+        word => word.parse().ok().or_else(|| {
+            NUMBERS
+                .iter()
+                .position(|number| *number == word)
+                .map(|n| n as u32)
+        }),
+    }
+}
+
+/// `3pm`, `3:30pm` or `15:00`, and `3` if `meridiem` (the next word, like `pm`) says so
+fn time(word: &str, meridiem: Option<&str>) -> Option<(NaiveTime, usize)> {
+    match word {
+        "noon" => return Some((NaiveTime::from_hms_opt(12, 0, 0)?, 1)),
+        "midnight" => return Some((NaiveTime::MIN, 1)),
+        _ => {}
+    }
+
+    let (clock, pm, consumed) = if let Some(clock) = word.strip_suffix("am") {
+        (clock, Some(false), 1)
+    } else if let Some(clock) = word.strip_suffix("pm") {
+        (clock, Some(true), 1)
+    } else {
+        match meridiem {
+            Some("am") => (word, Some(false), 2),
+            Some("pm") => (word, Some(true), 2),
+            _ => (word, None, 1),
+        }
+    };
+
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => {
+            (hour.parse::<u32>().ok()?, minute.parse().ok()?)
+        }
+        // a bare number is only a time with am or pm
+        None if pm.is_some() => (clock.parse::<u32>().ok()?, 0),
+        _ => return None,
+    };
+
+    let hour = match pm {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(false) => hour % 12,
+        Some(true) => hour % 12 + 12,
+        None => hour,
+    };
+    Some((NaiveTime::from_hms_opt(hour, minute, 0)?, consumed))
+}
+
+/// The date a phrase starting at `words[0]` means, and how many words it takes
+fn date_phrase(words: &[String], today: NaiveDate) -> Option<(NaiveDate, usize)> {
+    let word = |i: usize| words.get(i).map(String::as_str);
+
+    match word(0)? {
+        "today" | "tonight" => Some((today, 1)),
+        "tomorrow" => Some((today.succ_opt()?, 1)),
+        "yesterday" => Some((today.pred_opt()?, 1)),
+        "this" => {
+            let weekday = weekday(word(1)?, true)?;
+            Some((
+                today.checked_add_days(Days::new(days_until(today, weekday)))?,
+                2,
+            ))
+        }
+        "next" => match word(1)? {
+            "week" => Some((today.checked_add_days(Days::new(7))?, 2)),
+            "month" => Some((today.checked_add_months(Months::new(1))?, 2)),
+            "year" => Some((today.checked_add_months(Months::new(12))?, 2)),
+            next => {
+                let weekday = weekday(next, true)?;
+                let tomorrow = today.succ_opt()?;
+                let date = tomorrow.checked_add_days(Days::new(days_until(tomorrow, weekday)))?;
+                Some((date, 2))
+            }
+        },
+        "in" => {
+            let amount = number(word(1)?)?;
+            let date = match word(2)?.trim_end_matches('s') {
+                "day" => today.checked_add_days(Days::new(amount.into()))?,
+                "week" => today.checked_add_days(Days::new(u64::from(amount) * 7))?,
+                "month" => today.checked_add_months(Months::new(amount))?,
+                "year" => today.checked_add_months(Months::new(amount.checked_mul(12)?))?,
+                _ => return None,
+            };
+            Some((date, 3))
+        }
+        first => {
+            if let Ok(date) = NaiveDate::parse_from_str(first, "%Y-%m-%d") {
+                return Some((date, 1));
+            }
+            let weekday = weekday(first, false)?;
+            let tomorrow = today.succ_opt()?;
+            let date = tomorrow.checked_add_days(Days::new(days_until(tomorrow, weekday)))?;
+            Some((date, 1))
+        }
+    }
+}
+
+/// A date phrase with an optional time after it
+fn phrase(words: &[String], today: NaiveDate) -> Option<(NaiveDate, Option<NaiveTime>, usize)> {
+    let (date, mut consumed) = date_phrase(words, today)?;
+
+    let word = |i: usize| words.get(i).map(String::as_str);
+    let at = usize::from(word(consumed) == Some("at"));
+    let time = word(consumed + at).and_then(|next| time(next, word(consumed + at + 1)));
+    let time = time.map(|(time, time_words)| {
+        consumed += at + time_words;
+        time
+    });
+
+    Some((date, time, consumed))
+}
+
+fn is_label(label: &str) -> bool {
+    !label.is_empty()
+        && label
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Every date phrase in `text`, in order, relative to the writer's local `now`
+pub fn extract_dates(text: &str, now: NaiveDateTime) -> Vec<DetectedDate> {
+    let today = now.date();
+    let words = words(text);
+    let lowercase = words
+        .iter()
+        .map(|word| word.text.to_lowercase())
+        .collect::<Vec<_>>();
+
+    let mut dates = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let labeled = lowercase[i]
+            .strip_prefix('@')
+            .and_then(|labeled| labeled.split_once(':'))
+            .filter(|(label, _)| is_label(label));
+
+        let detected = match labeled {
+            Some((label_lowercase, value)) => {
+                // `@due:friday` holds the first word itself, `@due: friday` starts at the next one
+                let (mut value_words, skipped) = if value.is_empty() {
+                    (Vec::new(), 1)
+                } else {
+                    (vec![value.to_string()], 0)
+                };
+                value_words.extend(lowercase[i + 1..].iter().cloned());
+
+                phrase(&value_words, today).map(|(date, time, consumed)| {
+                    // the label as written, not lowercased
+                    let label = words[i].text[1..].split_once(':').map(|(label, _)| label);
+                    let label = label.unwrap_or(label_lowercase).to_string();
+                    (Some(label), date, time, consumed + skipped)
+                })
+            }
+            None => phrase(&lowercase[i..], today)
+                .map(|(date, time, consumed)| (None, date, time, consumed)),
+        };
+
+        match detected {
+            Some((label, date, time, consumed)) => {
+                let last = &words[i + consumed - 1];
+                dates.push(DetectedDate {
+                    label,
+                    date,
+                    time,
+                    span: words[i].span.start..last.span.end,
+                });
+                i += consumed;
+            }
+            None => i += 1,
+        }
+    }
+
+    dates
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+    use super::extract_dates;
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn time(time: &str) -> Option<NaiveTime> {
+        Some(NaiveTime::parse_from_str(time, "%H:%M").unwrap())
+    }
+
+    /// `text`'s dates at 10:00 on `today`, with their labels
+    fn dates(text: &str, today: &str) -> Vec<(Option<String>, NaiveDate, Option<NaiveTime>)> {
+        let now = NaiveDateTime::new(date(today), NaiveTime::from_hms_opt(10, 0, 0).unwrap());
+        extract_dates(text, now)
+            .into_iter()
+            .map(|detected| (detected.label, detected.date, detected.time))
+            .collect()
+    }
+
+    #[test]
+    fn reads_phrases_relative_to_today() {
+        // a monday
+        let today = "2026-10-19";
+        assert_eq!(dates("tomorrow", today), [(None, date("2026-10-20"), None)]);
+        assert_eq!(
+            dates("next friday 3pm", today),
+            [(None, date("2026-10-23"), time("15:00"))]
+        );
+        assert_eq!(
+            dates("in 2 weeks", today),
+            [(None, date("2026-11-02"), None)]
+        );
+        assert_eq!(
+            dates("@due:2026-11-01", today),
+            [(Some("due".to_string()), date("2026-11-01"), None)]
+        );
+        // never today, unless it says this monday
+        assert_eq!(dates("monday", today), [(None, date("2026-10-26"), None)]);
+        assert_eq!(dates("this mon", today), [(None, date("2026-10-19"), None)]);
+    }
+
+    #[test]
+    fn finds_every_phrase_in_a_text() {
+        let now = NaiveDateTime::new(
+            date("2026-10-19"),
+            NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+        );
+        let text = "Call Mom tomorrow at 3pm. @Due: 2026-11-01, gym this mon and next mon, \
+                    in 2 weeks, in a month noon. This is in the morning; friday 9 am or next week 15:00";
+        let detected = extract_dates(text, now);
+
+        assert_eq!(
+            detected
+                .iter()
+                .map(|detected| (detected.label.as_deref(), detected.date, detected.time))
+                .collect::<Vec<_>>(),
+            [
+                (None, date("2026-10-20"), time("15:00")),
+                (Some("Due"), date("2026-11-01"), None),
+                (None, date("2026-10-19"), None),
+                (None, date("2026-10-26"), None),
+                (None, date("2026-11-02"), None),
+                (None, date("2026-11-19"), time("12:00")),
+                (None, date("2026-10-23"), time("09:00")),
+                (None, date("2026-10-26"), time("15:00")),
+            ]
+        );
+        assert_eq!(&text[detected[0].span.clone()], "tomorrow at 3pm");
+        assert_eq!(&text[detected[1].span.clone()], "@Due: 2026-11-01");
+
+        let detected = extract_dates("@call:friday, at 3 or in the evening", now);
+        assert_eq!(detected.len(), 1);
+        assert_eq!(detected[0].label.as_deref(), Some("call"));
+        assert_eq!(detected[0].date, date("2026-10-23"));
+        assert_eq!(detected[0].time, None);
+    }
+
+    #[test]
+    fn months_and_years_end_on_their_last_day() {
+        assert_eq!(
+            dates("next month", "2026-01-31"),
+            [(None, date("2026-02-28"), None)]
+        );
+        assert_eq!(
+            dates("in 3 months", "2026-11-30"),
+            [(None, date("2027-02-28"), None)]
+        );
+        assert_eq!(
+            dates("next year", "2028-02-29"),
+            [(None, date("2029-02-28"), None)]
+        );
+        assert_eq!(
+            dates("in 2 weeks", "2026-12-25"),
+            [(None, date("2027-01-08"), None)]
+        );
+    }
+
+    #[test]
+    fn overflowing_dates_are_no_dates() {
+        for text in [
+            "in 99999999999 days",
+            "in 4000000000 weeks",
+            "in 4000000000 months",
+            "in 4000000000 years",
+        ] {
+            assert_eq!(dates(text, "2026-10-19"), [], "{text}");
+        }
+
+        // past the last date there is
+        let last = NaiveDate::MAX.format("%Y-%m-%d").to_string();
+        for text in [
+            "tomorrow",
+            "next week",
+            "next month",
+            "next year",
+            "next friday",
+            "friday",
+        ] {
+            assert_eq!(dates(text, &last), [], "{text}");
+        }
+    }
+}
@@ -1,3 +1,4 @@
+pub mod dates;
 pub mod request;
 pub mod response;
 
@@ -1,45 +1,154 @@
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Debug)]
+use crate::response::MarkdownFile;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TestStruct {
     pub field1: String,
     pub field2: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CreateNote {
     pub text: String,
+    /// Derived from the text when unset
+    #[serde(default)]
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    /// Store the date phrases found in the text (see [`crate::dates`]) as note dates
+    #[serde(default)]
+    pub extract_dates: bool,
+    /// IANA name of the writer's timezone, which "tomorrow" and the like are relative to, UTC if
+    /// unset
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+/// Keeps the note's title, dates and links
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UpdateNote {
+    pub id: Uuid,
+    pub text: String,
+    /// Replace the note's tags, which must all exist
     pub tags: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeleteNote {
+    pub id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CreateTag {
     pub name: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GetNotes {
     pub limit: Option<u32>,
     pub offset: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetNoteDetails {
+    pub id: Uuid,
+}
+
+/// Both ends are included
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct DateRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GetNotesFiltered {
     pub search_text: Option<String>,
     pub tags: Option<Vec<String>>,
+    /// Only notes with a date, or an occurrence of a recurring one, in the range
+    pub dates: Option<DateRange>,
+    /// Only notes created on these days, in `timezone`
+    #[serde(default)]
+    pub created: Option<DateRange>,
+    /// IANA name of the timezone the client displays dates in, UTC if unset
+    #[serde(default)]
+    pub timezone: Option<String>,
     pub limit: Option<u32>,
     pub offset: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetOccurrences {
+    pub dates: DateRange,
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportMarkdown {
+    pub files: Vec<MarkdownFile>,
+}
+
+/// One file of an import started with `import_begin`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportFile {
+    pub id: Uuid,
+    pub file: MarkdownFile,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportFinish {
+    pub id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AcknowledgeReminder {
+    pub id: Uuid,
+    /// The occurrence, the reminder's `date`
+    pub date: NaiveDate,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SnoozeReminder {
+    pub id: Uuid,
+    /// The occurrence, the reminder's `date`
+    pub date: NaiveDate,
+    pub minutes: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type", content = "data")]
 #[serde(rename_all = "snake_case")]
 pub enum Message {
+    /// Answered with `note_created`
     CreateNote(CreateNote),
+    /// Answered with `note_updated`
+    UpdateNote(UpdateNote),
+    /// Answered with `note_deleted`
+    DeleteNote(DeleteNote),
     CreateTag(CreateTag),
     GetNotes(GetNotes),
     GetTags,
     GetNotesFiltered(GetNotesFiltered),
+    /// A note with its tags, dates and links
+    GetNoteDetails(GetNoteDetails),
+    /// Every occurrence of every note date in a range, recurring dates expanded
+    GetOccurrences(GetOccurrences),
+    /// Answered with one `markdown_file` per note, then `export_finished`
+    ExportMarkdown,
+    /// Every file at once, answered with `imported`, for archives that fit in one message
+    ImportMarkdown(ImportMarkdown),
+    /// Starts an import sent a file at a time, answered with `import_started` and its id
+    ImportBegin,
+    /// Answered with `import_file_received`
+    ImportFile(ImportFile),
+    /// Imports the files sent so far, answered with `imported`
+    ImportFinish(ImportFinish),
+    /// Reminder requests are all answered with the remaining `reminders`
+    GetReminders,
+    AcknowledgeReminder(AcknowledgeReminder),
+    SnoozeReminder(SnoozeReminder),
     Test(TestStruct),
     Unknown(String),
 }
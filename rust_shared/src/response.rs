@@ -1,4 +1,4 @@
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
@@ -7,7 +7,48 @@ use uuid::Uuid;
 pub struct Note {
     pub id: Uuid,
     pub text: String,
-    pub created_at: NaiveDateTime,
+    /// Given by the writer, see [`Note::display_title`] for notes without one
+    #[serde(default)]
+    pub title: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Longest derived title, in characters
+const MAX_DERIVED_TITLE_LENGTH: usize = 80;
+
+impl Note {
+    /// The note's title, or one derived from its text (see [`derive_title`])
+    pub fn display_title(&self) -> String {
+        match self.title.as_deref().map(str::trim) {
+            Some(title) if !title.is_empty() => title.to_string(),
+            _ => derive_title(&self.text),
+        }
+    }
+}
+
+/// The first non-empty line, without Markdown heading marks, up to the end of its first sentence
+/// and at most [`MAX_DERIVED_TITLE_LENGTH`] characters
+pub fn derive_title(text: &str) -> String {
+    let line = text
+        .lines()
+        .map(|line| line.trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+
+    // a sentence ends at punctuation followed by a space, so "3.5" or "rember.rs" don't end one
+    let sentence = line
+        .char_indices()
+        .find(|(i, c)| matches!(c, '.' | '!' | '?') && line[i + c.len_utf8()..].starts_with(' '))
+        .map_or(line, |(i, c)| &line[..i + c.len_utf8()]);
+
+    if sentence.chars().count() <= MAX_DERIVED_TITLE_LENGTH {
+        return sentence.to_string();
+    }
+    let truncated = sentence
+        .chars()
+        .take(MAX_DERIVED_TITLE_LENGTH - 1)
+        .collect::<String>();
+    format!("{}…", truncated.trim_end())
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, FromRow)]
@@ -16,11 +57,118 @@ pub struct Tag {
     pub name: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, FromRow)]
+pub struct NoteDate {
+    pub id: Uuid,
+    pub note_id: Uuid,
+    pub label: Option<String>,
+    pub date: NaiveDate,
+    /// Recurrence rule, a subset of iCalendar's `RRULE` that the backend understands
+    #[serde(default)]
+    pub rrule: Option<String>,
+}
+
+/// A note along with everything attached to it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NoteWithDetails {
+    pub note: Note,
+    /// By name
+    pub tags: Vec<Tag>,
+    /// By date
+    pub note_dates: Vec<NoteDate>,
+    pub note_links: Vec<Note>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NoteDeleted {
+    pub id: Uuid,
+}
+
+/// One note of a markdown export, named after the note id
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MarkdownFile {
+    pub name: String,
+    pub content: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportFinished {
+    pub count: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportStarted {
+    pub id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportFileReceived {
+    pub id: Uuid,
+    /// Files received so far
+    pub count: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ImportReport {
+    pub created: usize,
+    pub updated: usize,
+    pub linked: usize,
+    /// Links between the imported notes that their files no longer list
+    pub unlinked: usize,
+    /// Files or links that were skipped, and why
+    pub errors: Vec<String>,
+}
+
+/// One occurrence of a note date, recurring dates have one per repetition
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Occurrence {
+    pub note_date_id: Uuid,
+    pub note: Note,
+    pub label: Option<String>,
+    pub date: NaiveDate,
+    pub rrule: Option<String>,
+}
+
+/// An occurrence of a reminder-labeled note date that is due
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Reminder {
+    /// Id of the note date
+    pub id: Uuid,
+    pub note: Note,
+    pub label: Option<String>,
+    /// The occurrence, the note date itself unless it recurs
+    pub date: NaiveDate,
+    pub snoozed_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RateLimited {
+    pub retry_after_ms: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type", content = "data")]
 #[serde(rename_all = "snake_case")]
 pub enum Message {
     Notes(Vec<Note>),
     Tags(Vec<Tag>),
+    NoteDetails(NoteWithDetails),
+    /// Answers `create_note` with the new note
+    NoteCreated(Note),
+    /// Answers `update_note` with the note as it is now
+    NoteUpdated(Note),
+    NoteDeleted(NoteDeleted),
+    MarkdownFile(MarkdownFile),
+    ExportFinished(ExportFinished),
+    ImportStarted(ImportStarted),
+    ImportFileReceived(ImportFileReceived),
+    Imported(ImportReport),
+    /// Ordered by date
+    Occurrences(Vec<Occurrence>),
+    /// Pushed by the server, unprompted, when a reminder comes due
+    Reminder(Reminder),
+    /// Every due reminder that hasn't been acknowledged
+    Reminders(Vec<Reminder>),
+    RateLimited(RateLimited),
     Unknown(String),
 }